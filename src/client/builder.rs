@@ -1,14 +1,16 @@
 use crate::config::{ProviderConfig, RoleConfigLoader};
-use crate::error::AiResult;
+use crate::error::{AiResult, OrionAiReason};
 use crate::provider::{AiProvider, AiProviderType};
 use crate::{AiConfig, AiRouter};
 use log::debug;
+use orion_error::{ToStructError, UvsConfFrom};
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
 
-use super::AiClient;
-use crate::providers::{mock, openai};
+use super::{AiClient, UsageAccountant};
+use crate::providers::replay::{FixtureRecorder, RecordingProvider, ReplayStore};
+use crate::providers::{anthropic, mock, ollama, openai};
 
 use getset::{Getters, MutGetters, Setters, WithSetters};
 #[derive(Clone, Debug, Getters, Setters, WithSetters, MutGetters)]
@@ -18,6 +20,12 @@ pub struct AiClientBuilder {
     config: AiConfig,
     timout: u64,
     role_file: Option<PathBuf>,
+    /// 录制夹具文件，设置后所有 provider 的成功交互都追加到该文件
+    record_file: Option<PathBuf>,
+    /// 回放夹具（文件或目录），设置后所有启用的 provider 都由回放的 MockProvider 代替
+    replay_file: Option<PathBuf>,
+    /// 替换按配置创建的 MockProvider，用于注入脚本化规则
+    mock_provider: Option<Arc<mock::MockProvider>>,
 }
 
 impl AiClientBuilder {
@@ -27,6 +35,9 @@ impl AiClientBuilder {
             config,
             timout: 30,
             role_file: None,
+            record_file: None,
+            replay_file: None,
+            mock_provider: None,
         }
     }
     pub fn with_role(self, role_file: PathBuf) -> Self {
@@ -38,13 +49,36 @@ impl AiClientBuilder {
         let mut providers: HashMap<AiProviderType, Arc<dyn AiProvider>> = HashMap::new();
         // 从配置注册provider
         Self::register_providers_from_config(&mut providers, &self.config.providers, self.timout)?;
+        if let Some(mock_provider) = &self.mock_provider {
+            providers.insert(
+                AiProviderType::Mock,
+                mock_provider.clone() as Arc<dyn AiProvider>,
+            );
+        }
+        if let Some(replay_file) = &self.replay_file {
+            let replay: Arc<dyn AiProvider> = Arc::new(mock::MockProvider::with_replay(Arc::new(
+                ReplayStore::load(replay_file)?,
+            )));
+            for provider in providers.values_mut() {
+                *provider = replay.clone();
+            }
+        } else if let Some(record_file) = &self.record_file {
+            let recorder = FixtureRecorder::open(record_file.clone())?;
+            for provider in providers.values_mut() {
+                *provider = Arc::new(RecordingProvider::new(provider.clone(), recorder.clone()))
+                    as Arc<dyn AiProvider>;
+            }
+        }
 
         // 初始化角色配置管理器 - 优先使用简化配置
         let roles_manager = RoleConfigLoader::layered_load(self.role_file.clone())?;
+        let router = AiRouter::from_config(&self.config)?;
+        let usage = UsageAccountant::new(self.config.limits.clone())?;
         Ok(AiClient {
             providers,
             config: self.config,
-            router: AiRouter::new(),
+            router,
+            usage: Arc::new(usage),
             roles: roles_manager,
         })
     }
@@ -68,7 +102,7 @@ impl AiClientBuilder {
                     if let Some(base_url) = &config.base_url {
                         provider = provider.with_base_url(base_url.clone());
                     }
-                    Arc::new(provider) as Arc<dyn AiProvider>
+                    Arc::new(provider.with_retry(config.retry.clone())) as Arc<dyn AiProvider>
                 }
                 AiProviderType::DeepSeek => {
                     let mut provider =
@@ -76,7 +110,7 @@ impl AiClientBuilder {
                     if let Some(base_url) = &config.base_url {
                         provider = provider.with_base_url(base_url.clone());
                     }
-                    Arc::new(provider) as Arc<dyn AiProvider>
+                    Arc::new(provider.with_retry(config.retry.clone())) as Arc<dyn AiProvider>
                 }
                 AiProviderType::Groq => {
                     let mut provider =
@@ -84,28 +118,53 @@ impl AiClientBuilder {
                     if let Some(base_url) = &config.base_url {
                         provider = provider.with_base_url(base_url.clone());
                     }
-                    Arc::new(provider) as Arc<dyn AiProvider>
+                    Arc::new(provider.with_retry(config.retry.clone())) as Arc<dyn AiProvider>
                 }
                 AiProviderType::Kimi => {
                     let mut provider =
-                        openai::OpenAiProvider::kimi_k2(config.api_key.clone(), timeout_sec);
+                        openai::OpenAiProvider::kimi(config.api_key.clone(), timeout_sec);
                     if let Some(base_url) = &config.base_url {
                         provider = provider.with_base_url(base_url.clone());
                     }
-                    Arc::new(provider) as Arc<dyn AiProvider>
+                    Arc::new(provider.with_retry(config.retry.clone())) as Arc<dyn AiProvider>
                 }
                 AiProviderType::Glm => {
                     let mut provider =
-                        openai::OpenAiProvider::new(config.api_key.clone(), timeout_sec);
+                        openai::OpenAiProvider::glm(config.api_key.clone(), timeout_sec);
                     if let Some(base_url) = &config.base_url {
                         provider = provider.with_base_url(base_url.clone());
                     }
-                    Arc::new(provider) as Arc<dyn AiProvider>
+                    Arc::new(provider.with_retry(config.retry.clone())) as Arc<dyn AiProvider>
+                }
+                AiProviderType::Anthropic => {
+                    let mut provider =
+                        anthropic::AnthropicProvider::new(config.api_key.clone(), timeout_sec);
+                    if let Some(base_url) = &config.base_url {
+                        provider = provider.with_base_url(base_url.clone());
+                    }
+                    Arc::new(provider.with_retry(config.retry.clone())) as Arc<dyn AiProvider>
                 }
                 AiProviderType::Mock => Arc::new(mock::MockProvider::new()) as Arc<dyn AiProvider>,
-                AiProviderType::Anthropic | AiProviderType::Ollama => {
-                    debug!("Provider {provider_type} is not yet implemented, skipping");
-                    continue;
+                AiProviderType::Custom(_) => {
+                    let base_url = config.base_url.clone().ok_or_else(|| {
+                        OrionAiReason::from_conf(format!("{provider_type}: base_url is required"))
+                            .to_err()
+                    })?;
+                    let provider = openai::OpenAiProvider::custom(
+                        *provider_type,
+                        config.api_key.clone(),
+                        base_url,
+                        timeout_sec,
+                    )
+                    .with_compat(config.compat.clone().unwrap_or_default())?;
+                    Arc::new(provider.with_retry(config.retry.clone())) as Arc<dyn AiProvider>
+                }
+                AiProviderType::Ollama => {
+                    let mut provider = ollama::OllamaProvider::new(timeout_sec);
+                    if let Some(base_url) = &config.base_url {
+                        provider = provider.with_base_url(base_url.clone());
+                    }
+                    Arc::new(provider.with_retry(config.retry.clone())) as Arc<dyn AiProvider>
                 }
             };
 
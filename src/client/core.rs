@@ -1,15 +1,23 @@
-use crate::config::RoleConfigManager;
-use crate::error::{AiError, AiResult, OrionAiReason};
-use crate::provider::{AiProvider, AiProviderType, AiRequest, AiResponse, FunctionDefinition};
+use crate::client::UsageAccountant;
+use crate::client::utils::tag_role_response;
+use crate::config::{BudgetAction, ContextOverflow, RoleConfigManager};
+use crate::error::{AiErrReason, AiError, AiResult, OrionAiReason};
+use crate::provider::{
+    AiProvider, AiProviderType, AiRequest, AiResponse, AiResponseStream, AiStreamEvent,
+    FunctionDefinition,
+};
 use crate::roleid::AiRoleID;
+use crate::tokens::estimate_request_tokens;
 use crate::{
     AiClientTrait, AiConfig, AiErrReason, AiRouter, FunctionRegistry, GlobalFunctionRegistry,
+    TaskTier,
 };
 use async_trait::async_trait;
+use futures_util::StreamExt;
 use getset::Getters;
-use log::error;
+use log::{error, warn};
 use orion_conf::ErrorWith;
-use orion_error::{ContextRecord, OperationContext, ToStructError, UvsBizFrom, UvsConfFrom};
+use orion_error::{ContextRecord, OperationContext, ToStructError, UvsConfFrom};
 use std::collections::HashMap;
 use std::sync::Arc;
 
@@ -21,37 +29,94 @@ pub struct AiClient {
     pub config: AiConfig,
     pub router: AiRouter,
     pub roles: RoleConfigManager,
+    pub usage: Arc<UsageAccountant>,
 }
 
 #[async_trait]
 impl AiClientTrait for AiClient {
+    fn build_ai_request(&self, role: &AiRoleID, user_input: &str) -> AiResult<AiRequest> {
+        AiClient::build_ai_request(self, role, user_input)
+    }
+
+    async fn send_request_with_functions(
+        &self,
+        request: AiRequest,
+        funcs: &[FunctionDefinition],
+    ) -> AiResult<AiResponse> {
+        AiClient::send_request_with_functions(self, request, funcs).await
+    }
+
     async fn send_request(&self, request: AiRequest) -> AiResult<AiResponse> {
         let mut ctx = OperationContext::want("client send_request")
             .with_auto_log()
             .with_mod_path("ai/client");
 
-        let provider_type = self.router.select_provider(&request.model, &self.config);
+        let functions = Self::request_functions(&request);
+        let request = self.apply_tier(request, functions.len());
+        let request = self.apply_budget(request, &functions).with(&ctx)?;
+        let candidates = self.failover_candidates(&request).with(&ctx)?;
+        let provider_type = candidates[0].0;
         ctx.record("model", request.model.as_str());
         ctx.record("provider", provider_type.to_string());
 
-        let response = if let Some(provider) = self.providers.get(&provider_type) {
-            provider.send_request(&request).await.with(&ctx)?
-        } else {
-            for key in self.providers().keys() {
-                error!("registed provider: {key}");
-            }
-            return Err(OrionAiReason::from(AiErrReason::NoProviderAvailable).to_err())
-                .with(provider_type.to_string());
-        };
+        let (mut response, served_by) = self
+            .send_with_failover(
+                &request,
+                candidates,
+                &functions,
+                |provider, request| async move { provider.send_request(&request).await },
+            )
+            .await
+            .inspect_err(|e| self.record_truncated_usage(request.role.as_ref(), e))
+            .with(&ctx)?;
+        Self::record_served_by(&mut response, provider_type, served_by);
+        self.usage.record(request.role.as_ref(), &response.usage);
+        if let Some(tier) = request.tier {
+            response
+                .metadata
+                .insert("tier".to_string(), tier.to_string().into());
+        }
         ctx.mark_suc();
         Ok(response)
     }
 
+    async fn send_request_stream(&self, request: AiRequest) -> AiResult<AiResponseStream> {
+        let mut ctx = OperationContext::want("client send_request_stream")
+            .with_auto_log()
+            .with_mod_path("ai/client");
+
+        let functions = Self::request_functions(&request);
+        let request = self.apply_tier(request, functions.len());
+        let request = self.apply_budget(request, &functions).with(&ctx)?;
+        let candidates = self.failover_candidates(&request).with(&ctx)?;
+        ctx.record("model", request.model.as_str());
+        ctx.record("provider", candidates[0].0.to_string());
+
+        let (stream, _) = self
+            .send_with_failover(
+                &request,
+                candidates,
+                &functions,
+                |provider, request| async move { provider.send_request_stream(&request).await },
+            )
+            .await
+            .with(&ctx)?;
+        let usage = self.usage.clone();
+        let role = request.role.clone();
+        let stream = stream.inspect(move |event| {
+            if let Ok(AiStreamEvent::Usage(info)) = event {
+                usage.record(role.as_ref(), info);
+            }
+        });
+        ctx.mark_suc();
+        Ok(Box::pin(stream))
+    }
+
     /// 基于角色的智能请求处理 - 用户只需选择角色，系统自动选择推荐模型
     async fn smart_role_request(&self, role: &AiRoleID, user_input: &str) -> AiResult<AiResponse> {
         let request = self.build_ai_request(role, user_input)?;
         let mut response = self.send_request(request).await?;
-        response.content = format!("[角色: {}]\n\n{}", role.description(), response.content);
+        tag_role_response(role, &mut response);
 
         Ok(response)
     }
@@ -68,7 +133,7 @@ impl AiClientTrait for AiClient {
         let mut response = self.send_request_with_functions(request, &func).await?;
 
         // 4. 在响应中添加角色信息
-        response.content = format!("[角色: {}]\n\n{}", role.description(), response.content);
+        tag_role_response(role, &mut response);
         Ok(response)
     }
 }
@@ -160,24 +225,248 @@ impl AiClient {
         let mut ctx = OperationContext::want("send_request_fun")
             .with_auto_log()
             .with_mod_path("ai/client");
-        let provider_type = self.router.select_provider(&request.model, &self.config);
+        let request = self.apply_tier(request, funcs.len());
+        let request = self.apply_budget(request, funcs).with(&ctx)?;
+        let candidates = self.failover_candidates(&request).with(&ctx)?;
+        let provider_type = candidates[0].0;
         ctx.record("model", request.model.as_str());
         ctx.record("provider", provider_type.to_string());
 
-        let response = if let Some(provider) = self.providers.get(&provider_type) {
-            if provider.supports_function_calling() {
-                provider.send_request_with_functions(&request, funcs).await
-            } else {
-                Err(OrionAiReason::from_biz(
-                    "TODO: provider does not support function calling".to_string(),
-                )
-                .to_err())
+        let (mut response, served_by) = self
+            .send_with_failover(
+                &request,
+                candidates,
+                funcs,
+                |provider, request| async move {
+                    if provider.supports_function_calling() {
+                        provider.send_request_with_functions(&request, funcs).await
+                    } else {
+                        Err(OrionAiReason::from(AiErrReason::FunctionCallingUnsupported(
+                            provider.provider_type().to_string(),
+                        ))
+                        .to_err())
+                    }
+                },
+            )
+            .await
+            .inspect_err(|e| self.record_truncated_usage(request.role.as_ref(), e))
+            .with(&ctx)?;
+        Self::record_served_by(&mut response, provider_type, served_by);
+        self.usage.record(request.role.as_ref(), &response.usage);
+        if let Some(tier) = request.tier {
+            response
+                .metadata
+                .insert("tier".to_string(), tier.to_string().into());
+        }
+        ctx.mark_suc();
+        Ok(response)
+    }
+
+    /// 按任务分级发送请求，模型由 `RoutingRules` 决定
+    pub async fn send_tiered(&self, tier: TaskTier, request: AiRequest) -> AiResult<AiResponse> {
+        self.send_request(request.with_tier(Some(tier))).await
+    }
+
+    /// 请求设置了任务分级时，用 `RoutingRules` 中对应的模型替换 `model`
+    fn apply_tier(&self, mut request: AiRequest, tool_count: usize) -> AiRequest {
+        if let Some(tier) = request.tier {
+            let tier = tier.resolve(&request, tool_count);
+            request.model = tier.model(&self.config.routing).to_string();
+            request.tier = Some(tier);
+        }
+        request
+    }
+
+    /// 检查用量预算，超出时按 `limits.on_exceed` 拒绝或改用 `routing.free` 模型
+    fn apply_budget(
+        &self,
+        mut request: AiRequest,
+        functions: &[FunctionDefinition],
+    ) -> AiResult<AiRequest> {
+        let requested =
+            estimate_request_tokens(&request, functions) + request.max_tokens.unwrap_or(0);
+        match self.usage.check(request.role.as_ref(), requested) {
+            Ok(()) => Ok(request),
+            Err(e) if self.config.limits.on_exceed == BudgetAction::Downgrade => {
+                warn!("{e}, downgrading to {}", self.config.routing.free);
+                request.model = self.config.routing.free.clone();
+                request.tier = Some(TaskTier::Free);
+                Ok(request)
             }
-        } else {
-            Err(OrionAiReason::from(AiErrReason::NoProviderAvailable).to_err())
+            Err(e) => Err(e),
+        }
+    }
+
+    /// 请求中启用的工具定义
+    fn request_functions(request: &AiRequest) -> Vec<FunctionDefinition> {
+        match &request.functions {
+            Some(functions) if request.enable_function_calling => functions.clone(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// 发送前检查提示词是否放得下模型的上下文窗口（`ModelInfo.max_tokens`）
+    ///
+    /// 超出时按 `limits.on_context_overflow` 拒绝，或从最早的对话历史开始丢弃。
+    fn preflight(
+        &self,
+        mut request: AiRequest,
+        (provider_type, model): &(AiProviderType, String),
+        functions: &[FunctionDefinition],
+    ) -> AiResult<AiRequest> {
+        let Some(provider) = self.providers.get(provider_type) else {
+            return Ok(request);
         };
-        ctx.mark_suc();
+
+        loop {
+            let needed =
+                estimate_request_tokens(&request, functions) + request.max_tokens.unwrap_or(0);
+            if provider.check_token_limit(model, needed) {
+                return Ok(request);
+            }
+            let limit = provider
+                .get_model_info(model)
+                .map(|info| info.max_tokens)
+                .unwrap_or_default();
+            if self.config.limits.on_context_overflow == ContextOverflow::Truncate
+                && request.drop_oldest_turn()
+            {
+                warn!(
+                    "prompt needs ~{needed} tokens, over the {limit} context window of {model}; dropped oldest turn"
+                );
+                continue;
+            }
+            return Err(OrionAiReason::from(AiErrReason::TokenLimitError(needed, limit)).to_err());
+        }
+    }
+
+    /// 故障转移候选列表
+    ///
+    /// 第一个为路由到的 provider 和实际模型名；其余为 `failover.model_equivalents`
+    /// 中等价模型所在的已注册 provider，按 `ProviderConfig.priority` 升序排列。
+    pub fn failover_candidates(
+        &self,
+        request: &AiRequest,
+    ) -> AiResult<Vec<(AiProviderType, String)>> {
+        let role = request.role.as_ref();
+        let primary = self.resolve_model(&request.model, role)?;
+        let model = primary.1.clone();
+        let mut candidates = vec![primary];
+        if !self.config.failover.enabled {
+            return Ok(candidates);
+        }
+
+        let mut fallbacks: Vec<(u32, AiProviderType, String)> = self
+            .config
+            .failover
+            .equivalents(&model)
+            .into_iter()
+            .filter_map(|m| self.resolve_model(m, role).ok())
+            .filter(|(provider, _)| self.providers.contains_key(provider))
+            .map(|(provider, m)| {
+                let priority = self
+                    .config
+                    .providers
+                    .get(&provider)
+                    .and_then(|c| c.priority)
+                    .unwrap_or(u32::MAX);
+                (priority, provider, m)
+            })
+            .collect();
+        fallbacks.sort_by_key(|(priority, _, _)| *priority);
+
+        for (_, provider, m) in fallbacks {
+            if !candidates.iter().any(|(p, _)| *p == provider) {
+                candidates.push((provider, m));
+            }
+        }
+        Ok(candidates)
+    }
+
+    /// 解析模型名：先展开 `model_aliases` 中的别名，否则交给路由器
+    pub fn resolve_model(
+        &self,
+        model: &str,
+        role: Option<&AiRoleID>,
+    ) -> AiResult<(AiProviderType, String)> {
+        match self.config.resolve_model_alias(model) {
+            Some((provider, target)) => Ok((provider, target.to_string())),
+            None => self.router.route(model, role),
+        }
+    }
+
+    /// 依次尝试故障转移候选，发送前按各候选的上下文窗口做 `preflight`；
+    /// 检查不通过、遇到暂时性错误或 provider 未注册时切换到下一个
+    async fn send_with_failover<T, F, Fut>(
+        &self,
+        request: &AiRequest,
+        candidates: Vec<(AiProviderType, String)>,
+        functions: &[FunctionDefinition],
+        call: F,
+    ) -> AiResult<(T, AiProviderType)>
+    where
+        F: Fn(Arc<dyn AiProvider>, AiRequest) -> Fut,
+        Fut: Future<Output = AiResult<T>>,
+    {
+        let mut last_err = None;
+        for (provider_type, model) in candidates {
+            let Some(provider) = self.providers.get(&provider_type) else {
+                for key in self.providers().keys() {
+                    error!("registed provider: {key}");
+                }
+                last_err =
+                    Err::<(), _>(OrionAiReason::from(AiErrReason::NoProviderAvailable).to_err())
+                        .with(provider_type.to_string())
+                        .err();
+                continue;
+            };
+
+            let candidate = (provider_type, model);
+            let mut attempt = request.clone();
+            attempt.model = candidate.1.clone();
+            // 每个候选的上下文窗口不同，放不下时换下一个候选
+            let attempt = match self.preflight(attempt, &candidate, functions) {
+                Ok(attempt) => attempt,
+                Err(e) => {
+                    warn!("skip {provider_type}/{}: {e}", candidate.1);
+                    last_err = Some(e);
+                    continue;
+                }
+            };
+            match call(provider.clone(), attempt).await {
+                Ok(value) => return Ok((value, provider_type)),
+                Err(e) if matches!(e.reason(), OrionAiReason::Ai(r) if r.is_transient()) => {
+                    warn!("provider {provider_type} failed: {e}, trying next provider");
+                    last_err = Some(e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Err(last_err
+            .unwrap_or_else(|| OrionAiReason::from(AiErrReason::NoProviderAvailable).to_err()))
+    }
+
+    /// 截断错误携带部分响应，其用量同样计入统计
+    fn record_truncated_usage(&self, role: Option<&AiRoleID>, err: &AiError) {
+        if let OrionAiReason::Ai(AiErrReason::OutputTruncated(partial)) = err.reason() {
+            self.usage.record(role, &partial.usage);
+        }
+    }
+
+    /// 在响应元数据中记录实际应答的 provider
+    fn record_served_by(
+        response: &mut AiResponse,
+        routed: AiProviderType,
+        served_by: AiProviderType,
+    ) {
         response
+            .metadata
+            .insert("provider".to_string(), served_by.to_string().into());
+        if routed != served_by {
+            response
+                .metadata
+                .insert("failover_from".to_string(), routed.to_string().into());
+        }
     }
 
     /// 处理函数调用结果 - 简化版本
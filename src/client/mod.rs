@@ -1,6 +1,7 @@
 pub mod builder;
 pub mod core;
 pub mod trais;
+pub mod usage;
 pub mod utils;
 
 #[cfg(test)]
@@ -10,3 +11,4 @@ pub mod tests;
 pub use builder::AiClientBuilder;
 pub use core::AiClient;
 pub use trais::{AiClientTrait, AiCoreClient};
+pub use usage::{UsageAccountant, UsageCounter};
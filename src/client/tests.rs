@@ -16,7 +16,7 @@ fn create_mock_config() -> AiConfig {
         provider_config.enabled = false;
     }
     // 添加Mock提供商配置
-    use crate::config::ProviderConfig;
+    use crate::config::{ProviderConfig, RetryPolicy};
     config.providers.insert(
         AiProviderType::Mock,
         ProviderConfig {
@@ -26,6 +26,8 @@ fn create_mock_config() -> AiConfig {
             timeout: 30,
             model_aliases: None,
             priority: Some(999),
+            retry: RetryPolicy::default(),
+            compat: None,
         },
     );
     config
@@ -324,3 +326,366 @@ fn test_build_ai_request_response_structure() {
     assert!(request.temperature == Some(0.7) || request.temperature.is_none());
     assert!(request.role.is_some());
 }
+
+fn failover_client() -> crate::AiClient {
+    let mut config = create_mock_config();
+    config.failover.model_equivalents = vec![vec!["glm-4.5".to_string(), "mock".to_string()]];
+    AiClientBuilder::new(config)
+        .with_role(PathBuf::from("./_gal/ai-roles.yml"))
+        .build()
+        .assert("ai-cleint new")
+}
+
+#[tokio::test]
+async fn test_failover_when_provider_missing() {
+    let client = failover_client();
+    let request = AiRequest::builder()
+        .model("glm-4.5")
+        .user_prompt("hi")
+        .build();
+    assert_eq!(
+        client.failover_candidates(&request).assert("candidates"),
+        vec![
+            (AiProviderType::Glm, "glm-4.5".to_string()),
+            (AiProviderType::Mock, "mock".to_string())
+        ]
+    );
+
+    let response = client.send_request(request).await.assert("failover");
+    assert_eq!(response.provider, AiProviderType::Mock);
+    assert_eq!(response.model, "mock");
+    assert_eq!(
+        response.metadata["provider"],
+        AiProviderType::Mock.to_string()
+    );
+    assert_eq!(
+        response.metadata["failover_from"],
+        AiProviderType::Glm.to_string()
+    );
+}
+
+#[tokio::test]
+async fn test_failover_only_on_transient_errors() {
+    use crate::config::RetryPolicy;
+    use crate::provider::AiProvider;
+    use crate::providers::openai::OpenAiProvider;
+    use crate::providers::stub::{StubResponse, StubServer};
+    use std::sync::Arc;
+
+    let server = StubServer::start(vec![
+        StubResponse::json(503, "{}"),
+        StubResponse::json(401, r#"{"error":{"message":"bad key"}}"#),
+    ])
+    .await;
+    let mut client = failover_client();
+    let glm = OpenAiProvider::glm("key".to_string(), 5)
+        .with_base_url(server.base_url().to_string())
+        .with_retry(RetryPolicy::none());
+    client
+        .providers
+        .insert(AiProviderType::Glm, Arc::new(glm) as Arc<dyn AiProvider>);
+
+    let request = AiRequest::builder()
+        .model("glm-4.5")
+        .user_prompt("hi")
+        .build();
+    let response = client
+        .send_request(request.clone())
+        .await
+        .assert("failover");
+    assert_eq!(response.provider, AiProviderType::Mock);
+
+    // 鉴权失败不是暂时性错误，不切换 provider
+    assert!(client.send_request(request).await.is_err());
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn test_model_alias_resolved_from_role() {
+    use std::collections::HashMap;
+    use std::io::Write;
+
+    let mut config = create_mock_config();
+    config
+        .providers
+        .get_mut(&AiProviderType::Mock)
+        .unwrap()
+        .model_aliases = Some(HashMap::from([
+        ("fast".to_string(), "mock-fast".to_string()),
+        ("smart".to_string(), "mock-smart".to_string()),
+    ]));
+
+    let mut role_file = tempfile::NamedTempFile::new().unwrap();
+    write!(
+        role_file,
+        "default_role:\n  id: tester\ndefault_model: fast\nroles:\n  tester:\n    name: tester\n    description: 测试角色\n    system_prompt: 你是测试助手\n  reviewer:\n    name: reviewer\n    description: 评审角色\n    system_prompt: 你是评审助手\n    used_model: smart\n"
+    )
+    .unwrap();
+    let client = AiClientBuilder::new(config)
+        .with_role(role_file.path().to_path_buf())
+        .build()
+        .assert("ai-cleint new");
+
+    let request = client
+        .build_ai_request(&AiRoleID::new("tester"), "hi")
+        .assert("build request");
+    assert_eq!(request.model, "fast");
+    assert_eq!(
+        client.failover_candidates(&request).assert("candidates")[0],
+        (AiProviderType::Mock, "mock-fast".to_string())
+    );
+
+    let request = client
+        .build_ai_request(&AiRoleID::new("reviewer"), "hi")
+        .assert("build request");
+    let response = client.send_request(request).await.assert("alias request");
+    assert_eq!(response.provider, AiProviderType::Mock);
+    assert_eq!(response.model, "mock-smart");
+}
+
+#[tokio::test]
+async fn test_send_tiered_uses_routing_models() {
+    use crate::TaskTier;
+
+    let mut config = create_mock_config();
+    config.routing.simple = "mock-simple".to_string();
+    config.routing.complex = "mock-complex".to_string();
+    let client = AiClientBuilder::new(config)
+        .with_role(PathBuf::from("./_gal/ai-roles.yml"))
+        .build()
+        .assert("ai-cleint new");
+
+    let request = AiRequest::builder()
+        .model("gpt-4o")
+        .user_prompt("hi")
+        .build();
+    let response = client
+        .send_tiered(TaskTier::Complex, request.clone())
+        .await
+        .assert("tiered request");
+    assert_eq!(response.model, "mock-complex");
+    assert_eq!(response.metadata["tier"], "complex");
+
+    let response = client
+        .send_request(request.with_tier(Some(TaskTier::Auto)))
+        .await
+        .assert("auto tier request");
+    assert_eq!(response.model, "mock-simple");
+    assert_eq!(response.metadata["tier"], "simple");
+}
+
+#[tokio::test]
+async fn test_usage_budget_reject_and_downgrade() {
+    use crate::config::BudgetAction;
+    use crate::error::{AiErrReason, OrionAiReason};
+
+    let mut config = create_mock_config();
+    config.limits.session_token_budget = 60;
+    config.routing.free = "mock-free".to_string();
+    let client = AiClientBuilder::new(config.clone())
+        .with_role(PathBuf::from("./_gal/ai-roles.yml"))
+        .build()
+        .assert("ai-cleint new");
+
+    let request = AiRequest::builder()
+        .model("mock")
+        .user_prompt("hi")
+        .max_tokens(20)
+        .build();
+    client
+        .send_request(request.clone())
+        .await
+        .assert("within budget");
+    assert_eq!(client.usage().session().tokens, 50);
+
+    let err = client.send_request(request.clone()).await.unwrap_err();
+    assert!(matches!(
+        err.reason(),
+        OrionAiReason::Ai(AiErrReason::TokenLimitError(_, 60))
+    ));
+
+    config.limits.on_exceed = BudgetAction::Downgrade;
+    let client = AiClientBuilder::new(config)
+        .with_role(PathBuf::from("./_gal/ai-roles.yml"))
+        .build()
+        .assert("ai-cleint new");
+    client
+        .send_request(request.clone())
+        .await
+        .assert("within budget");
+    let response = client.send_request(request).await.assert("downgraded");
+    assert_eq!(response.model, "mock-free");
+    assert_eq!(response.metadata["tier"], "free");
+}
+
+#[tokio::test]
+async fn test_preflight_context_window() {
+    use crate::config::ContextOverflow;
+    use crate::error::{AiErrReason, OrionAiReason};
+    use crate::provider::ChatMessage;
+
+    let history: Vec<ChatMessage> = (0..3)
+        .flat_map(|_| {
+            [
+                ChatMessage::user("问".repeat(1000)),
+                ChatMessage::assistant("答".repeat(500)),
+            ]
+        })
+        .collect();
+    let request = AiRequest::builder()
+        .model("mock")
+        .messages(history)
+        .user_prompt("hi")
+        .build();
+
+    // mock 模型的上下文窗口为 4000 token
+    let mut config = create_mock_config();
+    let client = AiClientBuilder::new(config.clone())
+        .with_role(PathBuf::from("./_gal/ai-roles.yml"))
+        .build()
+        .assert("ai-cleint new");
+    let err = client.send_request(request.clone()).await.unwrap_err();
+    assert!(matches!(
+        err.reason(),
+        OrionAiReason::Ai(AiErrReason::TokenLimitError(_, 4000))
+    ));
+
+    config.limits.on_context_overflow = ContextOverflow::Truncate;
+    let client = AiClientBuilder::new(config)
+        .with_role(PathBuf::from("./_gal/ai-roles.yml"))
+        .build()
+        .assert("ai-cleint new");
+    client
+        .send_request(request)
+        .await
+        .assert("truncated request");
+}
+
+#[tokio::test]
+async fn test_preflight_checked_per_failover_candidate() {
+    use crate::config::RetryPolicy;
+    use crate::error::{AiErrReason, OrionAiReason};
+    use crate::provider::{AiProvider, ChatMessage};
+    use crate::providers::openai::OpenAiProvider;
+    use crate::providers::stub::{StubResponse, StubServer};
+    use std::sync::Arc;
+
+    let server = StubServer::start(vec![StubResponse::json(503, "{}")]).await;
+    let mut client = failover_client();
+    let glm = OpenAiProvider::glm("key".to_string(), 5)
+        .with_base_url(server.base_url().to_string())
+        .with_retry(RetryPolicy::none());
+    client
+        .providers
+        .insert(AiProviderType::Glm, Arc::new(glm) as Arc<dyn AiProvider>);
+
+    // 放得下 glm-4.5 的窗口，放不下 mock 的 4000 token
+    let request = AiRequest::builder()
+        .model("glm-4.5")
+        .message(ChatMessage::user("问".repeat(5000)))
+        .user_prompt("hi")
+        .build();
+    let err = client.send_request(request).await.unwrap_err();
+    assert!(matches!(
+        err.reason(),
+        OrionAiReason::Ai(AiErrReason::TokenLimitError(_, 4000))
+    ));
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn test_record_and_replay_through_builder() {
+    once_init_log();
+    let dir = tempfile::TempDir::new().unwrap();
+    let fixture = dir.path().join("mock.jsonl");
+    let role_file = PathBuf::from("./_gal/ai-roles.yml");
+    let request = AiRequest::builder()
+        .model("mock-gpt")
+        .user_prompt("录制一次")
+        .build();
+
+    let client = AiClientBuilder::new(create_mock_config())
+        .with_role(role_file.clone())
+        .with_record_file(Some(fixture.clone()))
+        .build()
+        .assert("recording client");
+    let recorded = client.send_request(request.clone()).await.assert("record");
+
+    let client = AiClientBuilder::new(create_mock_config())
+        .with_role(role_file)
+        .with_replay_file(Some(fixture))
+        .build()
+        .assert("replay client");
+    let replayed = client.send_request(request).await.assert("replay");
+    assert_eq!(replayed.content, recorded.content);
+    assert_eq!(replayed.usage, recorded.usage);
+
+    let unrecorded = AiRequest::builder()
+        .model("mock-gpt")
+        .user_prompt("没有录制")
+        .build();
+    assert!(client.send_request(unrecorded).await.is_err());
+}
+
+#[tokio::test]
+async fn test_truncated_output_usage_recorded() {
+    use crate::error::{AiErrReason, OrionAiReason};
+    use crate::provider::AiProvider;
+    use crate::providers::mock::{MockError, MockProvider, MockRule};
+    use std::sync::Arc;
+
+    let mut client = AiClientBuilder::new(create_mock_config())
+        .with_role(PathBuf::from("./_gal/ai-roles.yml"))
+        .build()
+        .assert("ai-cleint new");
+    let mock = MockProvider::new();
+    mock.push_rule(MockRule::fail(MockError::Truncated));
+    client
+        .providers
+        .insert(AiProviderType::Mock, Arc::new(mock) as Arc<dyn AiProvider>);
+
+    let request = AiRequest::builder().model("mock").user_prompt("hi").build();
+    let err = client.send_request(request).await.unwrap_err();
+    match err.reason() {
+        OrionAiReason::Ai(AiErrReason::OutputTruncated(partial)) => {
+            assert_eq!(partial.usage.total_tokens, 50)
+        }
+        other => panic!("unexpected reason: {other:?}"),
+    }
+    // 截断的部分响应同样消耗了 token
+    assert_eq!(client.usage().session().tokens, 50);
+}
+
+#[tokio::test]
+async fn test_builder_uses_glm_and_kimi_profiles() {
+    use crate::config::ProviderConfig;
+    use crate::provider::AiProvider;
+
+    let mut config = create_mock_config();
+    for provider in [AiProviderType::Glm, AiProviderType::Kimi] {
+        config.providers.insert(
+            provider,
+            ProviderConfig {
+                enabled: true,
+                api_key: "key".to_string(),
+                ..Default::default()
+            },
+        );
+    }
+    let client = AiClientBuilder::new(config)
+        .with_role(PathBuf::from("./_gal/ai-roles.yml"))
+        .build()
+        .assert("ai-cleint new");
+
+    // 配置中的 GLM/Kimi 使用各自的 profile，而不是 OpenAI 的默认值
+    let glm = &client.providers[&AiProviderType::Glm];
+    assert_eq!(glm.provider_type(), AiProviderType::Glm);
+    assert_eq!(glm.get_config_keys(), vec!["GLM_API_KEY", "GLM_BASE_URL"]);
+    assert!(glm.get_model_info("glm-4.5").is_some());
+    let kimi = &client.providers[&AiProviderType::Kimi];
+    assert_eq!(kimi.provider_type(), AiProviderType::Kimi);
+    assert_eq!(
+        kimi.get_config_keys(),
+        vec!["KIMI_API_KEY", "KIMI_BASE_URL"]
+    );
+}
@@ -1,6 +1,6 @@
 use crate::FunctionDefinition;
 use crate::error::AiResult;
-use crate::provider::{AiRequest, AiResponse};
+use crate::provider::{AiRequest, AiResponse, AiResponseStream};
 use crate::roleid::AiRoleID;
 use async_trait::async_trait;
 
@@ -14,7 +14,17 @@ pub enum AiCoreClient {
 /// AI客户端trait定义
 #[async_trait]
 pub trait AiClientTrait: Send + Sync {
+    /// 按角色配置构建请求
+    fn build_ai_request(&self, role: &AiRoleID, user_input: &str) -> AiResult<AiRequest>;
     async fn send_request(&self, request: AiRequest) -> AiResult<AiResponse>;
+    /// 发送带函数定义的请求，用于多轮工具调用
+    async fn send_request_with_functions(
+        &self,
+        request: AiRequest,
+        funcs: &[FunctionDefinition],
+    ) -> AiResult<AiResponse>;
+    /// 发送流式请求，返回增量事件流
+    async fn send_request_stream(&self, request: AiRequest) -> AiResult<AiResponseStream>;
     async fn smart_role_request(&self, role: &AiRoleID, user_input: &str) -> AiResult<AiResponse>;
     async fn role_funs_request(
         &self,
@@ -26,12 +36,34 @@ pub trait AiClientTrait: Send + Sync {
 
 #[async_trait]
 impl AiClientTrait for AiCoreClient {
+    fn build_ai_request(&self, role: &AiRoleID, user_input: &str) -> AiResult<AiRequest> {
+        match self {
+            Self::Basic(o) => o.build_ai_request(role, user_input),
+        }
+    }
+
     async fn send_request(&self, request: AiRequest) -> AiResult<AiResponse> {
         match self {
             Self::Basic(o) => o.send_request(request).await,
         }
     }
 
+    async fn send_request_with_functions(
+        &self,
+        request: AiRequest,
+        funcs: &[FunctionDefinition],
+    ) -> AiResult<AiResponse> {
+        match self {
+            Self::Basic(o) => o.send_request_with_functions(request, funcs).await,
+        }
+    }
+
+    async fn send_request_stream(&self, request: AiRequest) -> AiResult<AiResponseStream> {
+        match self {
+            Self::Basic(o) => o.send_request_stream(request).await,
+        }
+    }
+
     async fn smart_role_request(&self, role: &AiRoleID, user_input: &str) -> AiResult<AiResponse> {
         match self {
             Self::Basic(o) => o.smart_role_request(role, user_input).await,
@@ -0,0 +1,544 @@
+//! token 用量计数与预算控制
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use chrono::Local;
+use log::warn;
+use orion_error::{ErrorOwe, ToStructError};
+use serde::{Deserialize, Serialize};
+
+use crate::config::{BudgetPeriod, UsageLimits};
+use crate::error::{AiErrReason, AiResult, OrionAiReason};
+use crate::provider::UsageInfo;
+use crate::roleid::AiRoleID;
+
+/// 用量计数
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageCounter {
+    pub requests: usize,
+    pub tokens: usize,
+    pub cost: f64,
+}
+
+impl UsageCounter {
+    fn add(&mut self, usage: &UsageInfo) {
+        self.requests += 1;
+        self.tokens += usage.total_tokens;
+        self.cost += usage.estimated_cost.unwrap_or(0.0);
+    }
+
+    fn merge(&mut self, other: &UsageCounter) {
+        self.requests += other.requests;
+        self.tokens += other.tokens;
+        self.cost += other.cost;
+    }
+}
+
+/// 按天持久化的用量
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+struct DailyUsage {
+    date: String,
+    total: UsageCounter,
+    #[serde(default)]
+    roles: HashMap<String, UsageCounter>,
+}
+
+impl DailyUsage {
+    fn new(date: &str) -> Self {
+        Self {
+            date: date.to_string(),
+            ..Self::default()
+        }
+    }
+
+    fn add(&mut self, role: Option<&AiRoleID>, usage: &UsageInfo) {
+        self.total.add(usage);
+        if let Some(role) = role {
+            self.roles.entry(role.to_string()).or_default().add(usage);
+        }
+    }
+
+    fn merge(&mut self, other: &DailyUsage) {
+        self.total.merge(&other.total);
+        for (role, counter) in &other.roles {
+            self.roles.entry(role.clone()).or_default().merge(counter);
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct UsageState {
+    daily: DailyUsage,
+    session: UsageCounter,
+    session_roles: HashMap<String, UsageCounter>,
+}
+
+impl UsageState {
+    /// 日期变化时清零当日计数
+    fn roll_over(&mut self, today: &str) {
+        if self.daily.date != today {
+            self.daily = DailyUsage::new(today);
+        }
+    }
+}
+
+/// 用量文件写入器
+///
+/// 累积尚未落盘的增量，在 tokio 运行时中由阻塞线程池写入，不阻塞请求路径。
+/// 写入时在文件锁内读取文件、加上增量、写临时文件再改名，多个进程共享同一文件时计数不会互相覆盖
+#[derive(Debug)]
+struct UsagePersister {
+    path: PathBuf,
+    pending: Mutex<Option<DailyUsage>>,
+    writing: Mutex<()>,
+    state: Arc<Mutex<UsageState>>,
+}
+
+impl UsagePersister {
+    /// 累加增量，增量属于更早的日期时直接丢弃（用量文件只保存当日计数）
+    fn schedule(self: &Arc<Self>, date: &str, role: Option<&AiRoleID>, usage: &UsageInfo) {
+        let idle = {
+            let mut pending = self.pending.lock().expect("usage pending poisoned");
+            let idle = pending.is_none();
+            let mut delta = pending
+                .take()
+                .filter(|delta| delta.date == date)
+                .unwrap_or_else(|| DailyUsage::new(date));
+            delta.add(role, usage);
+            *pending = Some(delta);
+            idle
+        };
+        if !idle {
+            // 已有写入排队，会一并写出这次的增量
+            return;
+        }
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                let persister = self.clone();
+                handle.spawn_blocking(move || persister.write_pending());
+            }
+            Err(_) => self.write_pending(),
+        }
+    }
+
+    /// 把待写增量合并进用量文件，并以合并结果刷新内存中的当日计数
+    fn write_pending(&self) {
+        let _writing = self.writing.lock().expect("usage writer poisoned");
+        let Some(delta) = self.pending.lock().expect("usage pending poisoned").take() else {
+            return;
+        };
+        match UsageAccountant::merge_into(&self.path, &delta) {
+            Ok(merged) => {
+                // 包含其他进程的用量；期间新记录的增量尚未落盘，需要叠加
+                let mut state = self.state.lock().expect("usage state poisoned");
+                if state.daily.date == merged.date {
+                    let mut daily = merged;
+                    if let Some(pending) = self
+                        .pending
+                        .lock()
+                        .expect("usage pending poisoned")
+                        .as_ref()
+                        && pending.date == daily.date
+                    {
+                        daily.merge(pending);
+                    }
+                    state.daily = daily;
+                }
+            }
+            Err(e) => {
+                warn!("failed to persist usage to {}: {e}", self.path.display());
+                // 保留增量，下次写入时重试
+                let mut pending = self.pending.lock().expect("usage pending poisoned");
+                match pending.as_mut() {
+                    Some(newer) if newer.date == delta.date => newer.merge(&delta),
+                    Some(_) => {}
+                    None => *pending = Some(delta),
+                }
+            }
+        }
+    }
+}
+
+/// 用量记账
+///
+/// 按会话（进程生命周期）、按天、按角色累计 token 和 `estimated_cost`，
+/// 配置了 `usage_file` 时当日计数会在后台合并写入文件，重启后继续累计，多个进程共享预算。
+#[derive(Debug)]
+pub struct UsageAccountant {
+    limits: UsageLimits,
+    state: Arc<Mutex<UsageState>>,
+    persister: Option<Arc<UsagePersister>>,
+}
+
+impl UsageAccountant {
+    pub fn new(limits: UsageLimits) -> AiResult<Self> {
+        let daily = match &limits.usage_file {
+            Some(path) if path.exists() => Self::load(path)?,
+            _ => DailyUsage::default(),
+        };
+        let mut state = UsageState {
+            daily,
+            ..UsageState::default()
+        };
+        state.roll_over(&Self::today());
+        let state = Arc::new(Mutex::new(state));
+        let persister = limits.usage_file.clone().map(|path| {
+            Arc::new(UsagePersister {
+                path,
+                pending: Mutex::new(None),
+                writing: Mutex::new(()),
+                state: state.clone(),
+            })
+        });
+        Ok(Self {
+            limits,
+            state,
+            persister,
+        })
+    }
+
+    fn today() -> String {
+        Local::now().date_naive().to_string()
+    }
+
+    fn load(path: &Path) -> AiResult<DailyUsage> {
+        let content = std::fs::read_to_string(path).owe_res()?;
+        serde_json::from_str(&content).owe_data()
+    }
+
+    /// 在文件锁内把增量加到用量文件上，返回合并后的当日用量
+    ///
+    /// 先写临时文件再改名，写到一半崩溃不会截断原文件；锁加在旁边的 `.lock` 文件上，
+    /// 因为改名会替换用量文件本身
+    fn merge_into(path: &Path, delta: &DailyUsage) -> AiResult<DailyUsage> {
+        if let Some(parent) = path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            std::fs::create_dir_all(parent).owe_res()?;
+        }
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let lock = std::fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path.with_file_name(format!("{file_name}.lock")))
+            .owe_res()?;
+        lock.lock().owe_res()?;
+
+        let mut daily = if path.exists() {
+            Self::load(path)?
+        } else {
+            DailyUsage::new(&delta.date)
+        };
+        // 文件中是其他进程已经滚动到的新一天时，旧增量不再计入
+        if daily.date > delta.date {
+            return Ok(daily);
+        }
+        if daily.date < delta.date {
+            daily = DailyUsage::new(&delta.date);
+        }
+        daily.merge(delta);
+
+        let content = serde_json::to_string_pretty(&daily).owe_data()?;
+        let temp = path.with_file_name(format!("{file_name}.{}.tmp", std::process::id()));
+        std::fs::write(&temp, content).owe_res()?;
+        std::fs::rename(&temp, path).owe_res()?;
+        Ok(daily)
+    }
+
+    pub fn limits(&self) -> &UsageLimits {
+        &self.limits
+    }
+
+    /// 检查再使用 `requested` 个 token 是否超出预算
+    ///
+    /// 费用无法在发送前确定，已用费用达到费用预算后拒绝后续请求
+    pub fn check(&self, role: Option<&AiRoleID>, requested: usize) -> AiResult<()> {
+        let mut state = self.state.lock().expect("usage state poisoned");
+        state.roll_over(&Self::today());
+
+        let role_budget = role.and_then(|role| {
+            let budget = *self.limits.role_token_budgets.get(role.as_str())?;
+            let used = Self::role_counter(&state, self.limits.role_budget_period, role)
+                .map_or(0, |c| c.tokens);
+            Some((used, budget))
+        });
+        let budgets = [
+            (state.session.tokens, self.limits.session_token_budget),
+            (state.daily.total.tokens, self.limits.daily_token_budget),
+        ];
+
+        for (used, budget) in budgets.into_iter().chain(role_budget) {
+            if budget > 0 && used + requested > budget {
+                return Err(OrionAiReason::from(AiErrReason::TokenLimitError(
+                    used + requested,
+                    budget,
+                ))
+                .to_err());
+            }
+        }
+
+        let cost_budgets = [
+            (state.session.cost, self.limits.session_cost_budget),
+            (state.daily.total.cost, self.limits.daily_cost_budget),
+        ];
+        for (used, budget) in cost_budgets {
+            if budget > 0.0 && used >= budget {
+                return Err(
+                    OrionAiReason::from(AiErrReason::CostLimitError(used, budget)).to_err(),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn role_counter<'a>(
+        state: &'a UsageState,
+        period: BudgetPeriod,
+        role: &AiRoleID,
+    ) -> Option<&'a UsageCounter> {
+        match period {
+            BudgetPeriod::Daily => state.daily.roles.get(role.as_str()),
+            BudgetPeriod::Session => state.session_roles.get(role.as_str()),
+        }
+    }
+
+    /// 记录一次请求的用量
+    pub fn record(&self, role: Option<&AiRoleID>, usage: &UsageInfo) {
+        let mut state = self.state.lock().expect("usage state poisoned");
+        let today = Self::today();
+        state.roll_over(&today);
+
+        state.session.add(usage);
+        state.daily.add(role, usage);
+        if let Some(role) = role {
+            state
+                .session_roles
+                .entry(role.to_string())
+                .or_default()
+                .add(usage);
+        }
+        drop(state);
+
+        if let Some(persister) = &self.persister {
+            persister.schedule(&today, role, usage);
+        }
+    }
+
+    /// 立即写出尚未落盘的用量
+    pub fn flush(&self) {
+        if let Some(persister) = &self.persister {
+            persister.write_pending();
+        }
+    }
+
+    /// 本次会话的用量
+    pub fn session(&self) -> UsageCounter {
+        self.state
+            .lock()
+            .expect("usage state poisoned")
+            .session
+            .clone()
+    }
+
+    /// 当日用量
+    pub fn today_usage(&self) -> UsageCounter {
+        let mut state = self.state.lock().expect("usage state poisoned");
+        state.roll_over(&Self::today());
+        state.daily.total.clone()
+    }
+
+    /// 角色在 `role_budget_period` 周期内的用量
+    pub fn role_usage(&self, role: &AiRoleID) -> UsageCounter {
+        let mut state = self.state.lock().expect("usage state poisoned");
+        state.roll_over(&Self::today());
+        Self::role_counter(&state, self.limits.role_budget_period, role)
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(tokens: usize, cost: f64) -> UsageInfo {
+        UsageInfo {
+            prompt_tokens: tokens / 2,
+            completion_tokens: tokens - tokens / 2,
+            total_tokens: tokens,
+            estimated_cost: Some(cost),
+        }
+    }
+
+    fn token_limit(result: AiResult<()>) -> (usize, usize) {
+        match result.unwrap_err().reason() {
+            OrionAiReason::Ai(AiErrReason::TokenLimitError(requested, max)) => (*requested, *max),
+            other => panic!("unexpected reason: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_budgets_enforced() {
+        let limits = UsageLimits {
+            session_token_budget: 1000,
+            daily_token_budget: 800,
+            role_token_budgets: HashMap::from([("developer".to_string(), 300)]),
+            ..UsageLimits::default()
+        };
+        let accountant = UsageAccountant::new(limits).unwrap();
+        let developer = AiRoleID::new("developer");
+        let operator = AiRoleID::new("operator");
+
+        accountant.record(Some(&developer), &usage(200, 0.01));
+        accountant.check(Some(&developer), 100).unwrap();
+        assert_eq!(
+            token_limit(accountant.check(Some(&developer), 101)),
+            (301, 300)
+        );
+
+        accountant.record(Some(&operator), &usage(500, 0.02));
+        assert_eq!(
+            token_limit(accountant.check(Some(&operator), 101)),
+            (801, 800)
+        );
+
+        let today = accountant.today_usage();
+        assert_eq!(today.requests, 2);
+        assert_eq!(today.tokens, 700);
+        assert!((today.cost - 0.03).abs() < 1e-9);
+        assert_eq!(accountant.role_usage(&developer).tokens, 200);
+        assert_eq!(accountant.session().tokens, 700);
+    }
+
+    #[test]
+    fn test_usage_persisted_across_restarts() {
+        let dir = tempfile::tempdir().unwrap();
+        let limits = UsageLimits {
+            daily_token_budget: 1000,
+            usage_file: Some(dir.path().join("usage/ai-usage.json")),
+            ..UsageLimits::default()
+        };
+        let role = AiRoleID::new("developer");
+
+        let accountant = UsageAccountant::new(limits.clone()).unwrap();
+        accountant.record(Some(&role), &usage(600, 0.5));
+
+        let restarted = UsageAccountant::new(limits).unwrap();
+        assert_eq!(restarted.today_usage().tokens, 600);
+        assert_eq!(restarted.role_usage(&role).tokens, 600);
+        // 会话计数不跨进程
+        assert_eq!(restarted.session(), UsageCounter::default());
+        assert_eq!(token_limit(restarted.check(None, 401)), (1001, 1000));
+    }
+
+    #[test]
+    fn test_cost_budgets_enforced() {
+        let limits = UsageLimits {
+            session_cost_budget: 1.0,
+            daily_cost_budget: 0.5,
+            ..UsageLimits::default()
+        };
+        let accountant = UsageAccountant::new(limits).unwrap();
+
+        accountant.record(None, &usage(100, 0.4));
+        accountant.check(None, 100).unwrap();
+        accountant.record(None, &usage(100, 0.1));
+        match accountant.check(None, 1).unwrap_err().reason() {
+            OrionAiReason::Ai(AiErrReason::CostLimitError(used, budget)) => {
+                assert!((used - 0.5).abs() < 1e-9);
+                assert_eq!(*budget, 0.5);
+            }
+            other => panic!("unexpected reason: {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_usage_persisted_in_background() {
+        let dir = tempfile::tempdir().unwrap();
+        let limits = UsageLimits {
+            usage_file: Some(dir.path().join("ai-usage.json")),
+            ..UsageLimits::default()
+        };
+        let accountant = UsageAccountant::new(limits.clone()).unwrap();
+        for _ in 0..3 {
+            accountant.record(None, &usage(100, 0.0));
+        }
+        accountant.flush();
+
+        let restarted = UsageAccountant::new(limits).unwrap();
+        assert_eq!(restarted.today_usage().tokens, 300);
+    }
+
+    #[test]
+    fn test_processes_sharing_usage_file_merge_counts() {
+        let dir = tempfile::tempdir().unwrap();
+        let limits = UsageLimits {
+            usage_file: Some(dir.path().join("ai-usage.json")),
+            ..UsageLimits::default()
+        };
+        let role = AiRoleID::new("developer");
+        // 两个同时运行的进程各自从同一份文件起步
+        let first = UsageAccountant::new(limits.clone()).unwrap();
+        let second = UsageAccountant::new(limits.clone()).unwrap();
+
+        first.record(Some(&role), &usage(300, 0.1));
+        first.flush();
+        second.record(Some(&role), &usage(200, 0.1));
+        second.flush();
+
+        // 合并写入后能看到其他进程的用量
+        assert_eq!(second.today_usage().tokens, 500);
+        assert_eq!(second.role_usage(&role).tokens, 500);
+        let restarted = UsageAccountant::new(limits).unwrap();
+        assert_eq!(restarted.today_usage().requests, 2);
+        assert_eq!(restarted.today_usage().tokens, 500);
+
+        let leftovers: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .filter(|name| name.ends_with(".tmp"))
+            .collect();
+        assert!(leftovers.is_empty(), "{leftovers:?}");
+    }
+
+    #[test]
+    fn test_role_budget_per_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let limits = UsageLimits {
+            role_token_budgets: HashMap::from([("developer".to_string(), 300)]),
+            role_budget_period: BudgetPeriod::Session,
+            usage_file: Some(dir.path().join("ai-usage.json")),
+            ..UsageLimits::default()
+        };
+        let role = AiRoleID::new("developer");
+
+        let accountant = UsageAccountant::new(limits.clone()).unwrap();
+        accountant.record(Some(&role), &usage(250, 0.0));
+        assert_eq!(token_limit(accountant.check(Some(&role), 51)), (301, 300));
+        accountant.flush();
+
+        // 新会话的角色预算重新计算，当日总量仍然累计
+        let restarted = UsageAccountant::new(limits).unwrap();
+        restarted.check(Some(&role), 300).unwrap();
+        assert_eq!(restarted.role_usage(&role), UsageCounter::default());
+        assert_eq!(restarted.today_usage().tokens, 250);
+    }
+
+    #[test]
+    fn test_counters_reset_on_new_day() {
+        let mut state = UsageState::default();
+        state.roll_over("2026-01-01");
+        state.daily.total.add(&usage(100, 0.0));
+        state.roll_over("2026-01-01");
+        assert_eq!(state.daily.total.tokens, 100);
+        state.roll_over("2026-01-02");
+        assert_eq!(state.daily.total, UsageCounter::default());
+        assert_eq!(state.daily.date, "2026-01-02");
+    }
+}
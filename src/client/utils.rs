@@ -1 +1,7 @@
+use crate::provider::AiResponse;
+use crate::roleid::AiRoleID;
 
+/// 在角色请求的响应前标注角色
+pub fn tag_role_response(role: &AiRoleID, response: &mut AiResponse) {
+    response.content = format!("[角色: {}]\n\n{}", role.description(), response.content);
+}
@@ -0,0 +1,248 @@
+//! 模型目录：上下文窗口、能力和价格
+//!
+//! 内置目录随 crate 打包，依次叠加用户级 `~/.galaxy/ai-models.yml`
+//! 和项目级 `_gal/ai-models.yml`，同一 provider 下的同名模型以后加载的为准。
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+use log::{info, warn};
+use orion_error::{ToStructError, UvsConfFrom};
+use serde::{Deserialize, Serialize};
+
+use crate::config::utils::first_parent_file;
+use crate::const_val::gxl_const::{AI_MODELS_FILE, PRJ_AI_MODELS_PATH};
+use crate::error::{AiResult, OrionAiReason};
+use crate::provider::{AiProviderType, ModelInfo};
+
+const BUNDLED_CATALOG: &str = include_str!("models.yml");
+
+/// 模型目录
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModelCatalog {
+    #[serde(default)]
+    pub models: Vec<ModelInfo>,
+}
+
+impl ModelCatalog {
+    /// 内置目录
+    pub fn bundled() -> Self {
+        Self::from_yaml_str(BUNDLED_CATALOG).expect("bundled model catalog is valid")
+    }
+
+    pub fn from_yaml_str(content: &str) -> AiResult<Self> {
+        serde_yaml::from_str(content)
+            .map_err(|e| OrionAiReason::from_conf(format!("model catalog: {e}")).to_err())
+    }
+
+    pub fn from_file(path: &Path) -> AiResult<Self> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            OrionAiReason::from_conf(format!("read {}: {e}", path.display())).to_err()
+        })?;
+        Self::from_yaml_str(&content)
+    }
+
+    /// 分层加载：内置 < 用户级 < 项目级
+    pub fn layered_load() -> AiResult<Self> {
+        let mut catalog = Self::bundled();
+        let user_file = dirs::home_dir().map(|home| home.join(".galaxy").join(AI_MODELS_FILE));
+        for path in [user_file, first_parent_file(PRJ_AI_MODELS_PATH)]
+            .into_iter()
+            .flatten()
+            .filter(|path| path.exists())
+        {
+            info!("load model catalog {}", path.display());
+            catalog.merge(Self::from_file(&path)?);
+        }
+        Ok(catalog)
+    }
+
+    /// 全局模型目录，首次使用时分层加载，加载失败时退回内置目录
+    pub fn global() -> &'static ModelCatalog {
+        static CATALOG: OnceLock<ModelCatalog> = OnceLock::new();
+        CATALOG.get_or_init(|| {
+            Self::layered_load().unwrap_or_else(|e| {
+                warn!("failed to load model catalog overrides: {e}");
+                Self::bundled()
+            })
+        })
+    }
+
+    /// 合并另一个目录，同一 provider 下的同名模型被覆盖
+    pub fn merge(&mut self, other: ModelCatalog) {
+        for model in other.models {
+            match self
+                .models
+                .iter_mut()
+                .find(|m| m.provider == model.provider && m.name == model.name)
+            {
+                Some(existing) => *existing = model,
+                None => self.models.push(model),
+            }
+        }
+    }
+
+    /// 查找模型信息
+    pub fn get(&self, provider: AiProviderType, model: &str) -> Option<&ModelInfo> {
+        self.models
+            .iter()
+            .find(|m| m.provider == provider && m.name == model)
+    }
+
+    /// 某个 provider 的全部模型
+    pub fn models_of(&self, provider: AiProviderType) -> Vec<ModelInfo> {
+        self.models
+            .iter()
+            .filter(|m| m.provider == provider)
+            .cloned()
+            .collect()
+    }
+
+    /// 按目录价格估算费用，未知模型返回 `None`
+    pub fn estimate_cost(
+        &self,
+        provider: AiProviderType,
+        model: &str,
+        input_tokens: usize,
+        output_tokens: usize,
+    ) -> Option<f64> {
+        self.get(provider, model)
+            .map(|info| info.estimate_cost(input_tokens, output_tokens))
+    }
+
+    /// 估算费用，目录中未登记的模型按该 provider 最贵的模型估价并告警
+    ///
+    /// 宁可高估也不让费用预算因未知模型失效；provider 没有任何登记模型时返回 `None`
+    pub fn estimate_cost_or_default(
+        &self,
+        provider: AiProviderType,
+        model: &str,
+        input_tokens: usize,
+        output_tokens: usize,
+    ) -> Option<f64> {
+        if let Some(cost) = self.estimate_cost(provider, model, input_tokens, output_tokens) {
+            return Some(cost);
+        }
+        let fallback = self
+            .models
+            .iter()
+            .filter(|m| m.provider == provider)
+            .max_by(|a, b| {
+                (a.cost_per_1k_input + a.cost_per_1k_output)
+                    .total_cmp(&(b.cost_per_1k_input + b.cost_per_1k_output))
+            });
+        warn_unpriced(provider, model, fallback.map(|info| info.name.as_str()));
+        fallback.map(|info| info.estimate_cost(input_tokens, output_tokens))
+    }
+
+    /// 检查 `max_tokens` 是否在模型上下文窗口内，未知模型不做限制
+    pub fn check_token_limit(
+        &self,
+        provider: AiProviderType,
+        model: &str,
+        max_tokens: usize,
+    ) -> bool {
+        self.get(provider, model)
+            .is_none_or(|info| max_tokens <= info.max_tokens)
+    }
+}
+
+/// 每个未登记的模型只告警一次
+fn warn_unpriced(provider: AiProviderType, model: &str, fallback: Option<&str>) {
+    static WARNED: OnceLock<Mutex<HashSet<String>>> = OnceLock::new();
+    let key = format!("{provider}/{model}");
+    if !WARNED
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap()
+        .insert(key)
+    {
+        return;
+    }
+    match fallback {
+        Some(fallback) => warn!(
+            "{provider} model {model} is not in the model catalog, estimating cost with {fallback} prices"
+        ),
+        None => warn!("{provider} model {model} is not in the model catalog, cost is unknown"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundled_catalog() {
+        let catalog = ModelCatalog::bundled();
+        let mini = catalog.get(AiProviderType::OpenAi, "gpt-4o-mini").unwrap();
+        assert_eq!(mini.max_tokens, 128000);
+        assert!(!mini.supports_reasoning);
+        assert!(
+            catalog
+                .get(AiProviderType::Kimi, "kimi-k2-0711-preview")
+                .is_some()
+        );
+        assert!(catalog.get(AiProviderType::OpenAi, "glm-4.5").is_none());
+        assert!(catalog.get(AiProviderType::Glm, "glm-4.5").is_some());
+
+        let cost = catalog
+            .estimate_cost(AiProviderType::Anthropic, "claude-sonnet-4-0", 1000, 1000)
+            .unwrap();
+        assert!((cost - 0.018).abs() < 1e-9);
+        assert_eq!(
+            catalog.estimate_cost(AiProviderType::OpenAi, "unknown-model", 1000, 1000),
+            None
+        );
+        // 未登记的模型按该 provider 最贵的模型估价
+        let priciest = catalog
+            .models_of(AiProviderType::OpenAi)
+            .into_iter()
+            .map(|m| m.estimate_cost(1000, 1000))
+            .fold(0.0, f64::max);
+        assert_eq!(
+            catalog.estimate_cost_or_default(AiProviderType::OpenAi, "unknown-model", 1000, 1000),
+            Some(priciest)
+        );
+        assert_eq!(
+            catalog.estimate_cost_or_default(AiProviderType::custom("unpriced"), "any", 1000, 1000),
+            None
+        );
+        assert!(catalog.check_token_limit(AiProviderType::Groq, "llama3-70b-8192", 8192));
+        assert!(!catalog.check_token_limit(AiProviderType::Groq, "llama3-70b-8192", 8193));
+        assert!(catalog.check_token_limit(AiProviderType::OpenAi, "unknown-model", 1_000_000));
+    }
+
+    #[test]
+    fn test_merge_overrides() {
+        let mut catalog = ModelCatalog::bundled();
+        let count = catalog.models.len();
+        let overrides = ModelCatalog::from_yaml_str(
+            r#"
+models:
+  - name: gpt-4o
+    provider: OpenAi
+    max_tokens: 64000
+    cost_per_1k_input: 0.0025
+    cost_per_1k_output: 0.01
+  - name: qwen-max
+    provider: OpenAi
+    max_tokens: 32768
+"#,
+        )
+        .unwrap();
+        catalog.merge(overrides);
+
+        assert_eq!(catalog.models.len(), count + 1);
+        let gpt4o = catalog.get(AiProviderType::OpenAi, "gpt-4o").unwrap();
+        assert_eq!(gpt4o.max_tokens, 64000);
+        assert!(!gpt4o.supports_images);
+        assert_eq!(
+            catalog
+                .get(AiProviderType::OpenAi, "qwen-max")
+                .unwrap()
+                .cost_per_1k_input,
+            0.0
+        );
+    }
+}
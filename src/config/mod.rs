@@ -1,3 +1,4 @@
+pub mod catalog;
 pub mod loader;
 pub mod roles;
 pub mod structures;
@@ -9,8 +10,11 @@ pub use traits::*;
 #[cfg(test)]
 pub mod tests;
 // 重新导出主要的类型和函数，保持向后兼容
+pub use self::catalog::ModelCatalog;
 pub use self::loader::ConfigLoader;
 pub use self::roles::{RoleConfig, RoleConfigLoader, RoleConfigManager, RulesConfig};
 pub use self::structures::{
-    AiConfig, FileConfig, ProviderConfig, RoutingRules, ThreadConfig, UsageLimits,
+    AiConfig, BudgetAction, BudgetPeriod, CompatConfig, ContextOverflow, FailoverConfig,
+    FileConfig, JsonlRotation, ProviderConfig, RetryPolicy, RouteRule, RoutingRules, ThreadConfig,
+    ThreadFormat, UsageLimits,
 };
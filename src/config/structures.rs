@@ -7,6 +7,7 @@ use std::collections::HashMap;
 
 use std::env::home_dir;
 use std::path::PathBuf;
+use std::time::Duration;
 
 use crate::AiResult;
 use crate::config::utils::first_parent_file;
@@ -22,6 +23,8 @@ pub struct AiConfig {
     pub limits: UsageLimits,
     #[serde(default = "default_thread_config")]
     pub thread: ThreadConfig,
+    #[serde(default)]
+    pub failover: FailoverConfig,
 }
 
 impl EnvEvalable<AiConfig> for AiConfig {
@@ -36,6 +39,7 @@ impl EnvEvalable<AiConfig> for AiConfig {
             routing,
             limits,
             thread,
+            failover: self.failover,
         }
     }
 }
@@ -92,6 +96,27 @@ impl AiConfig {
 
         Ok(())
     }
+
+    /// 解析模型别名
+    ///
+    /// 在已启用 provider 的 `model_aliases` 中查找，返回别名所在的 provider 和实际模型名；
+    /// 多个 provider 定义了同名别名时取 `priority` 最小的。
+    pub fn resolve_model_alias(&self, alias: &str) -> Option<(AiProviderType, &str)> {
+        self.providers
+            .iter()
+            .filter(|(_, config)| config.enabled)
+            .filter_map(|(provider, config)| {
+                let model = config.model_aliases.as_ref()?.get(alias)?;
+                Some((
+                    config.priority.unwrap_or(u32::MAX),
+                    *provider,
+                    model.as_str(),
+                ))
+            })
+            .min_by_key(|(priority, provider, _)| (*priority, provider.to_string()))
+            .map(|(_, provider, model)| (provider, model))
+    }
+
     pub fn galaxy_load(dict: &EnvDict) -> AiResult<Self> {
         let galaxy_dir = home_dir()
             .ok_or_else(|| OrionAiReason::from_res("Cannot find home directory"))?
@@ -123,6 +148,8 @@ impl AiConfig {
                 timeout: 30,
                 model_aliases: None,
                 priority: Some(1),
+                retry: RetryPolicy::default(),
+                compat: None,
             },
         );
 
@@ -136,6 +163,8 @@ impl AiConfig {
                 timeout: 30,
                 model_aliases: None,
                 priority: Some(2),
+                retry: RetryPolicy::default(),
+                compat: None,
             },
         );
 
@@ -149,6 +178,8 @@ impl AiConfig {
                 timeout: 30,
                 model_aliases: None,
                 priority: Some(3),
+                retry: RetryPolicy::default(),
+                compat: None,
             },
         );
 
@@ -162,6 +193,8 @@ impl AiConfig {
                 timeout: 30,
                 model_aliases: None,
                 priority: Some(4),
+                retry: RetryPolicy::default(),
+                compat: None,
             },
         );
 
@@ -170,6 +203,7 @@ impl AiConfig {
             routing: RoutingRules::default(),
             limits: UsageLimits::default(),
             thread: ThreadConfig::default(),
+            failover: FailoverConfig::default(),
         }
     }
 
@@ -187,6 +221,8 @@ impl AiConfig {
                 timeout: 30,
                 model_aliases: None,
                 priority: Some(1),
+                retry: RetryPolicy::default(),
+                compat: None,
             },
         );
 
@@ -199,6 +235,8 @@ impl AiConfig {
                 timeout: 30,
                 model_aliases: None,
                 priority: Some(2),
+                retry: RetryPolicy::default(),
+                compat: None,
             },
         );
 
@@ -211,6 +249,8 @@ impl AiConfig {
                 timeout: 30,
                 model_aliases: None,
                 priority: Some(3),
+                retry: RetryPolicy::default(),
+                compat: None,
             },
         );
 
@@ -223,6 +263,8 @@ impl AiConfig {
                 timeout: 30,
                 model_aliases: None,
                 priority: Some(999),
+                retry: RetryPolicy::default(),
+                compat: None,
             },
         );
 
@@ -235,6 +277,8 @@ impl AiConfig {
                 timeout: 30,
                 model_aliases: None,
                 priority: Some(4),
+                retry: RetryPolicy::default(),
+                compat: None,
             },
         );
 
@@ -247,6 +291,8 @@ impl AiConfig {
                 timeout: 30,
                 model_aliases: None,
                 priority: Some(5),
+                retry: RetryPolicy::default(),
+                compat: None,
             },
         );
 
@@ -255,6 +301,7 @@ impl AiConfig {
             routing: RoutingRules::default(),
             limits: UsageLimits::default(),
             thread: ThreadConfig::default(),
+            failover: FailoverConfig::default(),
         }
     }
 
@@ -277,6 +324,7 @@ impl AiConfig {
                 AiProviderType::Groq => std::env::var("GROQ_API_KEY").ok(),
                 AiProviderType::Kimi => std::env::var("KIMI_API_KEY").ok(),
                 AiProviderType::Glm => std::env::var("GLM_API_KEY").ok(),
+                AiProviderType::Custom(_) => None,
             }
         }
     }
@@ -312,6 +360,12 @@ pub struct ProviderConfig {
     pub timeout: u64,
     pub model_aliases: Option<HashMap<String, String>>,
     pub priority: Option<u32>,
+    /// HTTP 请求重试策略
+    #[serde(default)]
+    pub retry: RetryPolicy,
+    /// OpenAI 兼容接口的接入设置，`custom:<name>` 类型的 provider 使用
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compat: Option<CompatConfig>,
 }
 
 impl EnvEvalable<ProviderConfig> for ProviderConfig {
@@ -327,6 +381,8 @@ impl EnvEvalable<ProviderConfig> for ProviderConfig {
             timeout: self.timeout,
             model_aliases,
             priority: self.priority,
+            retry: self.retry,
+            compat: self.compat.map(|compat| compat.env_eval(dict)),
         }
     }
 }
@@ -349,12 +405,141 @@ impl ProviderConfig {
     }
 }
 
+/// OpenAI 兼容接口的接入设置
+///
+/// 用于 vLLM、LM Studio、OpenRouter 或内部网关等无需单独适配的服务。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CompatConfig {
+    /// 携带密钥的请求头
+    pub auth_header: String,
+    /// 密钥前缀，为空时直接发送密钥
+    pub auth_scheme: String,
+    /// 附加请求头，值支持变量替换
+    pub headers: HashMap<String, String>,
+    /// 路由到该 provider 的模型名前缀
+    pub model_prefixes: Vec<String>,
+}
+
+impl Default for CompatConfig {
+    fn default() -> Self {
+        Self {
+            auth_header: "Authorization".to_string(),
+            auth_scheme: "Bearer".to_string(),
+            headers: HashMap::new(),
+            model_prefixes: Vec::new(),
+        }
+    }
+}
+
+impl EnvEvalable<CompatConfig> for CompatConfig {
+    fn env_eval(self, dict: &EnvDict) -> Self {
+        Self {
+            headers: self
+                .headers
+                .into_iter()
+                .map(|(k, v)| (k, v.env_eval(dict)))
+                .collect(),
+            ..self
+        }
+    }
+}
+
+/// 重试策略
+///
+/// 对 429、5xx 和连接错误按指数退避重试；响应带 `Retry-After` 时以其为准，
+/// 超过 `max_backoff_ms` 则不再重试。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// 最大重试次数（不含首次请求）
+    pub max_retries: u32,
+    /// 首次重试的等待时间（毫秒）
+    pub initial_backoff_ms: u64,
+    /// 单次等待时间上限（毫秒）
+    pub max_backoff_ms: u64,
+    /// 退避倍数
+    pub backoff_multiplier: f64,
+}
+
+impl RetryPolicy {
+    /// 不重试
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Self::default()
+        }
+    }
+
+    /// 第 `attempt` 次重试（从0开始）前的等待时间
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let delay = self.initial_backoff_ms as f64 * self.backoff_multiplier.powi(attempt as i32);
+        Duration::from_millis((delay as u64).min(self.max_backoff_ms))
+    }
+
+    /// 最大等待时间
+    pub fn max_backoff(&self) -> Duration {
+        Duration::from_millis(self.max_backoff_ms)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 2,
+            initial_backoff_ms: 500,
+            max_backoff_ms: 8000,
+            backoff_multiplier: 2.0,
+        }
+    }
+}
+
+/// 故障转移配置
+///
+/// 路由到的 provider 不可用或返回可重试错误时，按 `ProviderConfig.priority`
+/// 依次尝试其他已启用的 provider，模型通过 `model_equivalents` 换算。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FailoverConfig {
+    pub enabled: bool,
+    /// 等价模型分组，同组模型可互相替代，例如 `[gpt-4o, deepseek-chat, glm-4.5]`
+    pub model_equivalents: Vec<Vec<String>>,
+}
+
+impl FailoverConfig {
+    /// 获取与 `model` 等价的模型（不含自身）
+    pub fn equivalents(&self, model: &str) -> Vec<&str> {
+        self.model_equivalents
+            .iter()
+            .filter(|group| group.iter().any(|m| m == model))
+            .flat_map(|group| group.iter())
+            .map(String::as_str)
+            .filter(|m| *m != model)
+            .collect()
+    }
+}
+
+impl Default for FailoverConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            model_equivalents: Vec::new(),
+        }
+    }
+}
+
 /// 路由规则结构
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RoutingRules {
     pub simple: String,
     pub complex: String,
     pub free: String,
+    /// 模型路由规则，按顺序匹配，优先于内置规则
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<RouteRule>,
+    /// 按角色覆盖的路由规则，优先于 `rules`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub roles: HashMap<String, Vec<RouteRule>>,
 }
 
 impl EnvEvalable<RoutingRules> for RoutingRules {
@@ -363,26 +548,122 @@ impl EnvEvalable<RoutingRules> for RoutingRules {
             simple: self.simple.env_eval(dict),
             complex: self.complex.env_eval(dict),
             free: self.free.env_eval(dict),
+            rules: self.rules,
+            roles: self.roles,
+        }
+    }
+}
+
+/// 单条路由规则
+///
+/// `match` 支持精确模型名、glob（`*`/`?`）和以 `re:` 开头的正则表达式。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RouteRule {
+    #[serde(rename = "match")]
+    pub pattern: String,
+    pub provider: AiProviderType,
+}
+
+impl RouteRule {
+    pub fn new(pattern: impl Into<String>, provider: AiProviderType) -> Self {
+        Self {
+            pattern: pattern.into(),
+            provider,
         }
     }
 }
 
 /// 使用限制结构
+///
+/// token 和费用预算为 0 表示不限制。
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UsageLimits {
+    /// 已废弃：请求不区分审查与分析，不参与预算控制，请改用 `role_token_budgets`
     pub review_budget: usize,
+    /// 已废弃：请求不区分审查与分析，不参与预算控制，请改用 `role_token_budgets`
     pub analysis_budget: usize,
+    /// 每日 token 预算
+    #[serde(default)]
+    pub daily_token_budget: usize,
+    /// 单次进程（会话）的 token 预算
+    #[serde(default)]
+    pub session_token_budget: usize,
+    /// 按角色的 token 预算，统计周期由 `role_budget_period` 决定
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub role_token_budgets: HashMap<String, usize>,
+    /// 角色预算的统计周期
+    #[serde(default)]
+    pub role_budget_period: BudgetPeriod,
+    /// 每日费用预算，按 `estimated_cost` 累计
+    #[serde(default)]
+    pub daily_cost_budget: f64,
+    /// 单次进程（会话）的费用预算
+    #[serde(default)]
+    pub session_cost_budget: f64,
+    /// 超出预算时的处理方式
+    #[serde(default)]
+    pub on_exceed: BudgetAction,
+    /// 用量计数持久化文件，未设置时只在内存中计数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage_file: Option<PathBuf>,
+    /// 提示词超出模型上下文窗口时的处理方式
+    #[serde(default)]
+    pub on_context_overflow: ContextOverflow,
 }
 
 impl EnvEvalable<UsageLimits> for UsageLimits {
-    fn env_eval(self, _dict: &EnvDict) -> Self {
+    fn env_eval(self, dict: &EnvDict) -> Self {
         Self {
             review_budget: self.review_budget,
             analysis_budget: self.analysis_budget,
+            daily_token_budget: self.daily_token_budget,
+            session_token_budget: self.session_token_budget,
+            role_token_budgets: self.role_token_budgets,
+            role_budget_period: self.role_budget_period,
+            daily_cost_budget: self.daily_cost_budget,
+            session_cost_budget: self.session_cost_budget,
+            on_exceed: self.on_exceed,
+            on_context_overflow: self.on_context_overflow,
+            usage_file: self
+                .usage_file
+                .map(|path| PathBuf::from(path.to_string_lossy().to_string().env_eval(dict))),
         }
     }
 }
 
+/// 提示词超出上下文窗口时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContextOverflow {
+    /// 发送前直接返回 `TokenLimitError`
+    #[default]
+    Reject,
+    /// 从最早的对话历史开始丢弃，直到放得下
+    Truncate,
+}
+
+/// 超出预算时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetAction {
+    /// 拒绝请求，返回 `TokenLimitError` 或 `CostLimitError`
+    #[default]
+    Reject,
+    /// 改用 `routing.free` 模型继续请求
+    Downgrade,
+}
+
+/// 预算统计周期
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetPeriod {
+    /// 按自然日累计，配置了 `usage_file` 时跨进程共享
+    #[default]
+    Daily,
+    /// 只在本次进程（会话）内累计
+    Session,
+}
+
 /// Default 实现们
 impl Default for ProviderConfig {
     fn default() -> Self {
@@ -393,6 +674,8 @@ impl Default for ProviderConfig {
             timeout: 30,
             model_aliases: None,
             priority: None,
+            retry: RetryPolicy::default(),
+            compat: None,
         }
     }
 }
@@ -403,6 +686,8 @@ impl Default for RoutingRules {
             simple: "gpt-4o-mini".to_string(),
             complex: "gpt-4o".to_string(),
             free: "deepseek-chat".to_string(),
+            rules: Vec::new(),
+            roles: HashMap::new(),
         }
     }
 }
@@ -412,6 +697,15 @@ impl Default for UsageLimits {
         Self {
             review_budget: 2000,
             analysis_budget: 4000,
+            daily_token_budget: 0,
+            session_token_budget: 0,
+            role_token_budgets: HashMap::new(),
+            role_budget_period: BudgetPeriod::Daily,
+            daily_cost_budget: 0.0,
+            session_cost_budget: 0.0,
+            on_exceed: BudgetAction::Reject,
+            usage_file: None,
+            on_context_overflow: ContextOverflow::Reject,
         }
     }
 }
@@ -450,6 +744,48 @@ pub struct ThreadConfig {
     /// 告知AI的通知消息
     #[serde(default = "default_thread_inform_message")]
     pub inform_message: String,
+
+    /// 记录格式
+    #[serde(default)]
+    pub format: ThreadFormat,
+
+    /// JSONL 记录的文件切分方式
+    #[serde(default)]
+    pub jsonl_rotation: JsonlRotation,
+}
+
+/// Thread记录格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThreadFormat {
+    /// 便于阅读的 Markdown 摘要
+    #[default]
+    Markdown,
+    /// 每次交互一行 JSON，包含完整请求、响应、用量和耗时
+    Jsonl,
+    /// 同时写入两种格式
+    Both,
+}
+
+impl ThreadFormat {
+    pub fn writes_markdown(self) -> bool {
+        matches!(self, ThreadFormat::Markdown | ThreadFormat::Both)
+    }
+
+    pub fn writes_jsonl(self) -> bool {
+        matches!(self, ThreadFormat::Jsonl | ThreadFormat::Both)
+    }
+}
+
+/// JSONL 文件切分方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JsonlRotation {
+    /// 每天一个文件
+    #[default]
+    Daily,
+    /// 每个会话一个文件，写入 `sessions/<id>.jsonl`
+    Session,
 }
 
 /// Thread配置的默认值函数
@@ -532,6 +868,8 @@ impl Default for ThreadConfig {
             summary_keywords: default_thread_summary_keywords(),
             inform_ai: default_thread_inform_ai(),
             inform_message: default_thread_inform_message(),
+            format: ThreadFormat::default(),
+            jsonl_rotation: JsonlRotation::default(),
         }
     }
 }
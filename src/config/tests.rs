@@ -265,6 +265,7 @@ fn test_enabled_providers() {
 }
 
 #[test]
+#[allow(deprecated)]
 fn test_budget_checking() {
     let config = AiConfig::example();
 
@@ -274,3 +275,140 @@ fn test_budget_checking() {
     assert!(config.has_review_budget(1000));
     assert!(!config.has_review_budget(3000));
 }
+
+#[test]
+fn test_provider_retry_policy_from_yaml() {
+    let yaml = r#"
+enabled: true
+api_key: key
+base_url: null
+timeout: 30
+model_aliases: null
+priority: 1
+"#;
+    let config: ProviderConfig = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(config.retry, RetryPolicy::default());
+
+    let yaml = format!("{yaml}retry:\n  max_retries: 5\n  initial_backoff_ms: 100\n");
+    let config: ProviderConfig = serde_yaml::from_str(&yaml).unwrap();
+    assert_eq!(config.retry.max_retries, 5);
+    assert_eq!(config.retry.initial_backoff_ms, 100);
+    assert_eq!(config.retry.max_backoff_ms, 8000);
+}
+
+#[test]
+fn test_routing_rules_from_yaml() {
+    let yaml = r#"
+simple: gpt-4o-mini
+complex: gpt-4o
+free: deepseek-chat
+rules:
+  - match: "qwen*"
+    provider: Ollama
+  - match: "re:^moonshot-v1-.*$"
+    provider: Kimi
+roles:
+  developer:
+    - match: "*"
+      provider: DeepSeek
+"#;
+    let routing: RoutingRules = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(
+        routing.rules,
+        vec![
+            RouteRule::new("qwen*", AiProviderType::Ollama),
+            RouteRule::new("re:^moonshot-v1-.*$", AiProviderType::Kimi),
+        ]
+    );
+    assert_eq!(routing.roles["developer"].len(), 1);
+
+    let yaml = "simple: a\ncomplex: b\nfree: c\n";
+    let routing: RoutingRules = serde_yaml::from_str(yaml).unwrap();
+    assert!(routing.rules.is_empty());
+    assert!(routing.roles.is_empty());
+}
+
+#[test]
+fn test_resolve_model_alias() {
+    let mut config = AiConfig::example();
+    for (provider, models) in [
+        (AiProviderType::Glm, ("glm-4.5", 3)),
+        (AiProviderType::DeepSeek, ("deepseek-chat", 2)),
+    ] {
+        let provider_config = config.providers.get_mut(&provider).unwrap();
+        provider_config.enabled = true;
+        provider_config.priority = Some(models.1);
+        provider_config.model_aliases =
+            Some(HashMap::from([("fast".to_string(), models.0.to_string())]));
+    }
+    config
+        .providers
+        .get_mut(&AiProviderType::Glm)
+        .unwrap()
+        .model_aliases
+        .as_mut()
+        .unwrap()
+        .insert("smart".to_string(), "glm-4.5-air".to_string());
+
+    assert_eq!(
+        config.resolve_model_alias("fast"),
+        Some((AiProviderType::DeepSeek, "deepseek-chat"))
+    );
+    assert_eq!(
+        config.resolve_model_alias("smart"),
+        Some((AiProviderType::Glm, "glm-4.5-air"))
+    );
+    assert_eq!(config.resolve_model_alias("gpt-4o"), None);
+
+    // 已禁用的 provider 不参与解析
+    config
+        .providers
+        .get_mut(&AiProviderType::DeepSeek)
+        .unwrap()
+        .enabled = false;
+    assert_eq!(
+        config.resolve_model_alias("fast"),
+        Some((AiProviderType::Glm, "glm-4.5"))
+    );
+}
+
+#[test]
+fn test_custom_provider_from_yaml() {
+    let yaml = r#"
+OpenAi:
+  enabled: false
+  api_key: "${OPENAI_API_KEY}"
+  timeout: 30
+custom:vllm:
+  enabled: true
+  api_key: ""
+  base_url: http://localhost:8000/v1
+  timeout: 60
+  compat:
+    auth_header: api-key
+    auth_scheme: ""
+    headers:
+      X-Team: "${TEAM}"
+    model_prefixes: [qwen, Qwen/]
+"#;
+    let providers: HashMap<AiProviderType, ProviderConfig> = serde_yaml::from_str(yaml).unwrap();
+    let vllm = AiProviderType::custom("vllm");
+    assert_eq!(vllm.to_string(), "custom:vllm");
+    assert!(providers[&AiProviderType::OpenAi].compat.is_none());
+
+    let mut env_dict = EnvDict::new();
+    env_dict.insert("TEAM".to_string(), "ai".into());
+    let config = providers[&vllm].clone().env_eval(&env_dict);
+    let compat = config.compat.unwrap();
+    assert_eq!(compat.auth_header, "api-key");
+    assert_eq!(compat.auth_scheme, "");
+    assert_eq!(compat.headers["X-Team"], "ai");
+    assert_eq!(compat.model_prefixes, vec!["qwen", "Qwen/"]);
+
+    let serialized = serde_yaml::to_string(&providers).unwrap();
+    assert!(serialized.contains("custom:vllm:"));
+    assert!(serialized.contains("OpenAi:"));
+    let compat = CompatConfig::default();
+    assert_eq!(compat.auth_header, "Authorization");
+    assert_eq!(compat.auth_scheme, "Bearer");
+}
@@ -13,9 +13,11 @@ pub trait ConfigExt {
     fn enabled_providers(&self) -> Vec<AiProviderType>;
 
     /// 检查是否有足够的预算用于分析
+    #[deprecated(note = "不参与预算控制，请使用 UsageLimits 的 token/费用预算")]
     fn has_analysis_budget(&self, tokens: usize) -> bool;
 
     /// 检查是否有足够的预算用于审查
+    #[deprecated(note = "不参与预算控制，请使用 UsageLimits 的 token/费用预算")]
     fn has_review_budget(&self, tokens: usize) -> bool;
 }
 
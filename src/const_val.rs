@@ -3,4 +3,6 @@ pub mod gxl_const {
     pub const AI_CONF_FILE: &str = "ai.yml";
     pub const PRJ_AI_CONF_PATH: &str = "_gal/ai.yml";
     pub const PRJ_AI_ROLE_PATH: &str = "_gal/ai-roles.yml";
+    pub const AI_MODELS_FILE: &str = "ai-models.yml";
+    pub const PRJ_AI_MODELS_PATH: &str = "_gal/ai-models.yml";
 }
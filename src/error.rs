@@ -5,6 +5,8 @@ use orion_sec::{OrionSecReason, SecReason};
 use serde_derive::Serialize;
 use thiserror::Error;
 
+use crate::provider::AiResponse;
+
 #[derive(Debug, PartialEq, Serialize, Error, From)]
 pub enum OrionAiReason {
     #[error("{0}")]
@@ -22,14 +24,23 @@ pub enum AiErrReason {
 
     #[error("Token limit exceeded: {0} tokens requested, max {1}")]
     TokenLimitError(usize, usize),
+    /// 已用费用、费用预算
+    #[error("Cost budget exceeded: {0:.4} used, budget {1:.4}")]
+    CostLimitError(f64, f64),
     #[error("Context collection failed: {0}")]
     ContextError(String),
 
     #[error("No suitable provider found for request")]
     NoProviderAvailable,
 
+    #[error("Provider unavailable: {0}")]
+    ProviderUnavailable(String),
+
     #[error("Invalid model specified: {0}")]
     InvalidModel(String),
+    /// provider 不支持 function calling
+    #[error("Function calling not supported by provider: {0}")]
+    FunctionCallingUnsupported(String),
 
     #[error("Sensitive content filtered")]
     SensitiveContentFiltered,
@@ -45,6 +56,28 @@ pub enum AiErrReason {
     PermissionDenied(String),
     #[error("Internal error: {0}")]
     InternalError(String),
+    /// 提供商返回的错误：provider、错误码、错误信息
+    #[error("{0} error [{1}]: {2}")]
+    ProviderError(String, String, String),
+    #[error("Empty response: {0}")]
+    EmptyResponse(String),
+    /// 输出达到 max_tokens 被截断，携带部分响应及其用量
+    #[error(
+        "Output truncated: {} stopped at max_tokens after {} completion tokens",
+        .0.model,
+        .0.usage.completion_tokens
+    )]
+    OutputTruncated(Box<AiResponse>),
+}
+
+impl AiErrReason {
+    /// 是否为暂时性错误，可切换到其他 provider 重试
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            Self::RateLimitError(_) | Self::ProviderUnavailable(_) | Self::NoProviderAvailable
+        )
+    }
 }
 
 impl From<SerdeReason> for OrionAiReason {
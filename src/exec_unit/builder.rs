@@ -14,6 +14,8 @@ pub struct AiExecUnitBuilder {
     role: Option<AiRoleID>,
     tools: Vec<String>,
     timeout: Option<u64>,
+    max_rounds: Option<usize>,
+    token_budget: Option<usize>,
 }
 
 impl AiExecUnitBuilder {
@@ -25,6 +27,8 @@ impl AiExecUnitBuilder {
             role: None,
             tools: Vec::new(),
             timeout: Some(60), // 默认超时60秒
+            max_rounds: None,
+            token_budget: None,
         }
     }
 
@@ -120,6 +124,18 @@ impl AiExecUnitBuilder {
         self
     }
 
+    /// 设置工具调用循环的最大轮次
+    pub fn with_max_rounds(mut self, max_rounds: usize) -> Self {
+        self.max_rounds = Some(max_rounds);
+        self
+    }
+
+    /// 设置工具调用循环的累计token预算
+    pub fn with_token_budget(mut self, token_budget: usize) -> Self {
+        self.token_budget = Some(token_budget);
+        self
+    }
+
     /// 构建执行单元
     ///
     /// # 返回
@@ -145,13 +161,14 @@ impl AiExecUnitBuilder {
         // 设置角色
         let role = self
             .role
+            .clone()
             .unwrap_or_else(|| client.roles().default_role().clone());
 
         // 获取函数注册表
         let registry = client.get_registry_with_tools(&self.tools)?;
 
         // 创建执行单元
-        Ok(AiExecUnit::new(client, role, registry))
+        Ok(self.apply_loop_limits(AiExecUnit::new(client, role, registry)))
     }
 
     /// 构建执行单元，但不验证工具是否存在
@@ -169,6 +186,7 @@ impl AiExecUnitBuilder {
         // 验证必需的配置
         let config = self
             .config
+            .clone()
             .ok_or_else(|| OrionAiReason::from_conf("AI配置未设置".to_string()))?;
 
         // 创建AI客户端
@@ -181,6 +199,7 @@ impl AiExecUnitBuilder {
         // 设置角色
         let role = self
             .role
+            .clone()
             .unwrap_or_else(|| client.roles().default_role().clone());
 
         // 获取函数注册表，忽略工具注册错误
@@ -193,7 +212,15 @@ impl AiExecUnitBuilder {
         };
 
         // 创建执行单元
-        Ok(AiExecUnit::new(client, role, registry))
+        Ok(self.apply_loop_limits(AiExecUnit::new(client, role, registry)))
+    }
+
+    fn apply_loop_limits(&self, mut unit: AiExecUnit) -> AiExecUnit {
+        if let Some(max_rounds) = self.max_rounds {
+            unit.set_max_rounds(max_rounds);
+        }
+        unit.set_token_budget(self.token_budget);
+        unit
     }
 
     /// 从示例配置创建构建器
@@ -218,6 +245,18 @@ mod tests {
         assert!(builder.config.is_none());
         assert!(builder.role.is_none());
         assert!(builder.tools.is_empty());
+        assert!(builder.max_rounds.is_none());
+        assert!(builder.token_budget.is_none());
+    }
+
+    #[test]
+    fn test_builder_with_loop_limits() {
+        let builder = AiExecUnitBuilder::default()
+            .with_max_rounds(3)
+            .with_token_budget(2000);
+
+        assert_eq!(builder.max_rounds, Some(3));
+        assert_eq!(builder.token_budget, Some(2000));
     }
 
     #[test]
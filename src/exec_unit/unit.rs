@@ -1,18 +1,30 @@
 use crate::{
-    AiClient, AiResult, AiRoleID, FunctionResult, client::AiClientTrait,
-    func::registry::FunctionRegistry, types::result::ExecutionResult,
+    AiClient, AiResult, AiRoleID, FunctionCall, FunctionResult,
+    client::AiClientTrait,
+    func::registry::FunctionRegistry,
+    types::result::{ExecutionResult, ExecutionRound},
 };
 use getset::{Getters, MutGetters, Setters, WithSetters};
 
+/// 工具调用循环的默认最大轮次
+pub const DEFAULT_MAX_ROUNDS: usize = 8;
+
+/// AI执行单元
+///
+/// 客户端可以是任意 [`AiClientTrait`] 实现，例如用 `ThreadClient` 记录每一轮工具调用
 #[derive(Getters, MutGetters, Setters, WithSetters)]
 #[getset(get = "pub", set = "pub", get_mut = "pub", set_with = "pub")]
-pub struct AiExecUnit {
-    client: AiClient,
+pub struct AiExecUnit<C: AiClientTrait = AiClient> {
+    client: C,
     role: AiRoleID,
     registry: FunctionRegistry,
+    /// 工具调用循环的最大轮次
+    max_rounds: usize,
+    /// 工具调用循环的累计token预算
+    token_budget: Option<usize>,
 }
 
-impl std::fmt::Debug for AiExecUnit {
+impl<C: AiClientTrait> std::fmt::Debug for AiExecUnit<C> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("AiExecUnit")
             .field("role", &self.role)
@@ -20,12 +32,14 @@ impl std::fmt::Debug for AiExecUnit {
                 "registry",
                 &format!("FunctionRegistry({})", self.registry.get_functions().len()),
             )
-            .field("client", &"AiClient".to_string())
+            .field("client", &std::any::type_name::<C>())
+            .field("max_rounds", &self.max_rounds)
+            .field("token_budget", &self.token_budget)
             .finish()
     }
 }
 
-impl AiExecUnit {
+impl<C: AiClientTrait> AiExecUnit<C> {
     /// 创建新的执行单元
     ///
     /// # 参数
@@ -33,11 +47,13 @@ impl AiExecUnit {
     /// * `client` - AI客户端实例
     /// * `role` - AI角色标识
     /// * `registry` - 函数注册表
-    pub fn new(client: AiClient, role: AiRoleID, registry: FunctionRegistry) -> Self {
+    pub fn new(client: C, role: AiRoleID, registry: FunctionRegistry) -> Self {
         Self {
             client,
             role,
             registry,
+            max_rounds: DEFAULT_MAX_ROUNDS,
+            token_budget: None,
         }
     }
 
@@ -45,45 +61,95 @@ impl AiExecUnit {
         let response = self.client.smart_role_request(&self.role, prompt).await?;
         Ok(ExecutionResult::new(response.content))
     }
+
+    /// 带工具调用的执行循环
+    ///
+    /// 每轮把工具执行结果回传给模型，直到模型不再调用工具，
+    /// 或达到最大轮次/累计token预算为止。每一轮都记录在返回结果的 `rounds` 中，
+    /// 停止原因记录在 `metadata["stop_reason"]`。
     pub async fn execute_with_func(&self, prompt: &str) -> AiResult<ExecutionResult> {
-        let response = self
-            .client
-            .role_funs_request(&self.role, prompt, self.registry().clone_functions())
-            .await?;
-
-        // 立即执行模式 (方案1): 检测到工具调用后立即执行
-        // 注意：这是一个同步执行实现，AI会基于工具结果结束第一轮对话
-        // TODO: 未来可扩展为支持多轮工具调用的链式执行模式
-        let tool_results = if let Some(tool_calls) = &response.tool_calls {
-            let mut results = Vec::new();
-
-            for tool_call in tool_calls {
-                // 使用函数注册表实际执行工具调用
-                let execution_result = self.registry.execute_function(tool_call).await;
-
-                match execution_result {
-                    Ok(result) => {
-                        results.push(FunctionResult {
-                            name: tool_call.function.name.clone(),
-                            result: result.result, // 实际执行结果
-                            error: None,
-                        });
-                    }
-                    Err(e) => {
-                        results.push(FunctionResult {
-                            name: tool_call.function.name.clone(),
-                            result: serde_json::Value::Null,
-                            error: Some(e.to_string()), // 记录错误信息
-                        });
-                    }
-                }
+        let functions = self.registry.clone_functions();
+        let mut request = self.client.build_ai_request(&self.role, prompt)?;
+
+        let mut rounds = Vec::new();
+        let mut all_results = Vec::new();
+        let mut used_tokens = 0usize;
+        let mut content = String::new();
+        let mut stop_reason = "max_rounds";
+
+        while rounds.len() < self.max_rounds {
+            let response = self
+                .client
+                .send_request_with_functions(request.clone(), &functions)
+                .await?;
+            used_tokens += response.usage.total_tokens;
+            content = response.content.clone();
+
+            let tool_calls = response.tool_calls.clone().unwrap_or_default();
+            let executed = self.execute_tool_calls(&tool_calls).await;
+            let tool_results: Vec<FunctionResult> =
+                executed.iter().map(|(_, result)| result.clone()).collect();
+
+            rounds.push(ExecutionRound {
+                round: rounds.len() + 1,
+                content: response.content.clone(),
+                tool_calls,
+                tool_results: tool_results.clone(),
+                usage: response.usage.clone(),
+            });
+            all_results.extend(tool_results);
+
+            if executed.is_empty() {
+                stop_reason = "completed";
+                break;
             }
-            results
-        } else {
-            Vec::new()
-        };
+            if let Some(budget) = self.token_budget
+                && used_tokens >= budget
+            {
+                stop_reason = "token_budget";
+                break;
+            }
+
+            request.push_tool_round(&response, &executed);
+        }
+
+        let round_count = rounds.len();
+        Ok(ExecutionResult::new(format!(
+            "[角色: {}]\n\n{}",
+            self.role.description(),
+            content
+        ))
+        .with_tool_calls(all_results)
+        .with_rounds(rounds)
+        .with_metadata("rounds".to_string(), round_count.to_string())
+        .with_metadata("stop_reason".to_string(), stop_reason.to_string())
+        .with_metadata("total_tokens".to_string(), used_tokens.to_string()))
+    }
+
+    /// 执行一轮中的所有工具调用，失败的调用以错误结果返回给模型
+    async fn execute_tool_calls(
+        &self,
+        tool_calls: &[FunctionCall],
+    ) -> Vec<(FunctionCall, FunctionResult)> {
+        let mut results = Vec::new();
 
-        Ok(ExecutionResult::new(response.content).with_tool_calls(tool_results))
+        for tool_call in tool_calls {
+            // 使用函数注册表实际执行工具调用
+            let result = match self.registry.execute_function(tool_call).await {
+                Ok(result) => FunctionResult {
+                    name: tool_call.function.name.clone(),
+                    result: result.result, // 实际执行结果
+                    error: None,
+                },
+                Err(e) => FunctionResult {
+                    name: tool_call.function.name.clone(),
+                    result: serde_json::Value::Null,
+                    error: Some(e.to_string()), // 记录错误信息
+                },
+            };
+            results.push((tool_call.clone(), result));
+        }
+        results
     }
 
     /// 消费执行单元，返回其组件
@@ -91,7 +157,7 @@ impl AiExecUnit {
     /// # 返回
     ///
     /// 返回包含客户端、角色和函数注册表的元组
-    pub fn into_components(self) -> (AiClient, AiRoleID, FunctionRegistry) {
+    pub fn into_components(self) -> (C, AiRoleID, FunctionRegistry) {
         (self.client, self.role, self.registry)
     }
 }
@@ -99,7 +165,184 @@ impl AiExecUnit {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{client::AiClientBuilder, config::AiConfig};
+    use crate::{
+        FunctionDefinition, FunctionExecutor,
+        client::AiClientBuilder,
+        config::{AiConfig, ProviderConfig},
+        error::{AiErrReason, OrionAiReason},
+        provider::AiProviderType,
+        providers::mock::{MockError, MockProvider, MockRule},
+    };
+    use async_trait::async_trait;
+    use std::{io::Write, sync::Arc, time::Duration};
+
+    struct EchoExecutor;
+
+    #[async_trait]
+    impl FunctionExecutor for EchoExecutor {
+        async fn execute(&self, function_call: &FunctionCall) -> AiResult<FunctionResult> {
+            Ok(FunctionResult {
+                name: function_call.function.name.clone(),
+                result: serde_json::json!({"status": "clean"}),
+                error: None,
+            })
+        }
+
+        fn supported_functions(&self) -> Vec<String> {
+            vec!["git-status".to_string()]
+        }
+
+        fn get_function_schema(&self, _function_name: &str) -> Option<FunctionDefinition> {
+            None
+        }
+    }
+
+    fn mock_exec_unit() -> (AiExecUnit, tempfile::NamedTempFile) {
+        scripted_exec_unit(Vec::new())
+    }
+
+    fn scripted_exec_unit(rules: Vec<MockRule>) -> (AiExecUnit, tempfile::NamedTempFile) {
+        let mut config = AiConfig::example();
+        for (_, provider_config) in config.providers.iter_mut() {
+            provider_config.enabled = false;
+        }
+        config.providers.insert(
+            AiProviderType::Mock,
+            ProviderConfig {
+                api_key: String::new(),
+                priority: Some(999),
+                ..Default::default()
+            },
+        );
+
+        let mut role_file = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            role_file,
+            "default_role:\n  id: tester\ndefault_model: mock\nroles:\n  tester:\n    name: tester\n    description: 测试角色\n    system_prompt: 你是测试助手\n"
+        )
+        .unwrap();
+
+        let client = AiClientBuilder::new(config)
+            .with_role(role_file.path().to_path_buf())
+            .with_mock_provider(Some(Arc::new(MockProvider::with_rules(rules))))
+            .build()
+            .unwrap();
+        let mut registry = FunctionRegistry::new();
+        registry
+            .register_function(FunctionDefinition::new(
+                "git-status",
+                "查看状态",
+                Vec::new(),
+            ))
+            .unwrap();
+        registry
+            .register_executor("git-status".to_string(), Arc::new(EchoExecutor))
+            .unwrap();
+
+        let unit = AiExecUnit::new(client, AiRoleID::new("tester".to_string()), registry);
+        (unit, role_file)
+    }
+
+    #[tokio::test]
+    async fn test_execute_with_func_loops_until_no_tool_calls() {
+        let (unit, _role_file) = mock_exec_unit();
+
+        let result = unit.execute_with_func("run git-status").await.unwrap();
+
+        assert_eq!(result.rounds.len(), 2);
+        assert_eq!(result.rounds[0].tool_calls.len(), 1);
+        assert_eq!(result.rounds[0].tool_results[0].result["status"], "clean");
+        assert!(result.rounds[1].tool_calls.is_empty());
+        assert_eq!(result.tool_calls.len(), 1);
+        assert_eq!(result.metadata["stop_reason"], "completed");
+        assert_eq!(
+            result.total_tokens(),
+            result.metadata["total_tokens"].parse().unwrap()
+        );
+        assert!(result.content.starts_with("[角色: ai-role: tester]"));
+    }
+
+    #[tokio::test]
+    async fn test_execute_with_func_stops_at_max_rounds() {
+        let (unit, _role_file) = mock_exec_unit();
+        let unit = unit.with_max_rounds(1);
+
+        let result = unit.execute_with_func("run git-status").await.unwrap();
+
+        assert_eq!(result.rounds.len(), 1);
+        assert_eq!(result.metadata["stop_reason"], "max_rounds");
+    }
+
+    #[tokio::test]
+    async fn test_execute_with_func_stops_at_token_budget() {
+        let (unit, _role_file) = mock_exec_unit();
+        let unit = unit.with_token_budget(Some(1));
+
+        let result = unit.execute_with_func("run git-status").await.unwrap();
+
+        assert_eq!(result.rounds.len(), 1);
+        assert_eq!(result.metadata["stop_reason"], "token_budget");
+    }
+
+    #[tokio::test]
+    async fn test_execute_with_func_scripted_multi_round() {
+        let (unit, _role_file) = scripted_exec_unit(vec![
+            MockRule::reply("先检查状态")
+                .for_prompt("^deploy")
+                .unwrap()
+                .for_role(AiRoleID::new("tester"))
+                .with_functions(true)
+                .with_tool_call("git-status", serde_json::json!({"path": "."}))
+                .once(),
+            MockRule::reply("再确认一次")
+                .for_prompt("^deploy")
+                .unwrap()
+                .with_tool_call("git-status", serde_json::json!({"path": "src"}))
+                .with_tool_call("git-status", serde_json::json!({"path": "tests"}))
+                .once(),
+            MockRule::reply("部署完成")
+                .for_prompt("^deploy")
+                .unwrap()
+                .with_usage(100, 20),
+        ]);
+
+        let result = unit.execute_with_func("deploy v1").await.unwrap();
+
+        assert_eq!(result.rounds.len(), 3);
+        assert_eq!(result.rounds[1].tool_calls.len(), 2);
+        assert_eq!(
+            result.rounds[1].tool_calls[1].function.arguments,
+            r#"{"path":"tests"}"#
+        );
+        assert_eq!(result.tool_calls.len(), 3);
+        assert_eq!(result.rounds[2].usage.total_tokens, 120);
+        assert_eq!(result.metadata["stop_reason"], "completed");
+        assert!(result.content.ends_with("部署完成"));
+    }
+
+    #[tokio::test]
+    async fn test_execute_with_func_scripted_errors() {
+        let (unit, _role_file) = scripted_exec_unit(vec![
+            MockRule::reply("先检查状态")
+                .with_tool_call("git-status", serde_json::json!({}))
+                .once(),
+            MockRule::fail(MockError::RateLimit).once(),
+            MockRule::fail(MockError::Timeout).with_latency(Duration::from_millis(10)),
+        ]);
+
+        // 第二轮限流，错误直接返回
+        let err = unit.execute_with_func("run").await.unwrap_err();
+        assert!(matches!(
+            err.reason(),
+            OrionAiReason::Ai(AiErrReason::RateLimitError(_))
+        ));
+
+        let err = unit.execute_with_func("run").await.unwrap_err();
+        assert!(matches!(
+            err.reason(),
+            OrionAiReason::Ai(AiErrReason::ProviderUnavailable(_))
+        ));
+    }
 
     #[tokio::test]
     async fn test_exec_unit_creation() {
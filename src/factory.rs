@@ -1,13 +1,15 @@
+use async_trait::async_trait;
 use orion_error::UvsConfFrom;
 
 use crate::client::AiClientBuilder;
 use crate::error::{AiResult, OrionAiReason};
-use crate::provider::AiRequest;
+use crate::provider::{AiRequest, FunctionDefinition};
+use crate::roleid::AiRoleID;
 use crate::thread::recorder::ThreadClient;
 
 use super::AiConfig;
 use super::client::{AiClient, AiClientTrait, AiCoreClient};
-use super::provider::AiResponse;
+use super::provider::{AiResponse, AiResponseStream};
 /// AI客户端枚举，支持静态分发
 pub enum AiClientEnum {
     Basic(Box<AiClient>),
@@ -63,4 +65,81 @@ impl AiClientEnum {
             Self::ThreadRecording(client) => client.as_ref().send_request(request).await,
         }
     }
+
+    /// 发送流式AI请求
+    pub async fn send_request_stream(&self, request: AiRequest) -> AiResult<AiResponseStream> {
+        match self {
+            Self::Basic(client) => client.send_request_stream(request).await,
+            Self::ThreadRecording(client) => client.as_ref().send_request_stream(request).await,
+        }
+    }
+
+    /// 基于角色的智能请求
+    pub async fn smart_role_request(
+        &self,
+        role: &AiRoleID,
+        user_input: &str,
+    ) -> AiResult<AiResponse> {
+        match self {
+            Self::Basic(client) => client.smart_role_request(role, user_input).await,
+            Self::ThreadRecording(client) => client.smart_role_request(role, user_input).await,
+        }
+    }
+
+    /// 基于角色的函数调用请求
+    pub async fn role_funs_request(
+        &self,
+        role: &AiRoleID,
+        user_input: &str,
+        func: Vec<FunctionDefinition>,
+    ) -> AiResult<AiResponse> {
+        match self {
+            Self::Basic(client) => client.role_funs_request(role, user_input, func).await,
+            Self::ThreadRecording(client) => client.role_funs_request(role, user_input, func).await,
+        }
+    }
+}
+
+#[async_trait]
+impl AiClientTrait for AiClientEnum {
+    fn build_ai_request(&self, role: &AiRoleID, user_input: &str) -> AiResult<AiRequest> {
+        match self {
+            Self::Basic(client) => client.build_ai_request(role, user_input),
+            Self::ThreadRecording(client) => client.build_ai_request(role, user_input),
+        }
+    }
+
+    async fn send_request(&self, request: AiRequest) -> AiResult<AiResponse> {
+        AiClientEnum::send_request(self, request).await
+    }
+
+    async fn send_request_with_functions(
+        &self,
+        request: AiRequest,
+        funcs: &[FunctionDefinition],
+    ) -> AiResult<AiResponse> {
+        match self {
+            Self::Basic(client) => client.send_request_with_functions(request, funcs).await,
+            Self::ThreadRecording(client) => {
+                client.send_request_with_functions(request, funcs).await
+            }
+        }
+    }
+
+    async fn send_request_stream(&self, request: AiRequest) -> AiResult<AiResponseStream> {
+        AiClientEnum::send_request_stream(self, request).await
+    }
+
+    async fn smart_role_request(&self, role: &AiRoleID, user_input: &str) -> AiResult<AiResponse> {
+        AiClientEnum::smart_role_request(self, role, user_input).await
+    }
+
+    async fn role_funs_request(
+        &self,
+        role: &AiRoleID,
+        user_input: &str,
+        func: Vec<FunctionDefinition>,
+    ) -> AiResult<AiResponse> {
+        AiClientEnum::role_funs_request(self, role, user_input, func).await
+    }
 }
@@ -19,7 +19,7 @@ pub trait FunctionExecutor: Send + Sync {
 mod tests {
     use std::sync::Arc;
 
-    use crate::func::registry::FunctionRegistry;
+    use crate::{FunctionParameter, func::registry::FunctionRegistry};
 
     use super::*;
     use serde_json::json;
@@ -64,6 +64,7 @@ mod tests {
                     name: self.name.clone(),
                     description: format!("Mock function {}", self.name),
                     parameters: vec![],
+                    schema: None,
                 })
             } else {
                 None
@@ -80,6 +81,7 @@ mod tests {
             name: "test_function".to_string(),
             description: "Test function".to_string(),
             parameters: vec![],
+            schema: None,
         };
 
         assert!(registry.register_function(test_function.clone()).is_ok());
@@ -147,6 +149,45 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[tokio::test]
+    async fn test_execution_validates_arguments() {
+        let mut registry = FunctionRegistry::new();
+        registry
+            .register_function(FunctionDefinition::new(
+                "test_exec",
+                "Test function",
+                vec![FunctionParameter::new("count", "integer", "次数", true)],
+            ))
+            .unwrap();
+        registry
+            .register_executor(
+                "test_exec".to_string(),
+                Arc::new(MockExecutor::new("test_exec")),
+            )
+            .unwrap();
+
+        let mut function_call = FunctionCall {
+            index: Some(0),
+            id: "call_test_003".to_string(),
+            r#type: "function".to_string(),
+            function: crate::provider::FunctionCallInfo {
+                name: "test_exec".to_string(),
+                arguments: "{\"count\":\"two\"}".to_string(),
+            },
+        };
+
+        let err = registry.execute_function(&function_call).await.unwrap_err();
+        match err.reason() {
+            crate::error::OrionAiReason::Ai(crate::AiErrReason::ToolCallError(msg)) => {
+                assert!(msg.contains("`count` expected integer, got string"));
+            }
+            other => panic!("unexpected reason: {other:?}"),
+        }
+
+        function_call.function.arguments = "{\"count\":2}".to_string();
+        assert!(registry.execute_function(&function_call).await.is_ok());
+    }
+
     #[tokio::test]
     async fn test_batch_function_registration() {
         let mut registry = FunctionRegistry::new();
@@ -156,11 +197,13 @@ mod tests {
                 name: "func1".to_string(),
                 description: "Function 1".to_string(),
                 parameters: vec![],
+                schema: None,
             },
             FunctionDefinition {
                 name: "func2".to_string(),
                 description: "Function 2".to_string(),
                 parameters: vec![],
+                schema: None,
             },
         ];
 
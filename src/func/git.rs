@@ -3,27 +3,9 @@ use serde_json::json;
 
 use crate::{
     AiResult, FunctionCall, FunctionDefinition, FunctionExecutor, FunctionParameter,
-    FunctionResult, error::OrionAiReason,
+    FunctionResult, error::OrionAiReason, func::validate::parse_function_arguments,
 };
 
-// 解析函数参数的辅助函数
-fn parse_function_arguments(
-    arguments: &str,
-) -> AiResult<serde_json::Map<String, serde_json::Value>> {
-    if arguments.trim().is_empty() || arguments == "{}" {
-        return Ok(serde_json::Map::new());
-    }
-
-    let parsed: serde_json::Value = serde_json::from_str(arguments).map_err(|e| {
-        OrionAiReason::from_logic(format!("Failed to parse arguments: {}", e)).to_err()
-    })?;
-
-    match parsed {
-        serde_json::Value::Object(map) => Ok(map),
-        _ => Err(OrionAiReason::from_logic("Arguments must be an object".to_string()).to_err()),
-    }
-}
-
 // Git 函数执行器
 pub struct GitFunctionExecutor;
 
@@ -264,7 +246,9 @@ pub fn create_git_functions() -> Vec<FunctionDefinition> {
                 description: "仓库路径，默认为当前目录".to_string(),
                 r#type: "string".to_string(),
                 required: false,
+                schema: None,
             }],
+            schema: None,
         },
         FunctionDefinition {
             name: "git-diff".to_string(),
@@ -275,14 +259,17 @@ pub fn create_git_functions() -> Vec<FunctionDefinition> {
                     description: "Git仓库路径，默认为当前目录".to_string(),
                     r#type: "string".to_string(),
                     required: false,
+                    schema: None,
                 },
                 FunctionParameter {
                     name: "staged".to_string(),
                     description: "是否只显示暂存的变更，默认为false".to_string(),
                     r#type: "boolean".to_string(),
                     required: false,
+                    schema: None,
                 },
             ],
+            schema: None,
         },
         FunctionDefinition {
             name: "git-add".to_string(),
@@ -292,7 +279,9 @@ pub fn create_git_functions() -> Vec<FunctionDefinition> {
                 description: "要添加的文件列表，支持通配符".to_string(),
                 r#type: "array".to_string(),
                 required: true,
+                schema: Some(json!({ "items": { "type": "string" } })),
             }],
+            schema: None,
         },
         FunctionDefinition {
             name: "git-commit".to_string(),
@@ -302,7 +291,9 @@ pub fn create_git_functions() -> Vec<FunctionDefinition> {
                 description: "提交消息".to_string(),
                 r#type: "string".to_string(),
                 required: true,
+                schema: None,
             }],
+            schema: None,
         },
         FunctionDefinition {
             name: "git-push".to_string(),
@@ -313,14 +304,17 @@ pub fn create_git_functions() -> Vec<FunctionDefinition> {
                     description: "远程仓库名称，默认为origin".to_string(),
                     r#type: "string".to_string(),
                     required: false,
+                    schema: None,
                 },
                 FunctionParameter {
                     name: "branch".to_string(),
                     description: "分支名称，默认为当前分支".to_string(),
                     r#type: "string".to_string(),
                     required: false,
+                    schema: None,
                 },
             ],
+            schema: None,
         },
     ]
 }
@@ -675,6 +675,7 @@ mod global_registry_tests {
             name: "test-custom-function".to_string(),
             description: "Test custom function".to_string(),
             parameters: vec![],
+            schema: None,
         };
 
         // 注册函数
@@ -714,6 +715,7 @@ mod global_registry_tests {
                         name: "test-function".to_string(),
                         description: "Test function".to_string(),
                         parameters: vec![],
+                        schema: None,
                     })
                 } else {
                     None
@@ -725,6 +727,7 @@ mod global_registry_tests {
             name: "test-function".to_string(),
             description: "Test function".to_string(),
             parameters: vec![],
+            schema: None,
         };
 
         let executor = Arc::new(TestExecutor);
@@ -777,11 +780,13 @@ mod global_registry_tests {
                         name: "set-function-1".to_string(),
                         description: "Test set function 1".to_string(),
                         parameters: vec![],
+                        schema: None,
                     }),
                     "set-function-2" => Some(FunctionDefinition {
                         name: "set-function-2".to_string(),
                         description: "Test set function 2".to_string(),
                         parameters: vec![],
+                        schema: None,
                     }),
                     _ => None,
                 }
@@ -793,11 +798,13 @@ mod global_registry_tests {
                 name: "set-function-1".to_string(),
                 description: "Test set function 1".to_string(),
                 parameters: vec![],
+                schema: None,
             },
             FunctionDefinition {
                 name: "set-function-2".to_string(),
                 description: "Test set function 2".to_string(),
                 parameters: vec![],
+                schema: None,
             },
         ];
 
@@ -831,6 +838,7 @@ mod global_registry_tests {
             name: "test-unregister".to_string(),
             description: "Test function for unregistration".to_string(),
             parameters: vec![],
+            schema: None,
         };
 
         struct TestUnregisterExecutor;
@@ -855,6 +863,7 @@ mod global_registry_tests {
                         name: "test-unregister".to_string(),
                         description: "Test function".to_string(),
                         parameters: vec![],
+                        schema: None,
                     })
                 } else {
                     None
@@ -892,6 +901,7 @@ mod global_registry_tests {
             name: "auto-init-function".to_string(),
             description: "Auto init test function".to_string(),
             parameters: vec![],
+            schema: None,
         };
 
         // 注册函数（应该自动初始化）
@@ -3,3 +3,5 @@ pub mod git;
 pub mod global;
 pub mod registry;
 pub mod system;
+pub mod typed;
+pub mod validate;
@@ -3,8 +3,13 @@ use std::{collections::HashMap, sync::Arc};
 use orion_error::{ToStructError, UvsLogicFrom};
 
 use crate::{
-    AiResult, FunctionCall, FunctionDefinition, FunctionResult, error::OrionAiReason,
-    func::executor::FunctionExecutor,
+    AiErrReason, AiResult, FunctionCall, FunctionDefinition, FunctionResult,
+    error::OrionAiReason,
+    func::{
+        executor::FunctionExecutor,
+        typed::{TypedTool, TypedToolExecutor},
+        validate::validate_arguments,
+    },
 };
 
 /// 简化的函数注册表
@@ -36,6 +41,14 @@ impl FunctionRegistry {
         Ok(())
     }
 
+    /// 注册类型化工具，同时注册其函数定义和执行器
+    pub fn register_tool<T: TypedTool>(&mut self, tool: T) -> AiResult<()> {
+        let definition = tool.definition();
+        let name = definition.name.clone();
+        self.register_function(definition)?;
+        self.register_executor(name, Arc::new(TypedToolExecutor::new(tool)))
+    }
+
     /// 获取所有函数定义
     pub fn get_functions(&self) -> Vec<&FunctionDefinition> {
         self.functions.values().collect()
@@ -52,11 +65,22 @@ impl FunctionRegistry {
     }
 
     /// 执行函数调用
+    ///
+    /// 已注册函数定义的调用会先按其 JSON Schema 校验参数，
+    /// 校验失败或函数不存在时返回 `ToolCallError`。
     pub async fn execute_function(&self, function_call: &FunctionCall) -> AiResult<FunctionResult> {
-        let executor = self
-            .executors
-            .get(&function_call.function.name)
-            .ok_or_else(|| OrionAiReason::from_logic("TODO: executor not found").to_err())?;
+        let name = &function_call.function.name;
+        let executor = self.executors.get(name).ok_or_else(|| {
+            OrionAiReason::from(AiErrReason::ToolCallError(format!(
+                "unknown function `{}`",
+                name
+            )))
+            .to_err()
+        })?;
+
+        if let Some(function) = self.functions.get(name) {
+            validate_arguments(function, &function_call.function.arguments)?;
+        }
 
         executor.execute(function_call).await
     }
@@ -168,12 +168,15 @@ pub fn create_fs_functions() -> Vec<FunctionDefinition> {
                 description: "要列出的目录路径，默认为当前目录".to_string(),
                 r#type: "string".to_string(),
                 required: false,
+                schema: None,
             }],
+            schema: None,
         },
         FunctionDefinition {
             name: "fs-pwd".to_string(),
             description: "显示当前工作目录".to_string(),
             parameters: vec![],
+            schema: None,
         },
         FunctionDefinition {
             name: "fs-cat".to_string(),
@@ -183,7 +186,9 @@ pub fn create_fs_functions() -> Vec<FunctionDefinition> {
                 description: "要读取的文件路径".to_string(),
                 r#type: "string".to_string(),
                 required: true,
+                schema: None,
             }],
+            schema: None,
         },
         FunctionDefinition {
             name: "fs-find".to_string(),
@@ -194,14 +199,17 @@ pub fn create_fs_functions() -> Vec<FunctionDefinition> {
                     description: "搜索的起始路径，默认为当前目录".to_string(),
                     r#type: "string".to_string(),
                     required: false,
+                    schema: None,
                 },
                 FunctionParameter {
                     name: "pattern".to_string(),
                     description: "文件名模式，支持通配符，默认为 *".to_string(),
                     r#type: "string".to_string(),
                     required: false,
+                    schema: None,
                 },
             ],
+            schema: None,
         },
     ]
 }
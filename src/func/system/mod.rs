@@ -7,6 +7,8 @@ pub use fs::{FileSystemExecutor, create_fs_functions};
 pub use net::{NetworkExecutor, create_net_functions};
 pub use sys::{SystemInfoExecutor, create_sys_functions};
 
+pub use super::validate::parse_function_arguments;
+
 use crate::{AiResult, error::OrionAiReason};
 use orion_error::{ToStructError, UvsLogicFrom};
 use std::time::Duration;
@@ -56,23 +58,3 @@ pub fn validate_and_normalize_path(path: &str) -> AiResult<String> {
 
     Ok(normalized)
 }
-
-/// 解析函数参数的辅助函数
-pub fn parse_function_arguments(
-    arguments: &str,
-) -> AiResult<serde_json::Map<String, serde_json::Value>> {
-    use serde_json::Value;
-
-    if arguments.trim().is_empty() || arguments == "{}" {
-        return Ok(serde_json::Map::new());
-    }
-
-    let parsed: Value = serde_json::from_str(arguments).map_err(|e| {
-        OrionAiReason::from_logic(format!("Failed to parse arguments: {}", e)).to_err()
-    })?;
-
-    match parsed {
-        Value::Object(map) => Ok(map),
-        _ => Err(OrionAiReason::from_logic("Arguments must be an object".to_string()).to_err()),
-    }
-}
@@ -114,20 +114,24 @@ pub fn create_net_functions() -> Vec<FunctionDefinition> {
                 description: "要ping的主机名或IP地址".to_string(),
                 r#type: "string".to_string(),
                 required: true,
+                schema: None,
             },
             FunctionParameter {
                 name: "count".to_string(),
                 description: "ping包数量，默认为4，最大为10".to_string(),
                 r#type: "number".to_string(),
                 required: false,
+                schema: None,
             },
             FunctionParameter {
                 name: "timeout".to_string(),
                 description: "超时时间（秒），默认为10，最大为30".to_string(),
                 r#type: "number".to_string(),
                 required: false,
+                schema: None,
             },
         ],
+        schema: None,
     }]
 }
 
@@ -172,7 +172,9 @@ pub fn create_sys_functions() -> Vec<FunctionDefinition> {
                 description: "是否显示详细信息，默认为false".to_string(),
                 r#type: "boolean".to_string(),
                 required: false,
+                schema: None,
             }],
+            schema: None,
         },
         FunctionDefinition {
             name: "sys-ps".to_string(),
@@ -183,14 +185,17 @@ pub fn create_sys_functions() -> Vec<FunctionDefinition> {
                     description: "按进程名过滤，可选".to_string(),
                     r#type: "string".to_string(),
                     required: false,
+                    schema: None,
                 },
                 FunctionParameter {
                     name: "user".to_string(),
                     description: "按用户名过滤，可选".to_string(),
                     r#type: "string".to_string(),
                     required: false,
+                    schema: None,
                 },
             ],
+            schema: None,
         },
         FunctionDefinition {
             name: "sys-df".to_string(),
@@ -201,14 +206,17 @@ pub fn create_sys_functions() -> Vec<FunctionDefinition> {
                     description: "要检查的路径，默认为当前目录".to_string(),
                     r#type: "string".to_string(),
                     required: false,
+                    schema: None,
                 },
                 FunctionParameter {
                     name: "human_readable".to_string(),
                     description: "是否使用人类可读格式，默认为true".to_string(),
                     r#type: "boolean".to_string(),
                     required: false,
+                    schema: None,
                 },
             ],
+            schema: None,
         },
     ]
 }
@@ -0,0 +1,347 @@
+use async_trait::async_trait;
+use orion_error::ToStructError;
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::{Value, json};
+
+use crate::{
+    AiErrReason, AiResult, FunctionCall, FunctionDefinition, FunctionExecutor, FunctionParameter,
+    FunctionResult, error::OrionAiReason, func::validate::parse_function_arguments,
+};
+
+/// 可映射为 JSON Schema 的参数类型
+pub trait ToolSchema {
+    /// 该类型对应的 JSON Schema
+    fn schema() -> Value;
+
+    /// 作为工具参数时是否必填
+    fn required() -> bool {
+        true
+    }
+}
+
+macro_rules! impl_tool_schema {
+    ($json_type:literal => $($ty:ty),+) => {
+        $(impl ToolSchema for $ty {
+            fn schema() -> Value {
+                json!({ "type": $json_type })
+            }
+        })+
+    };
+}
+
+impl_tool_schema!("string" => String);
+impl_tool_schema!("boolean" => bool);
+impl_tool_schema!("integer" => i8, i16, i32, i64, u8, u16, u32, u64, usize);
+impl_tool_schema!("number" => f32, f64);
+
+impl<T: ToolSchema> ToolSchema for Vec<T> {
+    fn schema() -> Value {
+        json!({ "type": "array", "items": T::schema() })
+    }
+}
+
+impl<T: ToolSchema> ToolSchema for Option<T> {
+    fn schema() -> Value {
+        T::schema()
+    }
+
+    fn required() -> bool {
+        false
+    }
+}
+
+impl ToolSchema for Value {
+    fn schema() -> Value {
+        json!({ "type": "object" })
+    }
+}
+
+/// 工具的输入参数类型，通常由 [`tool_input!`](crate::tool_input) 生成
+pub trait ToolInput: DeserializeOwned + Send {
+    /// 参数列表，用于生成函数定义
+    fn parameters() -> Vec<FunctionParameter>;
+}
+
+/// 由字段类型和文档生成一个函数参数
+pub fn parameter<T: ToolSchema>(name: &str, description: &str) -> FunctionParameter {
+    let mut schema = T::schema();
+    let r#type = schema
+        .as_object_mut()
+        .and_then(|s| s.remove("type"))
+        .and_then(|t| t.as_str().map(str::to_string))
+        .unwrap_or_else(|| "string".to_string());
+
+    let param = FunctionParameter::new(name, r#type, description.trim(), T::required());
+    match schema {
+        Value::Object(extra) if !extra.is_empty() => param.with_schema(Value::Object(extra)),
+        _ => param,
+    }
+}
+
+/// 合并字段的多行文档注释，作为参数描述
+pub fn doc_description(lines: &[&str]) -> String {
+    lines
+        .iter()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 定义工具输入结构体，同时生成 `Deserialize` 和 [`ToolInput`] 实现
+///
+/// 每个字段的文档注释（可多行，也可省略）作为参数描述，`Option<T>` 字段为可选参数；
+/// 字段上的其他属性（如 `#[serde(default)]`）原样保留。
+/// 生成的代码通过本 crate 重新导出的 serde 派生，调用方无需直接依赖 serde。
+///
+/// ```ignore
+/// tool_input! {
+///     pub struct GitAddArgs {
+///         /// 要添加的文件列表
+///         pub files: Vec<String>,
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! tool_input {
+    (@doc doc = $doc:expr) => {
+        $doc
+    };
+    (@doc $($attr:tt)*) => {
+        ""
+    };
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $(
+                $(#[$($fattr:tt)*])*
+                $fvis:vis $field:ident : $fty:ty
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive($crate::__serde::Deserialize)]
+        #[serde(crate = "::orion_ai::__serde")]
+        $vis struct $name {
+            $(
+                $(#[$($fattr)*])*
+                $fvis $field: $fty,
+            )*
+        }
+
+        impl $crate::func::typed::ToolInput for $name {
+            fn parameters() -> Vec<$crate::FunctionParameter> {
+                vec![$($crate::func::typed::parameter::<$fty>(
+                    stringify!($field),
+                    &$crate::func::typed::doc_description(&[
+                        $($crate::tool_input!(@doc $($fattr)*)),*
+                    ]),
+                )),*]
+            }
+        }
+    };
+}
+
+/// 类型化的工具定义
+///
+/// 输入类型负责参数 schema 与解析，输出类型序列化为函数结果，
+/// 通过 [`FunctionRegistry::register_tool`](crate::FunctionRegistry::register_tool) 注册。
+#[async_trait]
+pub trait TypedTool: Send + Sync + 'static {
+    type Input: ToolInput;
+    type Output: Serialize;
+
+    /// 工具名称
+    fn name(&self) -> String;
+
+    /// 工具描述
+    fn description(&self) -> String;
+
+    /// 执行工具
+    async fn call(&self, input: Self::Input) -> AiResult<Self::Output>;
+
+    /// 生成函数定义
+    fn definition(&self) -> FunctionDefinition {
+        FunctionDefinition::new(
+            self.name(),
+            self.description(),
+            <Self::Input as ToolInput>::parameters(),
+        )
+    }
+}
+
+/// 将 [`TypedTool`] 适配为 [`FunctionExecutor`]
+pub struct TypedToolExecutor<T> {
+    tool: T,
+}
+
+impl<T: TypedTool> TypedToolExecutor<T> {
+    pub fn new(tool: T) -> Self {
+        Self { tool }
+    }
+}
+
+#[async_trait]
+impl<T: TypedTool> FunctionExecutor for TypedToolExecutor<T> {
+    async fn execute(&self, function_call: &FunctionCall) -> AiResult<FunctionResult> {
+        let name = self.tool.name();
+        let args = parse_function_arguments(&function_call.function.arguments)?;
+        let input: T::Input = serde_json::from_value(Value::Object(args)).map_err(|e| {
+            OrionAiReason::from(AiErrReason::ToolCallError(format!(
+                "invalid arguments for `{}`: {}",
+                name, e
+            )))
+            .to_err()
+        })?;
+
+        let output = self.tool.call(input).await?;
+        let result = serde_json::to_value(output).map_err(|e| {
+            OrionAiReason::from(AiErrReason::ExecutionError(format!(
+                "failed to serialize `{}` output: {}",
+                name, e
+            )))
+            .to_err()
+        })?;
+
+        Ok(FunctionResult {
+            name,
+            result,
+            error: None,
+        })
+    }
+
+    fn supported_functions(&self) -> Vec<String> {
+        vec![self.tool.name()]
+    }
+
+    fn get_function_schema(&self, function_name: &str) -> Option<FunctionDefinition> {
+        (function_name == self.tool.name()).then(|| self.tool.definition())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FunctionRegistry, provider::FunctionCallInfo};
+
+    crate::tool_input! {
+        #[derive(Debug)]
+        struct EchoArgs {
+            /// 要回显的文本
+            text: String,
+            /// 重复次数
+            times: Option<u32>,
+            /// 附加标签
+            tags: Vec<String>,
+        }
+    }
+
+    crate::tool_input! {
+        struct SearchArgs {
+            /// 搜索关键字，
+            /// 支持正则表达式
+            pattern: String,
+            #[serde(default)]
+            /// 是否区分大小写
+            case_sensitive: bool,
+            limit: Option<usize>,
+        }
+    }
+
+    #[derive(Serialize)]
+    struct EchoOutput {
+        echoed: String,
+        tags: usize,
+    }
+
+    struct EchoTool;
+
+    #[async_trait]
+    impl TypedTool for EchoTool {
+        type Input = EchoArgs;
+        type Output = EchoOutput;
+
+        fn name(&self) -> String {
+            "echo".to_string()
+        }
+
+        fn description(&self) -> String {
+            "回显文本".to_string()
+        }
+
+        async fn call(&self, input: EchoArgs) -> AiResult<EchoOutput> {
+            Ok(EchoOutput {
+                echoed: input.text.repeat(input.times.unwrap_or(1) as usize),
+                tags: input.tags.len(),
+            })
+        }
+    }
+
+    fn echo_call(arguments: &str) -> FunctionCall {
+        FunctionCall {
+            index: Some(0),
+            id: "call_echo".to_string(),
+            r#type: "function".to_string(),
+            function: FunctionCallInfo {
+                name: "echo".to_string(),
+                arguments: arguments.to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_definition_from_input_type() {
+        let definition = EchoTool.definition();
+        assert_eq!(definition.name, "echo");
+        assert_eq!(definition.parameters.len(), 3);
+        assert_eq!(definition.parameters[0].r#type, "string");
+        assert_eq!(definition.parameters[0].description, "要回显的文本");
+        assert!(definition.parameters[0].required);
+        assert_eq!(definition.parameters[1].r#type, "integer");
+        assert!(!definition.parameters[1].required);
+
+        let schema = definition.parameters_schema();
+        assert_eq!(schema["properties"]["tags"]["type"], "array");
+        assert_eq!(schema["properties"]["tags"]["items"]["type"], "string");
+        assert_eq!(schema["required"], json!(["text", "tags"]));
+    }
+
+    #[test]
+    fn test_field_docs_and_attributes() {
+        let params = SearchArgs::parameters();
+        assert_eq!(params[0].description, "搜索关键字，\n支持正则表达式");
+        assert_eq!(params[1].description, "是否区分大小写");
+        assert_eq!(params[1].r#type, "boolean");
+        assert_eq!(params[2].description, "");
+        assert!(!params[2].required);
+
+        let args: SearchArgs = serde_json::from_value(json!({"pattern": "fn main"})).unwrap();
+        assert_eq!(args.pattern, "fn main");
+        assert!(!args.case_sensitive);
+        assert_eq!(args.limit, None);
+    }
+
+    #[tokio::test]
+    async fn test_register_and_execute_typed_tool() {
+        let mut registry = FunctionRegistry::new();
+        registry.register_tool(EchoTool).unwrap();
+        assert!(registry.contains_function("echo"));
+        assert!(registry.supports_function("echo"));
+
+        let result = registry
+            .execute_function(&echo_call(r#"{"text":"ab","times":2,"tags":["x"]}"#))
+            .await
+            .unwrap();
+        assert_eq!(result.name, "echo");
+        assert_eq!(result.result, json!({"echoed": "abab", "tags": 1}));
+
+        let err = registry
+            .execute_function(&echo_call(r#"{"tags":[]}"#))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.reason(),
+            OrionAiReason::Ai(AiErrReason::ToolCallError(_))
+        ));
+    }
+}
@@ -0,0 +1,282 @@
+use orion_error::ToStructError;
+use serde_json::{Map, Value};
+
+use crate::{AiErrReason, AiResult, FunctionDefinition, error::OrionAiReason};
+
+fn tool_call_error<T>(msg: String) -> AiResult<T> {
+    Err(OrionAiReason::from(AiErrReason::ToolCallError(msg)).to_err())
+}
+
+/// 解析函数参数为 JSON 对象，空参数视为空对象
+pub fn parse_function_arguments(arguments: &str) -> AiResult<Map<String, Value>> {
+    parse_arguments_object(arguments).or_else(tool_call_error)
+}
+
+fn parse_arguments_object(arguments: &str) -> Result<Map<String, Value>, String> {
+    if arguments.trim().is_empty() || arguments == "{}" {
+        return Ok(Map::new());
+    }
+
+    let parsed: Value =
+        serde_json::from_str(arguments).map_err(|e| format!("Failed to parse arguments: {}", e))?;
+
+    match parsed {
+        Value::Object(map) => Ok(map),
+        _ => Err("Arguments must be an object".to_string()),
+    }
+}
+
+/// 按函数定义的 JSON Schema 校验参数
+///
+/// 校验 required、type、enum、minimum/maximum，以及嵌套的 properties 和 items。
+/// 所有问题汇总在一个 `ToolCallError` 中返回，便于模型据此修正调用。
+pub fn validate_arguments(
+    function: &FunctionDefinition,
+    arguments: &str,
+) -> AiResult<Map<String, Value>> {
+    let args = match parse_arguments_object(arguments) {
+        Ok(args) => args,
+        Err(msg) => {
+            return tool_call_error(format!(
+                "invalid arguments for `{}`: {}",
+                function.name, msg
+            ));
+        }
+    };
+
+    let mut errors = Vec::new();
+    validate_value(
+        &function.parameters_schema(),
+        &Value::Object(args.clone()),
+        "",
+        &mut errors,
+    );
+
+    if errors.is_empty() {
+        Ok(args)
+    } else {
+        tool_call_error(format!(
+            "invalid arguments for `{}`: {}",
+            function.name,
+            errors.join("; ")
+        ))
+    }
+}
+
+fn validate_value(schema: &Value, value: &Value, path: &str, errors: &mut Vec<String>) {
+    let Some(schema) = schema.as_object() else {
+        return;
+    };
+
+    if let Some(expected) = schema.get("type")
+        && !type_matches(expected, value)
+    {
+        errors.push(format!(
+            "{} expected {}, got {}",
+            display_path(path),
+            type_label(expected),
+            json_type_name(value)
+        ));
+        return;
+    }
+
+    if let Some(Value::Array(options)) = schema.get("enum")
+        && !options.contains(value)
+    {
+        let options: Vec<String> = options.iter().map(|o| o.to_string()).collect();
+        errors.push(format!(
+            "{} must be one of [{}], got {}",
+            display_path(path),
+            options.join(", "),
+            value
+        ));
+    }
+
+    if let Some(number) = value.as_f64() {
+        if let Some(min) = schema.get("minimum").and_then(Value::as_f64)
+            && number < min
+        {
+            errors.push(format!("{} must be >= {}", display_path(path), min));
+        }
+        if let Some(max) = schema.get("maximum").and_then(Value::as_f64)
+            && number > max
+        {
+            errors.push(format!("{} must be <= {}", display_path(path), max));
+        }
+    }
+
+    match value {
+        Value::Object(map) => {
+            let required: Vec<&str> = schema
+                .get("required")
+                .and_then(Value::as_array)
+                .map(|r| r.iter().filter_map(Value::as_str).collect())
+                .unwrap_or_default();
+            for name in &required {
+                if map.get(*name).is_none_or(Value::is_null) {
+                    errors.push(format!(
+                        "missing required argument `{}`",
+                        join_path(path, name)
+                    ));
+                }
+            }
+
+            if let Some(Value::Object(properties)) = schema.get("properties") {
+                for (name, prop_schema) in properties {
+                    match map.get(name) {
+                        // 可选参数传 null 视为未提供
+                        Some(Value::Null) | None => {}
+                        Some(prop_value) => {
+                            validate_value(prop_schema, prop_value, &join_path(path, name), errors)
+                        }
+                    }
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    validate_value(item_schema, item, &format!("{}[{}]", path, i), errors);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn type_matches(expected: &Value, value: &Value) -> bool {
+    match expected {
+        Value::String(t) => single_type_matches(t, value),
+        Value::Array(types) => types
+            .iter()
+            .filter_map(Value::as_str)
+            .any(|t| single_type_matches(t, value)),
+        _ => true,
+    }
+}
+
+fn single_type_matches(expected: &str, value: &Value) -> bool {
+    match expected {
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "boolean" => value.is_boolean(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+fn type_label(expected: &Value) -> String {
+    match expected {
+        Value::String(t) => t.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn json_type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn join_path(path: &str, name: &str) -> String {
+    if path.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", path, name)
+    }
+}
+
+fn display_path(path: &str) -> String {
+    if path.is_empty() {
+        "arguments".to_string()
+    } else {
+        format!("`{}`", path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FunctionParameter, func::git::create_git_functions};
+
+    fn git_function(name: &str) -> FunctionDefinition {
+        create_git_functions()
+            .into_iter()
+            .find(|f| f.name == name)
+            .unwrap()
+    }
+
+    fn tool_call_message(result: AiResult<Map<String, Value>>) -> String {
+        match result.unwrap_err().reason() {
+            OrionAiReason::Ai(AiErrReason::ToolCallError(msg)) => msg.clone(),
+            other => panic!("unexpected reason: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_validate_accepts_valid_arguments() {
+        let args = validate_arguments(&git_function("git-add"), r#"{"files":["a.rs"]}"#).unwrap();
+        assert_eq!(args["files"][0], "a.rs");
+
+        let args = validate_arguments(&git_function("git-status"), "").unwrap();
+        assert!(args.is_empty());
+    }
+
+    #[test]
+    fn test_validate_reports_missing_and_wrong_types() {
+        let msg = tool_call_message(validate_arguments(&git_function("git-add"), "{}"));
+        assert!(msg.contains("git-add"));
+        assert!(msg.contains("missing required argument `files`"));
+
+        let msg = tool_call_message(validate_arguments(
+            &git_function("git-add"),
+            r#"{"files":["a.rs", 3]}"#,
+        ));
+        assert!(msg.contains("`files[1]` expected string, got integer"));
+
+        let msg = tool_call_message(validate_arguments(
+            &git_function("git-diff"),
+            r#"{"staged":"yes"}"#,
+        ));
+        assert!(msg.contains("`staged` expected boolean, got string"));
+    }
+
+    #[test]
+    fn test_validate_enum_and_range() {
+        let function = FunctionDefinition::new(
+            "ping",
+            "ping",
+            vec![
+                FunctionParameter::new("mode", "string", "模式", true)
+                    .with_schema(serde_json::json!({"enum": ["fast", "slow"]})),
+                FunctionParameter::new("count", "integer", "次数", false)
+                    .with_schema(serde_json::json!({"minimum": 1, "maximum": 10})),
+            ],
+        );
+
+        assert!(validate_arguments(&function, r#"{"mode":"fast","count":3}"#).is_ok());
+        let msg = tool_call_message(validate_arguments(
+            &function,
+            r#"{"mode":"turbo","count":0}"#,
+        ));
+        assert!(msg.contains(r#"`mode` must be one of ["fast", "slow"]"#));
+        assert!(msg.contains("`count` must be >= 1"));
+    }
+
+    #[test]
+    fn test_validate_rejects_non_object() {
+        let msg = tool_call_message(validate_arguments(&git_function("git-status"), "[1]"));
+        assert!(msg.contains("Arguments must be an object"));
+        let msg = tool_call_message(validate_arguments(&git_function("git-status"), "{oops"));
+        assert!(msg.contains("Failed to parse arguments"));
+    }
+}
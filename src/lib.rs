@@ -11,6 +11,7 @@ pub mod providers;
 mod roleid;
 pub mod router;
 pub mod thread;
+pub mod tokens;
 pub mod types;
 // 重新导出主要类型和trait
 pub use config::*;
@@ -20,14 +21,21 @@ pub use router::*;
 
 // Function calling 相关导出
 pub use func::global::GlobalFunctionRegistry;
-pub use func::{executor::FunctionExecutor, registry::FunctionRegistry};
-pub use provider::{FunctionCall, FunctionDefinition, FunctionParameter, FunctionResult};
+pub use func::{
+    executor::FunctionExecutor,
+    registry::FunctionRegistry,
+    typed::{ToolInput, ToolSchema, TypedTool},
+};
+pub use provider::{
+    AiResponseStream, AiStreamEvent, FunctionCall, FunctionDefinition, FunctionParameter,
+    FunctionResult,
+};
 
 // 添加方便的重新导出
 // 移除重复的别名导入，避免冲突
 
 // 客户端相关导出
-pub use client::{AiClient, AiClientTrait, AiCoreClient};
+pub use client::{AiClient, AiClientTrait, AiCoreClient, UsageAccountant, UsageCounter};
 pub use error::{AiErrReason, AiError, AiResult, OrionAiReason};
 pub use thread::ThreadConfig;
 pub use thread::recorder::{ThreadClient, ThreadFileManager};
@@ -37,3 +45,9 @@ pub use exec_unit::{AiExecUnit, AiExecUnitBuilder};
 
 // 类型系统相关导出
 pub use types::result::ExecutionResult;
+
+// 供 tool_input! 生成的代码引用，调用方无需直接依赖 serde
+#[doc(hidden)]
+pub use serde as __serde;
+// 使宏生成的 `::orion_ai::` 路径在本 crate 内同样可用
+extern crate self as orion_ai;
@@ -1,17 +1,22 @@
-use crate::error::OrionAiReason;
+use crate::error::{AiErrReason, OrionAiReason};
 use async_trait::async_trait;
 use getset::WithSetters;
 use orion_error::ToStructError;
 
-use orion_error::UvsLogicFrom;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::{Mutex, OnceLock};
 
 use crate::AiResult;
+use crate::providers::stream::stream_from_response;
 
 use super::roleid::AiRoleID;
+use crate::router::TaskTier;
 
 /// AI提供商类型
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Hash)]
+///
+/// 内置类型按变体名序列化；自定义的 OpenAI 兼容 provider 序列化为 `custom:<name>`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum AiProviderType {
     OpenAi,
     Anthropic,
@@ -21,6 +26,99 @@ pub enum AiProviderType {
     Groq,
     Kimi,
     Glm,
+    Custom(CustomName),
+}
+
+/// 自定义 provider 的前缀
+const CUSTOM_PREFIX: &str = "custom:";
+
+/// 通过反序列化（即配置文件）最多可声明的自定义 provider 数量
+pub const MAX_CUSTOM_PROVIDERS: usize = 64;
+
+/// 自定义 provider 名称
+///
+/// 名称连同 `custom:` 前缀驻留为 `&'static str`，使 `AiProviderType` 保持 `Copy`。
+/// 只有声明过的名称会驻留：`AiProviderType::custom` 和配置反序列化负责声明，
+/// `FromStr` 只解析已声明的名称，任意输入不会造成内存增长
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CustomName(&'static str);
+
+static CUSTOM_NAMES: OnceLock<Mutex<HashSet<&'static str>>> = OnceLock::new();
+
+impl CustomName {
+    /// 声明并驻留名称，同名只分配一次
+    pub fn new(name: &str) -> Self {
+        Self::declare(name, usize::MAX).expect("custom provider name")
+    }
+
+    /// 声明名称，已驻留的名称数达到 `limit` 时拒绝新名称
+    fn declare(name: &str, limit: usize) -> Result<Self, String> {
+        let display = format!("{CUSTOM_PREFIX}{name}");
+        let mut names = CUSTOM_NAMES.get_or_init(Default::default).lock().unwrap();
+        if let Some(interned) = names.get(display.as_str()) {
+            return Ok(Self(interned));
+        }
+        if names.len() >= limit {
+            return Err(format!(
+                "too many custom providers (max {limit}), cannot declare {display}"
+            ));
+        }
+        let interned: &'static str = Box::leak(display.into_boxed_str());
+        names.insert(interned);
+        Ok(Self(interned))
+    }
+
+    /// 查找已声明的名称，不会驻留新名称
+    pub fn lookup(name: &str) -> Option<Self> {
+        let display = format!("{CUSTOM_PREFIX}{name}");
+        let names = CUSTOM_NAMES.get_or_init(Default::default).lock().unwrap();
+        names.get(display.as_str()).map(|interned| Self(interned))
+    }
+
+    /// 不含 `custom:` 前缀的名称
+    pub fn as_str(&self) -> &'static str {
+        &self.0[CUSTOM_PREFIX.len()..]
+    }
+}
+
+impl std::fmt::Debug for CustomName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.as_str())
+    }
+}
+
+impl AiProviderType {
+    /// 自定义 provider，声明其名称
+    pub fn custom(name: &str) -> Self {
+        AiProviderType::Custom(CustomName::new(name))
+    }
+
+    /// 按 `Display` 名称解析，不区分大小写；`custom:<name>` 中的名称保持原样，
+    /// 由 `resolve` 决定如何取得自定义名称
+    fn parse_with(
+        s: &str,
+        resolve: impl FnOnce(&str) -> Result<CustomName, String>,
+    ) -> Result<Self, String> {
+        if let Some((prefix, name)) = s.split_once(':')
+            && prefix.eq_ignore_ascii_case("custom")
+        {
+            if name.is_empty() {
+                return Err(format!("empty custom provider name: {s}"));
+            }
+            return resolve(name).map(AiProviderType::Custom);
+        }
+        match s.to_ascii_lowercase().as_str() {
+            "openai" => Ok(AiProviderType::OpenAi),
+            "anthropic" => Ok(AiProviderType::Anthropic),
+            "ollama" => Ok(AiProviderType::Ollama),
+            "mock" => Ok(AiProviderType::Mock),
+            "deepseek" => Ok(AiProviderType::DeepSeek),
+            "groq" => Ok(AiProviderType::Groq),
+            "kimi" => Ok(AiProviderType::Kimi),
+            "glm" => Ok(AiProviderType::Glm),
+            _ => Err(format!("unknown provider: {s}")),
+        }
+    }
 }
 
 impl std::fmt::Display for AiProviderType {
@@ -34,10 +132,22 @@ impl std::fmt::Display for AiProviderType {
             AiProviderType::Groq => write!(f, "groq"),
             AiProviderType::Kimi => write!(f, "kimi"),
             AiProviderType::Glm => write!(f, "glm"),
+            AiProviderType::Custom(name) => f.write_str(name.0),
         }
     }
 }
 
+impl std::str::FromStr for AiProviderType {
+    type Err = String;
+
+    /// 按 `Display` 名称解析，不区分大小写；自定义 provider 须已在配置中声明
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse_with(s, |name| {
+            CustomName::lookup(name).ok_or_else(|| format!("undeclared custom provider: {s}"))
+        })
+    }
+}
+
 impl From<AiProviderType> for &'static str {
     fn from(provider: AiProviderType) -> Self {
         match provider {
@@ -49,22 +159,62 @@ impl From<AiProviderType> for &'static str {
             AiProviderType::Groq => "groq",
             AiProviderType::Kimi => "kimi",
             AiProviderType::Glm => "glm",
+            AiProviderType::Custom(name) => name.0,
         }
     }
 }
 
+impl Serialize for AiProviderType {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            AiProviderType::OpenAi => serializer.serialize_str("OpenAi"),
+            AiProviderType::Anthropic => serializer.serialize_str("Anthropic"),
+            AiProviderType::Ollama => serializer.serialize_str("Ollama"),
+            AiProviderType::Mock => serializer.serialize_str("Mock"),
+            AiProviderType::DeepSeek => serializer.serialize_str("DeepSeek"),
+            AiProviderType::Groq => serializer.serialize_str("Groq"),
+            AiProviderType::Kimi => serializer.serialize_str("Kimi"),
+            AiProviderType::Glm => serializer.serialize_str("Glm"),
+            AiProviderType::Custom(_) => serializer.collect_str(self),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for AiProviderType {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // 反序列化来自配置文件，自定义名称在此声明
+        let name = String::deserialize(deserializer)?;
+        Self::parse_with(&name, |custom| {
+            CustomName::declare(custom, MAX_CUSTOM_PROVIDERS)
+        })
+        .map_err(serde::de::Error::custom)
+    }
+}
+
 /// 模型信息结构
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ModelInfo {
     pub name: String,
     pub provider: AiProviderType,
     pub max_tokens: usize,
+    #[serde(default)]
     pub supports_images: bool,
+    #[serde(default)]
     pub supports_reasoning: bool,
-    pub cost_per_1k_input: f64,  // 美元
+    #[serde(default)]
+    pub cost_per_1k_input: f64, // 美元
+    #[serde(default)]
     pub cost_per_1k_output: f64, // 美元
 }
 
+impl ModelInfo {
+    /// 按单价计算费用
+    pub fn estimate_cost(&self, input_tokens: usize, output_tokens: usize) -> f64 {
+        (input_tokens as f64 * self.cost_per_1k_input / 1000.0)
+            + (output_tokens as f64 * self.cost_per_1k_output / 1000.0)
+    }
+}
+
 /// 统一AI请求结构
 #[derive(Debug, Clone, Serialize, Deserialize, WithSetters)]
 #[getset(set_with = "pub")]
@@ -78,12 +228,167 @@ pub struct AiRequest {
     // 新增：简单的函数调用支持
     pub functions: Option<Vec<FunctionDefinition>>,
     pub enable_function_calling: bool,
+    /// 多轮对话历史，位于 system_prompt 之后、user_prompt 之前
+    #[serde(default)]
+    pub messages: Vec<ChatMessage>,
+    /// 任务分级，设置后由 `RoutingRules` 决定模型，忽略 `model`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tier: Option<TaskTier>,
 }
 
 impl AiRequest {
     pub fn builder() -> AiRequestBuilder {
         AiRequestBuilder::new()
     }
+
+    /// 获取完整的对话消息序列
+    ///
+    /// 顺序为：system_prompt（非空时）、历史消息、user_prompt（非空时）
+    pub fn conversation(&self) -> Vec<ChatMessage> {
+        let mut conversation = Vec::with_capacity(self.messages.len() + 2);
+        if !self.system_prompt.is_empty() {
+            conversation.push(ChatMessage::system(self.system_prompt.clone()));
+        }
+        conversation.extend(self.messages.iter().cloned());
+        if !self.user_prompt.is_empty() {
+            conversation.push(ChatMessage::user(self.user_prompt.clone()));
+        }
+        conversation
+    }
+
+    /// 丢弃最早的一轮对话历史，连同其后孤立的工具结果，返回是否有消息被丢弃
+    pub fn drop_oldest_turn(&mut self) -> bool {
+        if self.messages.is_empty() {
+            return false;
+        }
+        self.messages.remove(0);
+        while self
+            .messages
+            .first()
+            .is_some_and(|m| m.role == ChatRole::Tool)
+        {
+            self.messages.remove(0);
+        }
+        true
+    }
+
+    /// 追加助手回复及其工具调用结果，并清空 user_prompt，用于继续下一轮对话
+    pub fn push_tool_round(
+        &mut self,
+        response: &AiResponse,
+        results: &[(FunctionCall, FunctionResult)],
+    ) {
+        if !self.user_prompt.is_empty() {
+            self.messages
+                .push(ChatMessage::user(std::mem::take(&mut self.user_prompt)));
+        }
+        self.messages.push(ChatMessage::assistant_with_tool_calls(
+            response.content.clone(),
+            response.tool_calls.clone().unwrap_or_default(),
+        ));
+        for (call, result) in results {
+            self.messages.push(ChatMessage::tool_result(call, result));
+        }
+    }
+}
+
+/// 对话消息角色
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum ChatRole {
+    System,
+    User,
+    Assistant,
+    Tool,
+}
+
+impl std::fmt::Display for ChatRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChatRole::System => write!(f, "system"),
+            ChatRole::User => write!(f, "user"),
+            ChatRole::Assistant => write!(f, "assistant"),
+            ChatRole::Tool => write!(f, "tool"),
+        }
+    }
+}
+
+/// 对话消息
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: ChatRole,
+    pub content: String,
+    /// 助手消息发起的工具调用
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_calls: Option<Vec<FunctionCall>>,
+    /// 工具结果对应的调用ID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+    /// 工具结果对应的函数名
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// 工具执行是否失败，失败时 `content` 为 `{"error": ...}`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub is_error: bool,
+}
+
+impl ChatMessage {
+    fn plain(role: ChatRole, content: impl Into<String>) -> Self {
+        Self {
+            role,
+            content: content.into(),
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+            is_error: false,
+        }
+    }
+
+    pub fn system(content: impl Into<String>) -> Self {
+        Self::plain(ChatRole::System, content)
+    }
+
+    pub fn user(content: impl Into<String>) -> Self {
+        Self::plain(ChatRole::User, content)
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self::plain(ChatRole::Assistant, content)
+    }
+
+    pub fn assistant_with_tool_calls(
+        content: impl Into<String>,
+        tool_calls: Vec<FunctionCall>,
+    ) -> Self {
+        let mut message = Self::plain(ChatRole::Assistant, content);
+        if !tool_calls.is_empty() {
+            message.tool_calls = Some(tool_calls);
+        }
+        message
+    }
+
+    /// 工具执行结果消息，通过 tool_call_id 关联到对应的调用
+    pub fn tool(
+        tool_call_id: impl Into<String>,
+        name: impl Into<String>,
+        content: impl Into<String>,
+    ) -> Self {
+        let mut message = Self::plain(ChatRole::Tool, content);
+        message.tool_call_id = Some(tool_call_id.into());
+        message.name = Some(name.into());
+        message
+    }
+
+    /// 由函数调用及其执行结果构建工具消息
+    pub fn tool_result(call: &FunctionCall, result: &FunctionResult) -> Self {
+        let content = match &result.error {
+            Some(error) => serde_json::json!({ "error": error }).to_string(),
+            None => result.result.to_string(),
+        };
+        let mut message = Self::tool(call.id.clone(), call.function.name.clone(), content);
+        message.is_error = result.error.is_some();
+        message
+    }
 }
 
 /// AI请求构建器
@@ -96,6 +401,8 @@ pub struct AiRequestBuilder {
     role: Option<AiRoleID>,
     functions: Option<Vec<FunctionDefinition>>,
     enable_function_calling: bool,
+    messages: Vec<ChatMessage>,
+    tier: Option<TaskTier>,
 }
 
 impl Default for AiRequestBuilder {
@@ -115,6 +422,8 @@ impl AiRequestBuilder {
             role: None,
             functions: None,
             enable_function_calling: false,
+            messages: Vec::new(),
+            tier: None,
         }
     }
 
@@ -158,6 +467,21 @@ impl AiRequestBuilder {
         self
     }
 
+    pub fn messages(mut self, messages: Vec<ChatMessage>) -> Self {
+        self.messages = messages;
+        self
+    }
+
+    pub fn message(mut self, message: ChatMessage) -> Self {
+        self.messages.push(message);
+        self
+    }
+
+    pub fn tier(mut self, tier: TaskTier) -> Self {
+        self.tier = Some(tier);
+        self
+    }
+
     pub fn build(self) -> AiRequest {
         AiRequest {
             model: self.model,
@@ -168,12 +492,14 @@ impl AiRequestBuilder {
             role: self.role,
             functions: self.functions,
             enable_function_calling: self.enable_function_calling,
+            messages: self.messages,
+            tier: self.tier,
         }
     }
 }
 
 /// 统一AI响应结构
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AiResponse {
     pub content: String,
     pub model: String,
@@ -185,7 +511,29 @@ pub struct AiResponse {
     pub tool_calls: Option<Vec<FunctionCall>>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// 流式响应中的增量事件
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum AiStreamEvent {
+    /// 文本片段
+    TextDelta(String),
+    /// 工具调用片段，按 index 聚合；arguments 为参数 JSON 的增量片段
+    ToolCallDelta {
+        index: u32,
+        id: Option<String>,
+        name: Option<String>,
+        arguments: String,
+    },
+    /// 最终用量
+    Usage(UsageInfo),
+    /// 结束原因
+    Finish(Option<String>),
+}
+
+/// 流式响应类型
+pub type AiResponseStream =
+    std::pin::Pin<Box<dyn futures_util::Stream<Item = AiResult<AiStreamEvent>> + Send>>;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct UsageInfo {
     pub prompt_tokens: usize,
     pub completion_tokens: usize,
@@ -208,6 +556,21 @@ pub trait AiProvider: Send + Sync {
     /// 发送AI请求
     async fn send_request(&self, request: &AiRequest) -> AiResult<AiResponse>;
 
+    /// 发送流式请求
+    ///
+    /// 默认实现发送一次性请求，再把完整响应拆成合成的事件流
+    async fn send_request_stream(&self, request: &AiRequest) -> AiResult<AiResponseStream> {
+        let response = match &request.functions {
+            Some(functions)
+                if request.enable_function_calling && self.supports_function_calling() =>
+            {
+                self.send_request_with_functions(request, functions).await?
+            }
+            _ => self.send_request(request).await?,
+        };
+        Ok(stream_from_response(response))
+    }
+
     /// 获取配置参数
     fn get_config_keys(&self) -> Vec<&'static str> {
         vec![]
@@ -221,9 +584,14 @@ pub trait AiProvider: Send + Sync {
     /// 计算预估成本
     fn estimate_cost(&self, model: &str, input_tokens: usize, output_tokens: usize) -> Option<f64>;
 
-    /// 检查token限制
+    /// 检查请求所需 token 是否在模型上下文窗口内，发送前由 `preflight` 调用
     fn check_token_limit(&self, model: &str, max_tokens: usize) -> bool;
 
+    /// 获取模型信息，未知模型返回 `None`
+    fn get_model_info(&self, _model: &str) -> Option<ModelInfo> {
+        None
+    }
+
     /// 检查是否支持 function calling
     fn supports_function_calling(&self) -> bool {
         false
@@ -235,7 +603,10 @@ pub trait AiProvider: Send + Sync {
         _request: &AiRequest,
         _functions: &[FunctionDefinition],
     ) -> AiResult<AiResponse> {
-        Err(OrionAiReason::from_logic("TODO: function calling not supported".to_string()).to_err())
+        Err(OrionAiReason::from(AiErrReason::FunctionCallingUnsupported(
+            self.provider_type().to_string(),
+        ))
+        .to_err())
     }
 }
 
@@ -246,18 +617,194 @@ pub struct FunctionParameter {
     pub description: String,
     pub r#type: String, // 直接使用字符串类型描述
     pub required: bool,
+    /// 附加的 JSON Schema 关键字（items、enum、default、minimum 等），合并到参数定义中
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<serde_json::Value>,
+}
+
+impl FunctionParameter {
+    pub fn new(
+        name: impl Into<String>,
+        r#type: impl Into<String>,
+        description: impl Into<String>,
+        required: bool,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            description: description.into(),
+            r#type: r#type.into(),
+            required,
+            schema: None,
+        }
+    }
+
+    /// 合并附加的 JSON Schema 关键字
+    pub fn with_schema(mut self, extra: serde_json::Value) -> Self {
+        let serde_json::Value::Object(extra) = extra else {
+            return self;
+        };
+        match &mut self.schema {
+            Some(serde_json::Value::Object(schema)) => schema.extend(extra),
+            _ => self.schema = Some(serde_json::Value::Object(extra)),
+        }
+        self
+    }
+
+    /// 由参数 Schema 中的单个属性创建，非简单类型名的 `type`（如 `["string", "null"]`）保留在 `schema` 中
+    fn from_property(name: String, property: serde_json::Value, required: bool) -> Self {
+        let serde_json::Value::Object(mut property) = property else {
+            return Self::new(name, "string", "", required);
+        };
+        let description = match property.remove("description") {
+            Some(serde_json::Value::String(description)) => description,
+            Some(other) => {
+                property.insert("description".to_string(), other);
+                String::new()
+            }
+            None => String::new(),
+        };
+        let simple_type = property
+            .get("type")
+            .and_then(|t| t.as_str())
+            .filter(|t| normalize_schema_type(t) == *t)
+            .map(str::to_string);
+        if simple_type.is_some() {
+            property.remove("type");
+        }
+        let r#type = simple_type.unwrap_or_else(|| "string".to_string());
+        let mut param = Self::new(name, r#type, description, required);
+        if !property.is_empty() {
+            param.schema = Some(serde_json::Value::Object(property));
+        }
+        param
+    }
+
+    /// 生成该参数的 JSON Schema
+    pub fn to_schema(&self) -> serde_json::Value {
+        let mut schema = serde_json::Map::new();
+        schema.insert(
+            "type".to_string(),
+            serde_json::Value::String(normalize_schema_type(&self.r#type).to_string()),
+        );
+        if !self.description.is_empty() {
+            schema.insert(
+                "description".to_string(),
+                serde_json::Value::String(self.description.clone()),
+            );
+        }
+        if let Some(serde_json::Value::Object(extra)) = &self.schema {
+            schema.extend(extra.clone());
+        }
+        serde_json::Value::Object(schema)
+    }
 }
 
 /// 函数定义 - 简化版本
+///
+/// 简单参数通过 `parameters` 描述；嵌套对象等复杂结构可用
+/// [`FunctionDefinition::from_schema`] 从完整的参数 JSON Schema 创建
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct FunctionDefinition {
     pub name: String,
     pub description: String,
     pub parameters: Vec<FunctionParameter>,
+    /// 参数 Schema 顶层的其他关键字（additionalProperties、$defs 等）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<serde_json::Value>,
+}
+
+impl FunctionDefinition {
+    pub fn new(
+        name: impl Into<String>,
+        description: impl Into<String>,
+        parameters: Vec<FunctionParameter>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            description: description.into(),
+            parameters,
+            schema: None,
+        }
+    }
+
+    /// 使用完整的参数 JSON Schema 创建函数定义
+    ///
+    /// 每个属性拆成一个参数，其余顶层关键字保存在 `schema` 中
+    pub fn from_schema(
+        name: impl Into<String>,
+        description: impl Into<String>,
+        schema: serde_json::Value,
+    ) -> Self {
+        let serde_json::Value::Object(mut schema) = schema else {
+            return Self::new(name, description, Vec::new());
+        };
+        schema.remove("type");
+        let required: Vec<String> = schema
+            .remove("required")
+            .and_then(|names| serde_json::from_value(names).ok())
+            .unwrap_or_default();
+        let parameters = match schema.remove("properties") {
+            Some(serde_json::Value::Object(properties)) => properties
+                .into_iter()
+                .map(|(name, property)| {
+                    let required = required.contains(&name);
+                    FunctionParameter::from_property(name, property, required)
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        let mut function = Self::new(name, description, parameters);
+        if !schema.is_empty() {
+            function.schema = Some(serde_json::Value::Object(schema));
+        }
+        function
+    }
+
+    /// 生成参数的 JSON Schema，供各 provider 转换为工具定义
+    pub fn parameters_schema(&self) -> serde_json::Value {
+        let properties: serde_json::Map<String, serde_json::Value> = self
+            .parameters
+            .iter()
+            .map(|p| (p.name.clone(), p.to_schema()))
+            .collect();
+        let required: Vec<String> = self
+            .parameters
+            .iter()
+            .filter(|p| p.required)
+            .map(|p| p.name.clone())
+            .collect();
+
+        let mut schema = serde_json::json!({
+            "type": "object",
+            "properties": properties,
+            "required": required
+        });
+        if let (Some(schema), Some(serde_json::Value::Object(extra))) =
+            (schema.as_object_mut(), &self.schema)
+        {
+            for (key, value) in extra {
+                schema.entry(key.clone()).or_insert_with(|| value.clone());
+            }
+        }
+        schema
+    }
+}
+
+/// 将简化的类型描述映射为 JSON Schema 类型，未知类型按 string 处理
+fn normalize_schema_type(param_type: &str) -> &'static str {
+    match param_type {
+        "string" => "string",
+        "array" => "array",
+        "number" => "number",
+        "integer" => "integer",
+        "boolean" => "boolean",
+        "object" => "object",
+        _ => "string", // 默认为 string
+    }
 }
 
 /// 函数调用请求 - 匹配 OpenAI 和 DeepSeek API 格式
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct FunctionCall {
     pub index: Option<u32>,
     pub id: String,
@@ -265,7 +812,7 @@ pub struct FunctionCall {
     pub function: FunctionCallInfo,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct FunctionCallInfo {
     pub name: String,
     pub arguments: String,
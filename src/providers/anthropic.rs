@@ -0,0 +1,654 @@
+use async_trait::async_trait;
+use log::debug;
+use orion_error::{ErrorOwe, ErrorWith, ToStructError};
+use reqwest::{Client, header};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::config::{ModelCatalog, RetryPolicy};
+use crate::error::{AiResult, OrionAiReason};
+use crate::provider::*;
+use crate::providers::openai::OpenAiProvider;
+use crate::providers::resp::{parse_error_body, provider_error};
+use crate::providers::retry::send_with_retry;
+use getset::{Getters, MutGetters, Setters};
+
+/// Anthropic Messages API 默认版本
+const ANTHROPIC_API_VERSION: &str = "2023-06-01";
+/// Anthropic 要求必须携带 max_tokens
+const DEFAULT_MAX_TOKENS: usize = 4096;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct AnthropicRequest {
+    model: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<String>,
+    messages: Vec<AnthropicMessage>,
+    max_tokens: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<AnthropicTool>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnthropicMessage {
+    pub role: String,
+    pub content: Vec<AnthropicContentBlock>,
+}
+
+/// Messages API 的内容块
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AnthropicContentBlock {
+    Text {
+        text: String,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        input: serde_json::Value,
+    },
+    ToolResult {
+        tool_use_id: String,
+        content: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        is_error: Option<bool>,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AnthropicTool {
+    pub name: String,
+    pub description: String,
+    pub input_schema: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AnthropicResponse {
+    pub model: String,
+    pub content: Vec<AnthropicContentBlock>,
+    pub stop_reason: Option<String>,
+    pub usage: Option<AnthropicUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AnthropicUsage {
+    pub input_tokens: usize,
+    pub output_tokens: usize,
+}
+
+#[derive(Clone, Debug, Getters, Setters, MutGetters)]
+#[getset(get = "pub", set = "pub", get_mut = "pub", set_with = "pub")]
+pub struct AnthropicProvider {
+    client: Arc<Client>,
+    api_key: String,
+    base_url: String,
+    api_version: String,
+    retry: RetryPolicy,
+}
+
+impl AnthropicProvider {
+    /// 创建Anthropic Provider
+    pub fn new(api_key: String, timeout_sec: u64) -> Self {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(timeout_sec))
+            .build()
+            .unwrap_or_else(|_| Client::new());
+
+        Self {
+            client: Arc::new(client),
+            api_key,
+            base_url: "https://api.anthropic.com/v1".to_string(),
+            api_version: ANTHROPIC_API_VERSION.to_string(),
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    pub fn with_base_url(mut self, url: String) -> Self {
+        self.base_url = url;
+        self
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    fn create_headers(&self) -> header::HeaderMap {
+        let mut headers = header::HeaderMap::new();
+
+        headers.insert(
+            header::HeaderName::from_static("x-api-key"),
+            header::HeaderValue::from_str(&self.api_key)
+                .unwrap_or_else(|_| header::HeaderValue::from_static("")),
+        );
+        headers.insert(
+            header::HeaderName::from_static("anthropic-version"),
+            header::HeaderValue::from_str(&self.api_version)
+                .unwrap_or_else(|_| header::HeaderValue::from_static(ANTHROPIC_API_VERSION)),
+        );
+        headers.insert(
+            header::CONTENT_TYPE,
+            header::HeaderValue::from_static("application/json"),
+        );
+
+        headers
+    }
+
+    /// 将函数定义转换为 Anthropic tools 格式
+    pub fn convert_to_anthropic_tools(functions: &[FunctionDefinition]) -> Vec<AnthropicTool> {
+        OpenAiProvider::convert_to_openai_tools(functions)
+            .into_iter()
+            .map(|tool| AnthropicTool {
+                name: tool.function.name,
+                description: tool.function.description,
+                input_schema: tool.function.parameters,
+            })
+            .collect()
+    }
+
+    /// 将函数执行结果转换为 tool_result 内容块
+    pub fn convert_function_result(
+        tool_use_id: &str,
+        result: &FunctionResult,
+    ) -> AnthropicContentBlock {
+        let (content, is_error) = match &result.error {
+            Some(error) => (error.clone(), Some(true)),
+            None => match &result.result {
+                serde_json::Value::String(text) => (text.clone(), None),
+                value => (value.to_string(), None),
+            },
+        };
+        AnthropicContentBlock::ToolResult {
+            tool_use_id: tool_use_id.to_string(),
+            content,
+            is_error,
+        }
+    }
+
+    /// 还原 [`ChatMessage::tool_result`] 编码的执行结果，是否失败以消息的 `is_error` 为准
+    fn function_result_of(message: &ChatMessage) -> FunctionResult {
+        let name = message.name.clone().unwrap_or_default();
+        let value = serde_json::from_str(&message.content)
+            .unwrap_or_else(|_| serde_json::Value::String(message.content.clone()));
+        if !message.is_error {
+            return FunctionResult {
+                name,
+                result: value,
+                error: None,
+            };
+        }
+        let error = value["error"]
+            .as_str()
+            .map_or_else(|| message.content.clone(), str::to_string);
+        FunctionResult {
+            name,
+            result: serde_json::Value::Null,
+            error: Some(error),
+        }
+    }
+
+    /// 将统一对话转换为 Messages API 格式
+    ///
+    /// system 消息合并到顶层 system 字段；工具结果作为 user 消息中的 tool_result 块；
+    /// 相邻同角色消息合并，以满足 user/assistant 交替的要求
+    pub fn convert_messages(request: &AiRequest) -> (Option<String>, Vec<AnthropicMessage>) {
+        let mut system_parts = Vec::new();
+        let mut messages: Vec<AnthropicMessage> = Vec::new();
+
+        for message in request.conversation() {
+            let (role, blocks) = match message.role {
+                ChatRole::System => {
+                    system_parts.push(message.content);
+                    continue;
+                }
+                ChatRole::User => (
+                    "user",
+                    vec![AnthropicContentBlock::Text {
+                        text: message.content,
+                    }],
+                ),
+                ChatRole::Assistant => {
+                    let mut blocks = Vec::new();
+                    if !message.content.is_empty() {
+                        blocks.push(AnthropicContentBlock::Text {
+                            text: message.content,
+                        });
+                    }
+                    for call in message.tool_calls.unwrap_or_default() {
+                        blocks.push(AnthropicContentBlock::ToolUse {
+                            id: call.id,
+                            name: call.function.name,
+                            input: serde_json::from_str(&call.function.arguments)
+                                .unwrap_or_else(|_| serde_json::json!({})),
+                        });
+                    }
+                    ("assistant", blocks)
+                }
+                ChatRole::Tool => (
+                    "user",
+                    vec![Self::convert_function_result(
+                        message.tool_call_id.as_deref().unwrap_or_default(),
+                        &Self::function_result_of(&message),
+                    )],
+                ),
+            };
+
+            match messages.last_mut() {
+                Some(last) if last.role == role => last.content.extend(blocks),
+                _ => messages.push(AnthropicMessage {
+                    role: role.to_string(),
+                    content: blocks,
+                }),
+            }
+        }
+
+        let system = if system_parts.is_empty() {
+            None
+        } else {
+            Some(system_parts.join("\n\n"))
+        };
+        (system, messages)
+    }
+
+    fn build_request(
+        &self,
+        request: &AiRequest,
+        functions: Option<&[FunctionDefinition]>,
+    ) -> AnthropicRequest {
+        let (system, messages) = Self::convert_messages(request);
+
+        AnthropicRequest {
+            model: request.model.clone(),
+            system,
+            messages,
+            max_tokens: request.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+            temperature: request.temperature,
+            tools: functions
+                .filter(|f| !f.is_empty())
+                .map(Self::convert_to_anthropic_tools),
+        }
+    }
+
+    async fn post_messages(&self, body: &AnthropicRequest) -> AiResult<AiResponse> {
+        debug!("send anthropic request: {body:#?}");
+
+        let url = format!("{}/messages", self.base_url);
+        let response = send_with_retry(&self.retry, AiProviderType::Anthropic, &body.model, || {
+            self.client
+                .post(&url)
+                .headers(self.create_headers())
+                .json(body)
+        })
+        .await
+        .with(url.clone())?;
+
+        let response_text = response.text().await.owe_data()?;
+        debug!("Raw response body: {response_text}");
+
+        self.convert_response_from_text(&response_text, &body.model)
+    }
+
+    /// 将 Messages API 响应文本转换为 AiResponse
+    pub fn convert_response_from_text(
+        &self,
+        response_text: &str,
+        request_model: &str,
+    ) -> AiResult<AiResponse> {
+        if let Some(error) = parse_error_body(response_text) {
+            return Err(OrionAiReason::from(provider_error(
+                AiProviderType::Anthropic,
+                request_model,
+                &error,
+            ))
+            .to_err());
+        }
+
+        let anthropic_response: AnthropicResponse =
+            serde_json::from_str(response_text).owe_data()?;
+
+        let mut content = String::new();
+        let mut tool_calls = Vec::new();
+        for block in &anthropic_response.content {
+            match block {
+                AnthropicContentBlock::Text { text } => content.push_str(text),
+                AnthropicContentBlock::ToolUse { id, name, input } => {
+                    tool_calls.push(FunctionCall {
+                        index: Some(tool_calls.len() as u32),
+                        id: id.clone(),
+                        r#type: "function".to_string(),
+                        function: FunctionCallInfo {
+                            name: name.clone(),
+                            arguments: input.to_string(),
+                        },
+                    })
+                }
+                AnthropicContentBlock::ToolResult { .. } => {}
+            }
+        }
+
+        let (prompt_tokens, completion_tokens) = anthropic_response
+            .usage
+            .as_ref()
+            .map(|u| (u.input_tokens, u.output_tokens))
+            .unwrap_or((0, 0));
+
+        Ok(AiResponse {
+            content,
+            model: anthropic_response.model.clone(),
+            usage: UsageInfo {
+                prompt_tokens,
+                completion_tokens,
+                total_tokens: prompt_tokens + completion_tokens,
+                estimated_cost: self.estimate_cost(request_model, prompt_tokens, completion_tokens),
+            },
+            finish_reason: anthropic_response.stop_reason.clone(),
+            provider: AiProviderType::Anthropic,
+            metadata: HashMap::new(),
+            tool_calls: if tool_calls.is_empty() {
+                None
+            } else {
+                Some(tool_calls)
+            },
+        })
+    }
+}
+
+#[async_trait]
+impl AiProvider for AnthropicProvider {
+    fn provider_type(&self) -> AiProviderType {
+        AiProviderType::Anthropic
+    }
+
+    async fn is_model_available(&self, model: &str) -> bool {
+        match self.list_models().await {
+            Ok(models) => models.iter().any(|m| m.name == model),
+            Err(_) => false,
+        }
+    }
+
+    async fn list_models(&self) -> AiResult<Vec<ModelInfo>> {
+        Ok(ModelCatalog::global().models_of(AiProviderType::Anthropic))
+    }
+
+    async fn send_request(&self, request: &AiRequest) -> AiResult<AiResponse> {
+        let body = self.build_request(request, None);
+        self.post_messages(&body).await
+    }
+
+    fn estimate_cost(&self, model: &str, input_tokens: usize, output_tokens: usize) -> Option<f64> {
+        ModelCatalog::global().estimate_cost_or_default(
+            AiProviderType::Anthropic,
+            model,
+            input_tokens,
+            output_tokens,
+        )
+    }
+
+    fn check_token_limit(&self, model: &str, max_tokens: usize) -> bool {
+        ModelCatalog::global().check_token_limit(AiProviderType::Anthropic, model, max_tokens)
+    }
+
+    fn get_model_info(&self, model: &str) -> Option<ModelInfo> {
+        ModelCatalog::global()
+            .get(AiProviderType::Anthropic, model)
+            .cloned()
+    }
+
+    fn get_config_keys(&self) -> Vec<&'static str> {
+        vec!["CLAUDE_API_KEY", "ANTHROPIC_BASE_URL"]
+    }
+
+    fn supports_function_calling(&self) -> bool {
+        true
+    }
+
+    async fn send_request_with_functions(
+        &self,
+        request: &AiRequest,
+        functions: &[FunctionDefinition],
+    ) -> AiResult<AiResponse> {
+        let body = self.build_request(request, Some(functions));
+        self.post_messages(&body).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::AiErrReason;
+    use crate::providers::stub::{StubResponse, StubServer};
+
+    fn tool_use_body() -> &'static str {
+        r#"{
+            "id": "msg_01",
+            "type": "message",
+            "role": "assistant",
+            "model": "claude-sonnet-4-0",
+            "content": [
+                {"type": "text", "text": "我来查看仓库状态"},
+                {"type": "tool_use", "id": "toolu_01", "name": "git-status", "input": {"path": "."}}
+            ],
+            "stop_reason": "tool_use",
+            "usage": {"input_tokens": 120, "output_tokens": 30}
+        }"#
+    }
+
+    #[tokio::test]
+    async fn test_send_request_headers_and_system() {
+        let server = StubServer::start(vec![StubResponse::json(
+            200,
+            r#"{"model":"claude-sonnet-4-0","content":[{"type":"text","text":"pong"}],"stop_reason":"end_turn","usage":{"input_tokens":10,"output_tokens":2}}"#,
+        )])
+        .await;
+        let provider = AnthropicProvider::new("test-key".to_string(), 5)
+            .with_base_url(server.base_url().to_string());
+
+        let request = AiRequest::builder()
+            .model("claude-sonnet-4-0")
+            .system_prompt("你是测试助手")
+            .user_prompt("ping")
+            .build();
+        let response = provider.send_request(&request).await.unwrap();
+
+        assert_eq!(response.content, "pong");
+        assert_eq!(response.provider, AiProviderType::Anthropic);
+        assert_eq!(response.usage.prompt_tokens, 10);
+        assert_eq!(response.usage.completion_tokens, 2);
+        assert_eq!(response.usage.total_tokens, 12);
+        assert!(response.tool_calls.is_none());
+
+        let captured = &server.requests()[0];
+        assert_eq!(captured.path, "/messages");
+        assert_eq!(captured.header("x-api-key"), Some("test-key"));
+        assert_eq!(
+            captured.header("anthropic-version"),
+            Some(ANTHROPIC_API_VERSION)
+        );
+        let body = captured.json();
+        assert_eq!(body["system"], "你是测试助手");
+        assert_eq!(body["max_tokens"], DEFAULT_MAX_TOKENS);
+        assert_eq!(body["messages"][0]["role"], "user");
+        assert_eq!(body["messages"][0]["content"][0]["text"], "ping");
+    }
+
+    #[tokio::test]
+    async fn test_send_request_with_functions_maps_tool_use() {
+        let server = StubServer::start(vec![StubResponse::json(200, tool_use_body())]).await;
+        let provider = AnthropicProvider::new("test-key".to_string(), 5)
+            .with_base_url(server.base_url().to_string());
+
+        let functions = crate::func::git::create_git_functions();
+        let request = AiRequest::builder()
+            .model("claude-sonnet-4-0")
+            .user_prompt("看看 git 状态")
+            .build();
+        let response = provider
+            .send_request_with_functions(&request, &functions)
+            .await
+            .unwrap();
+
+        assert_eq!(response.content, "我来查看仓库状态");
+        assert_eq!(response.finish_reason, Some("tool_use".to_string()));
+        let tool_calls = response.tool_calls.unwrap();
+        assert_eq!(tool_calls.len(), 1);
+        assert_eq!(tool_calls[0].id, "toolu_01");
+        assert_eq!(tool_calls[0].function.name, "git-status");
+        let args: serde_json::Value =
+            serde_json::from_str(&tool_calls[0].function.arguments).unwrap();
+        assert_eq!(args["path"], ".");
+
+        let body = server.requests()[0].json();
+        assert!(body.get("system").is_none());
+        assert_eq!(body["tools"][0]["name"], functions[0].name);
+        assert_eq!(body["tools"][0]["input_schema"]["type"], "object");
+    }
+
+    #[tokio::test]
+    async fn test_error_envelope_maps_rate_limit() {
+        let server = StubServer::start(vec![StubResponse::json(
+            429,
+            r#"{"type":"error","error":{"type":"rate_limit_error","message":"slow down"}}"#,
+        )])
+        .await;
+        let provider = AnthropicProvider::new("test-key".to_string(), 5)
+            .with_base_url(server.base_url().to_string())
+            .with_retry(RetryPolicy::none());
+
+        let request = AiRequest::builder().model("claude-sonnet-4-0").build();
+        let err = provider.send_request(&request).await.unwrap_err();
+        assert!(matches!(
+            err.reason(),
+            OrionAiReason::Ai(AiErrReason::RateLimitError(_))
+        ));
+    }
+
+    #[test]
+    fn test_convert_multi_turn_messages() {
+        let call = FunctionCall {
+            index: Some(0),
+            id: "toolu_01".to_string(),
+            r#type: "function".to_string(),
+            function: FunctionCallInfo {
+                name: "git-status".to_string(),
+                arguments: r#"{"path":"."}"#.to_string(),
+            },
+        };
+        let result = FunctionResult {
+            name: "git-status".to_string(),
+            result: serde_json::json!({"has_changes": true}),
+            error: None,
+        };
+        let request = AiRequest::builder()
+            .model("claude-sonnet-4-0")
+            .system_prompt("sys")
+            .message(ChatMessage::user("看看状态"))
+            .message(ChatMessage::assistant_with_tool_calls(
+                "",
+                vec![call.clone()],
+            ))
+            .message(ChatMessage::tool_result(&call, &result))
+            .user_prompt("然后呢？")
+            .build();
+
+        let (system, messages) = AnthropicProvider::convert_messages(&request);
+        assert_eq!(system, Some("sys".to_string()));
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[1].role, "assistant");
+        assert!(matches!(
+            &messages[1].content[0],
+            AnthropicContentBlock::ToolUse { id, input, .. } if id == "toolu_01" && input["path"] == "."
+        ));
+        // tool_result 与随后的用户输入合并为同一条 user 消息
+        assert_eq!(messages[2].role, "user");
+        assert_eq!(messages[2].content.len(), 2);
+        assert!(matches!(
+            &messages[2].content[0],
+            AnthropicContentBlock::ToolResult { tool_use_id, is_error: None, .. } if tool_use_id == "toolu_01"
+        ));
+
+        // 执行失败的工具结果带上 is_error
+        let failed = FunctionResult {
+            name: "git-status".to_string(),
+            result: serde_json::Value::Null,
+            error: Some("not a repo".to_string()),
+        };
+        let request = AiRequest::builder()
+            .model("claude-sonnet-4-0")
+            .message(ChatMessage::assistant_with_tool_calls(
+                "",
+                vec![call.clone()],
+            ))
+            .message(ChatMessage::tool_result(&call, &failed))
+            .build();
+        let (_, messages) = AnthropicProvider::convert_messages(&request);
+        assert_eq!(
+            messages[1].content[0],
+            AnthropicContentBlock::ToolResult {
+                tool_use_id: "toolu_01".to_string(),
+                content: "not a repo".to_string(),
+                is_error: Some(true),
+            }
+        );
+
+        // 成功结果中的 error 字段只是数据，不算失败
+        let found_nothing = FunctionResult {
+            name: "git-status".to_string(),
+            result: serde_json::json!({"error": "none found"}),
+            error: None,
+        };
+        let request = AiRequest::builder()
+            .model("claude-sonnet-4-0")
+            .message(ChatMessage::assistant_with_tool_calls(
+                "",
+                vec![call.clone()],
+            ))
+            .message(ChatMessage::tool_result(&call, &found_nothing))
+            .build();
+        let (_, messages) = AnthropicProvider::convert_messages(&request);
+        assert_eq!(
+            messages[1].content[0],
+            AnthropicContentBlock::ToolResult {
+                tool_use_id: "toolu_01".to_string(),
+                content: r#"{"error":"none found"}"#.to_string(),
+                is_error: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_convert_function_result() {
+        let ok = FunctionResult {
+            name: "git-status".to_string(),
+            result: serde_json::json!({"has_changes": false}),
+            error: None,
+        };
+        let block = AnthropicProvider::convert_function_result("toolu_01", &ok);
+        assert_eq!(
+            serde_json::to_value(&block).unwrap(),
+            serde_json::json!({
+                "type": "tool_result",
+                "tool_use_id": "toolu_01",
+                "content": "{\"has_changes\":false}"
+            })
+        );
+
+        let failed = FunctionResult {
+            name: "git-status".to_string(),
+            result: serde_json::Value::Null,
+            error: Some("not a repo".to_string()),
+        };
+        let block = AnthropicProvider::convert_function_result("toolu_02", &failed);
+        assert_eq!(
+            block,
+            AnthropicContentBlock::ToolResult {
+                tool_use_id: "toolu_02".to_string(),
+                content: "not a repo".to_string(),
+                is_error: Some(true),
+            }
+        );
+    }
+}
@@ -1,20 +1,275 @@
 use async_trait::async_trait;
+use orion_error::{ToStructError, UvsConfFrom};
+use regex::Regex;
 use std::collections::HashMap;
-// Removed unused import
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use wildmatch::WildMatch;
 
-use crate::{error::AiResult, provider::*};
+use super::replay::ReplayStore;
+use crate::error::{AiErrReason, OrionAiReason};
+use crate::roleid::AiRoleID;
+use crate::{config::ModelCatalog, error::AiResult, provider::*};
 
-pub struct MockProvider;
+/// 脚本化规则返回的错误
+#[derive(Debug, Clone, PartialEq)]
+pub enum MockError {
+    /// 限流，可触发 provider 切换
+    RateLimit,
+    /// 请求超时，与真实 provider 一样报告为 provider 不可用
+    Timeout,
+    /// 内容被安全策略过滤
+    ContentFiltered,
+    /// 输出因长度截断
+    Truncated,
+    /// 提供商返回的其他错误
+    Provider { code: String, message: String },
+}
 
-impl Default for MockProvider {
-    fn default() -> Self {
-        Self::new()
+impl MockError {
+    /// `response` 为规则本应返回的响应，截断时作为部分响应携带
+    fn to_reason(&self, mut response: AiResponse) -> AiErrReason {
+        match self {
+            Self::RateLimit => AiErrReason::RateLimitError("mock".to_string()),
+            Self::Timeout => {
+                AiErrReason::ProviderUnavailable("mock: request timed out".to_string())
+            }
+            Self::ContentFiltered => AiErrReason::SensitiveContentFiltered,
+            Self::Truncated => {
+                response.finish_reason = Some("length".to_string());
+                AiErrReason::OutputTruncated(Box::new(response))
+            }
+            Self::Provider { code, message } => {
+                AiErrReason::ProviderError("mock".to_string(), code.clone(), message.clone())
+            }
+        }
     }
 }
 
+/// 脚本化响应规则
+///
+/// 条件均为可选，未设置的条件视为匹配；`prompt` 匹配本轮用户输入，
+/// 工具结果回传轮次中 `user_prompt` 为空时取历史中最后一条用户消息
+#[derive(Debug, Clone, Default)]
+pub struct MockRule {
+    model: Option<WildMatch>,
+    role: Option<AiRoleID>,
+    prompt: Option<Regex>,
+    with_functions: Option<bool>,
+    /// 可命中次数，`None` 表示不限
+    times: Option<usize>,
+    content: String,
+    tool_calls: Vec<FunctionCall>,
+    error: Option<MockError>,
+    /// 提示和补全 token 数，未设置时按提示长度估算
+    usage: Option<(usize, usize)>,
+    latency: Duration,
+    finish_reason: Option<String>,
+}
+
+impl MockRule {
+    /// 返回文本内容
+    pub fn reply(content: impl Into<String>) -> Self {
+        Self {
+            content: content.into(),
+            ..Default::default()
+        }
+    }
+
+    /// 返回错误
+    pub fn fail(error: MockError) -> Self {
+        Self {
+            error: Some(error),
+            ..Default::default()
+        }
+    }
+
+    /// 匹配模型名，支持 `*`、`?` 通配
+    pub fn for_model(mut self, pattern: &str) -> Self {
+        self.model = Some(WildMatch::new(pattern));
+        self
+    }
+
+    pub fn for_role(mut self, role: AiRoleID) -> Self {
+        self.role = Some(role);
+        self
+    }
+
+    /// 用正则匹配用户输入
+    pub fn for_prompt(mut self, pattern: &str) -> AiResult<Self> {
+        let re = Regex::new(pattern).map_err(|e| {
+            OrionAiReason::from_conf(format!("invalid mock prompt regex `{pattern}`: {e}")).to_err()
+        })?;
+        self.prompt = Some(re);
+        Ok(self)
+    }
+
+    /// 只匹配带（`true`）或不带（`false`）函数定义的请求
+    pub fn with_functions(mut self, with_functions: bool) -> Self {
+        self.with_functions = Some(with_functions);
+        self
+    }
+
+    pub fn times(mut self, times: usize) -> Self {
+        self.times = Some(times);
+        self
+    }
+
+    /// 只命中一次，按顺序添加的一次性规则即构成响应队列
+    pub fn once(self) -> Self {
+        self.times(1)
+    }
+
+    /// 追加一个工具调用
+    pub fn with_tool_call(mut self, name: &str, arguments: serde_json::Value) -> Self {
+        let index = self.tool_calls.len();
+        self.tool_calls.push(FunctionCall {
+            index: Some(index as u32),
+            id: format!("call_mock_{name}_{index}"),
+            r#type: "function".to_string(),
+            function: FunctionCallInfo {
+                name: name.to_string(),
+                arguments: arguments.to_string(),
+            },
+        });
+        self
+    }
+
+    pub fn with_usage(mut self, prompt_tokens: usize, completion_tokens: usize) -> Self {
+        self.usage = Some((prompt_tokens, completion_tokens));
+        self
+    }
+
+    /// 响应前等待的时长，错误同样会延迟返回
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    pub fn with_finish_reason(mut self, reason: impl Into<String>) -> Self {
+        self.finish_reason = Some(reason.into());
+        self
+    }
+
+    fn matches(&self, request: &AiRequest, with_functions: bool) -> bool {
+        self.model
+            .as_ref()
+            .is_none_or(|model| model.matches(&request.model))
+            && self
+                .role
+                .as_ref()
+                .is_none_or(|role| request.role.as_ref() == Some(role))
+            && self
+                .prompt
+                .as_ref()
+                .is_none_or(|re| re.is_match(current_prompt(request)))
+            && self.with_functions.is_none_or(|f| f == with_functions)
+    }
+
+    async fn respond(&self, request: &AiRequest) -> AiResult<AiResponse> {
+        if !self.latency.is_zero() {
+            tokio::time::sleep(self.latency).await;
+        }
+        let (prompt_tokens, completion_tokens) =
+            self.usage.unwrap_or((request.user_prompt.len() / 4, 50));
+        let finish_reason = self.finish_reason.clone().unwrap_or_else(|| {
+            if self.tool_calls.is_empty() {
+                "stop".to_string()
+            } else {
+                "tool_calls".to_string()
+            }
+        });
+        let response = AiResponse {
+            content: self.content.clone(),
+            model: request.model.clone(),
+            usage: UsageInfo {
+                prompt_tokens,
+                completion_tokens,
+                total_tokens: prompt_tokens + completion_tokens,
+                estimated_cost: Some(0.0),
+            },
+            finish_reason: Some(finish_reason),
+            provider: AiProviderType::Mock,
+            metadata: HashMap::new(),
+            tool_calls: (!self.tool_calls.is_empty()).then(|| self.tool_calls.clone()),
+        };
+        match &self.error {
+            Some(error) => Err(OrionAiReason::from(error.to_reason(response)).to_err()),
+            None => Ok(response),
+        }
+    }
+}
+
+/// 本轮用户输入
+fn current_prompt(request: &AiRequest) -> &str {
+    if !request.user_prompt.is_empty() {
+        return &request.user_prompt;
+    }
+    request
+        .messages
+        .iter()
+        .rev()
+        .find(|message| message.role == ChatRole::User)
+        .map(|message| message.content.as_str())
+        .unwrap_or_default()
+}
+
+/// 模拟 provider
+///
+/// 请求依次交给脚本化规则、回放数据处理，都未命中时使用内置的固定响应；
+/// 设置了回放数据时，未录制的请求返回错误
+#[derive(Debug, Default)]
+pub struct MockProvider {
+    /// 规则及其已命中次数
+    rules: Mutex<Vec<(MockRule, usize)>>,
+    replay: Option<Arc<ReplayStore>>,
+}
+
 impl MockProvider {
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+
+    /// 回放录制的交互
+    pub fn with_replay(replay: Arc<ReplayStore>) -> Self {
+        Self {
+            replay: Some(replay),
+            ..Default::default()
+        }
+    }
+
+    /// 按顺序匹配的脚本化规则
+    pub fn with_rules(rules: Vec<MockRule>) -> Self {
+        let provider = Self::new();
+        for rule in rules {
+            provider.push_rule(rule);
+        }
+        provider
+    }
+
+    /// 追加规则，可在客户端构建后继续添加
+    pub fn push_rule(&self, rule: MockRule) {
+        self.rules.lock().expect("mock rules lock").push((rule, 0));
+    }
+
+    /// 各规则的命中次数，顺序与添加顺序一致
+    pub fn hits(&self) -> Vec<usize> {
+        self.rules
+            .lock()
+            .expect("mock rules lock")
+            .iter()
+            .map(|(_, hits)| *hits)
+            .collect()
+    }
+
+    /// 取出首个匹配且未用完的规则
+    fn scripted(&self, request: &AiRequest, with_functions: bool) -> Option<MockRule> {
+        let mut rules = self.rules.lock().expect("mock rules lock");
+        let (rule, hits) = rules.iter_mut().find(|(rule, hits)| {
+            rule.times.is_none_or(|times| *hits < times) && rule.matches(request, with_functions)
+        })?;
+        *hits += 1;
+        Some(rule.clone())
     }
 }
 
@@ -30,38 +285,16 @@ impl AiProvider for MockProvider {
     }
 
     async fn list_models(&self) -> AiResult<Vec<ModelInfo>> {
-        Ok(vec![
-            ModelInfo {
-                name: "mock-gpt".to_string(),
-                provider: AiProviderType::Mock,
-                max_tokens: 4000,
-                supports_images: false,
-                supports_reasoning: false,
-                cost_per_1k_input: 0.0,
-                cost_per_1k_output: 0.0,
-            },
-            ModelInfo {
-                name: "mock-claude".to_string(),
-                provider: AiProviderType::Mock,
-                max_tokens: 4000,
-                supports_images: false,
-                supports_reasoning: true,
-                cost_per_1k_input: 0.0,
-                cost_per_1k_output: 0.0,
-            },
-            ModelInfo {
-                name: "mock".to_string(),
-                provider: AiProviderType::Mock,
-                max_tokens: 4000,
-                supports_images: false,
-                supports_reasoning: false,
-                cost_per_1k_input: 0.0,
-                cost_per_1k_output: 0.0,
-            },
-        ])
+        Ok(ModelCatalog::global().models_of(AiProviderType::Mock))
     }
 
     async fn send_request(&self, request: &AiRequest) -> AiResult<AiResponse> {
+        if let Some(rule) = self.scripted(request, false) {
+            return rule.respond(request).await;
+        }
+        if let Some(replay) = &self.replay {
+            return replay.replay(request);
+        }
         let content = format!(
             "[MOCK] Response for model: {} with prompt: {:.50}...",
             request.model, request.user_prompt
@@ -92,8 +325,26 @@ impl AiProvider for MockProvider {
         Some(0.0)
     }
 
-    fn check_token_limit(&self, _model: &str, max_tokens: usize) -> bool {
-        max_tokens <= 4000
+    fn check_token_limit(&self, model: &str, max_tokens: usize) -> bool {
+        self.get_model_info(model)
+            .is_none_or(|info| max_tokens <= info.max_tokens)
+    }
+
+    /// 目录中未登记的模型名同样可用，上下文窗口按 4000 计
+    fn get_model_info(&self, model: &str) -> Option<ModelInfo> {
+        let info = ModelCatalog::global()
+            .get(AiProviderType::Mock, model)
+            .cloned()
+            .unwrap_or_else(|| ModelInfo {
+                name: model.to_string(),
+                provider: AiProviderType::Mock,
+                max_tokens: 4000,
+                supports_images: false,
+                supports_reasoning: false,
+                cost_per_1k_input: 0.0,
+                cost_per_1k_output: 0.0,
+            });
+        Some(info)
     }
 
     fn get_config_keys(&self) -> Vec<&'static str> {
@@ -103,8 +354,14 @@ impl AiProvider for MockProvider {
     async fn send_request_with_functions(
         &self,
         request: &AiRequest,
-        _functions: &[FunctionDefinition],
+        functions: &[FunctionDefinition],
     ) -> AiResult<AiResponse> {
+        if let Some(rule) = self.scripted(request, !functions.is_empty()) {
+            return rule.respond(request).await;
+        }
+        if let Some(replay) = &self.replay {
+            return replay.replay(request);
+        }
         // 模拟函数调用 - 根据用户提示决定是否调用函数
         let tool_calls = if request.user_prompt.contains("git-status") {
             Some(vec![FunctionCall {
@@ -179,3 +436,70 @@ impl AiProvider for MockProvider {
         true
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::func::git::create_git_functions;
+    use orion_error::TestAssertWithMsg;
+
+    #[tokio::test]
+    async fn test_scripted_rules() {
+        let provider = MockProvider::with_rules(vec![
+            MockRule::reply("gpt 专用")
+                .for_model("gpt-*")
+                .with_functions(false),
+            MockRule::reply("评审角色")
+                .for_role(AiRoleID::new("reviewer"))
+                .with_finish_reason("length")
+                .times(2),
+        ]);
+        let functions = create_git_functions();
+
+        let request = AiRequest::builder()
+            .model("gpt-4o")
+            .user_prompt("你好")
+            .build();
+        let response = provider.send_request(&request).await.assert("model rule");
+        assert_eq!(response.content, "gpt 专用");
+        assert_eq!(
+            response.usage.total_tokens,
+            request.user_prompt.len() / 4 + 50
+        );
+
+        // 带函数定义时不匹配第一条规则，回退到内置响应
+        let response = provider
+            .send_request_with_functions(&request, &functions)
+            .await
+            .assert("fallback");
+        assert!(response.content.starts_with("[MOCK]"));
+
+        let mut request = AiRequest::builder()
+            .model("mock")
+            .user_prompt("评审")
+            .build();
+        request.role = Some(AiRoleID::new("reviewer"));
+        for _ in 0..2 {
+            let response = provider.send_request(&request).await.assert("role rule");
+            assert_eq!(response.finish_reason.as_deref(), Some("length"));
+        }
+        let response = provider.send_request(&request).await.assert("exhausted");
+        assert!(response.content.starts_with("[MOCK]"));
+        assert_eq!(provider.hits(), vec![1, 2]);
+
+        provider.push_rule(MockRule::fail(MockError::Provider {
+            code: "1113".to_string(),
+            message: "余额不足".to_string(),
+        }));
+        let err = provider.send_request(&request).await.unwrap_err();
+        assert_eq!(
+            err.reason(),
+            &OrionAiReason::from(AiErrReason::ProviderError(
+                "mock".to_string(),
+                "1113".to_string(),
+                "余额不足".to_string()
+            ))
+        );
+        assert!(MockRule::reply("x").for_prompt("(").is_err());
+    }
+}
@@ -1,3 +1,11 @@
+pub mod anthropic;
 pub mod mock;
+pub mod ollama;
 pub mod openai;
+pub mod replay;
 pub mod resp;
+pub mod retry;
+pub mod stream;
+
+#[cfg(test)]
+pub(crate) mod stub;
@@ -0,0 +1,490 @@
+use async_trait::async_trait;
+use log::{debug, warn};
+use orion_error::{ErrorOwe, ErrorWith, ToStructError};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::config::{ModelCatalog, RetryPolicy};
+use crate::error::{AiResult, OrionAiReason};
+use crate::provider::*;
+use crate::providers::openai::{ModelListCache, OpenAiProvider};
+use crate::providers::resp::{parse_error_body, provider_error};
+use crate::providers::retry::{ensure_success, send_with_retry};
+use getset::{Getters, MutGetters, Setters};
+
+#[derive(Debug, Serialize)]
+struct OllamaChatRequest {
+    model: String,
+    messages: Vec<OllamaMessage>,
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    options: Option<OllamaOptions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize)]
+struct OllamaOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_predict: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OllamaMessage {
+    pub role: String,
+    #[serde(default)]
+    pub content: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_calls: Option<Vec<OllamaToolCall>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OllamaToolCall {
+    pub function: OllamaFunctionCall,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OllamaFunctionCall {
+    pub name: String,
+    /// Ollama 返回的是 JSON 对象而不是字符串
+    pub arguments: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OllamaChatResponse {
+    pub model: String,
+    pub message: OllamaMessage,
+    #[serde(default)]
+    pub done_reason: Option<String>,
+    #[serde(default)]
+    pub prompt_eval_count: Option<usize>,
+    #[serde(default)]
+    pub eval_count: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OllamaTagsResponse {
+    pub models: Vec<OllamaModelTag>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OllamaModelTag {
+    pub name: String,
+    #[serde(default)]
+    pub size: u64,
+}
+
+#[derive(Clone, Debug, Getters, Setters, MutGetters)]
+#[getset(get = "pub", set = "pub", get_mut = "pub", set_with = "pub")]
+pub struct OllamaProvider {
+    client: Arc<Client>,
+    base_url: String,
+    retry: RetryPolicy,
+    model_cache: ModelListCache,
+}
+
+impl OllamaProvider {
+    /// 创建本地Ollama Provider，本地服务无需密钥
+    pub fn new(timeout_sec: u64) -> Self {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(timeout_sec))
+            .build()
+            .unwrap_or_else(|_| Client::new());
+
+        Self {
+            client: Arc::new(client),
+            base_url: "http://localhost:11434".to_string(),
+            retry: RetryPolicy::default(),
+            model_cache: ModelListCache::default(),
+        }
+    }
+
+    pub fn with_base_url(mut self, url: String) -> Self {
+        self.base_url = url.trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// 设置本机模型列表的缓存时长
+    pub fn with_model_list_ttl(mut self, ttl: Duration) -> Self {
+        self.model_cache = ModelListCache::new(ttl);
+        self
+    }
+
+    /// 获取本机已拉取的模型
+    pub async fn fetch_tags(&self) -> AiResult<OllamaTagsResponse> {
+        let url = format!("{}/api/tags", self.base_url);
+        let response = self
+            .client
+            .get(&url)
+            .send()
+            .await
+            .owe_res()
+            .with(url.clone())?;
+        let response = ensure_success(response, AiProviderType::Ollama, "")
+            .await
+            .with(url)?;
+        let response_text = response.text().await.owe_data()?;
+        debug!("Raw tags body: {response_text}");
+        serde_json::from_str(&response_text).owe_data()
+    }
+
+    /// 本地模型信息，目录中未登记的 `max_tokens` 为 0，表示未知，不做上下文限制
+    fn model_info(name: &str) -> ModelInfo {
+        if let Some(info) = ModelCatalog::global().get(AiProviderType::Ollama, name) {
+            return info.clone();
+        }
+        ModelInfo {
+            name: name.to_string(),
+            provider: AiProviderType::Ollama,
+            max_tokens: 0,
+            supports_images: false,
+            supports_reasoning: false,
+            cost_per_1k_input: 0.0,
+            cost_per_1k_output: 0.0,
+        }
+    }
+
+    fn build_request(
+        &self,
+        request: &AiRequest,
+        functions: Option<&[FunctionDefinition]>,
+    ) -> OllamaChatRequest {
+        let messages = request
+            .conversation()
+            .into_iter()
+            .map(|message| OllamaMessage {
+                role: message.role.to_string(),
+                content: message.content,
+                tool_calls: message.tool_calls.map(|calls| {
+                    calls
+                        .into_iter()
+                        .map(|call| OllamaToolCall {
+                            function: OllamaFunctionCall {
+                                name: call.function.name,
+                                arguments: serde_json::from_str(&call.function.arguments)
+                                    .unwrap_or_else(|_| serde_json::json!({})),
+                            },
+                        })
+                        .collect()
+                }),
+            })
+            .collect();
+
+        let options = if request.temperature.is_some() || request.max_tokens.is_some() {
+            Some(OllamaOptions {
+                temperature: request.temperature,
+                num_predict: request.max_tokens,
+            })
+        } else {
+            None
+        };
+
+        let tools = functions.filter(|f| !f.is_empty()).map(|f| {
+            serde_json::to_value(OpenAiProvider::convert_to_openai_tools(f))
+                .unwrap_or(serde_json::Value::Null)
+        });
+
+        OllamaChatRequest {
+            model: request.model.clone(),
+            messages,
+            stream: false,
+            options,
+            tools,
+        }
+    }
+
+    async fn post_chat(&self, body: &OllamaChatRequest) -> AiResult<AiResponse> {
+        debug!("send ollama request: {body:#?}");
+
+        let url = format!("{}/api/chat", self.base_url);
+        let response = send_with_retry(&self.retry, AiProviderType::Ollama, &body.model, || {
+            self.client.post(&url).json(body)
+        })
+        .await
+        .with(url.clone())?;
+
+        let response_text = response.text().await.owe_data()?;
+        debug!("Raw response body: {response_text}");
+
+        Self::convert_response_from_text(&response_text, &body.model)
+    }
+
+    /// 将 /api/chat 响应文本转换为 AiResponse
+    pub fn convert_response_from_text(
+        response_text: &str,
+        request_model: &str,
+    ) -> AiResult<AiResponse> {
+        if let Some(error) = parse_error_body(response_text) {
+            return Err(OrionAiReason::from(provider_error(
+                AiProviderType::Ollama,
+                request_model,
+                &error,
+            ))
+            .to_err());
+        }
+
+        let chat: OllamaChatResponse = serde_json::from_str(response_text).owe_data()?;
+
+        let tool_calls = chat.message.tool_calls.as_ref().map(|calls| {
+            calls
+                .iter()
+                .enumerate()
+                .map(|(i, call)| FunctionCall {
+                    index: Some(i as u32),
+                    id: format!("call_ollama_{i}"),
+                    r#type: "function".to_string(),
+                    function: FunctionCallInfo {
+                        name: call.function.name.clone(),
+                        arguments: call.function.arguments.to_string(),
+                    },
+                })
+                .collect()
+        });
+
+        let prompt_tokens = chat.prompt_eval_count.unwrap_or(0);
+        let completion_tokens = chat.eval_count.unwrap_or(0);
+
+        Ok(AiResponse {
+            content: chat.message.content.clone(),
+            model: chat.model.clone(),
+            usage: UsageInfo {
+                prompt_tokens,
+                completion_tokens,
+                total_tokens: prompt_tokens + completion_tokens,
+                estimated_cost: Some(0.0),
+            },
+            finish_reason: chat.done_reason.clone(),
+            provider: AiProviderType::Ollama,
+            metadata: HashMap::new(),
+            tool_calls,
+        })
+    }
+}
+
+#[async_trait]
+impl AiProvider for OllamaProvider {
+    fn provider_type(&self) -> AiProviderType {
+        AiProviderType::Ollama
+    }
+
+    async fn is_model_available(&self, model: &str) -> bool {
+        match self.list_models().await {
+            Ok(models) => models
+                .iter()
+                .any(|m| m.name == model || m.name.strip_suffix(":latest") == Some(model)),
+            Err(_) => false,
+        }
+    }
+
+    /// 本机模型列表按 TTL 缓存
+    async fn list_models(&self) -> AiResult<Vec<ModelInfo>> {
+        if let Some(models) = self.model_cache.fresh() {
+            return Ok(models);
+        }
+        let tags = self.fetch_tags().await?;
+        let models: Vec<ModelInfo> = tags
+            .models
+            .iter()
+            .map(|m| Self::model_info(&m.name))
+            .collect();
+        self.model_cache.store(models.clone());
+        Ok(models)
+    }
+
+    async fn send_request(&self, request: &AiRequest) -> AiResult<AiResponse> {
+        let body = self.build_request(request, None);
+        self.post_chat(&body).await
+    }
+
+    async fn health_check(&self) -> AiResult<bool> {
+        // 守护进程未启动时返回 false，而不是错误；成功的结果写入缓存
+        match self.fetch_tags().await {
+            Ok(tags) => {
+                let models = tags
+                    .models
+                    .iter()
+                    .map(|m| Self::model_info(&m.name))
+                    .collect();
+                self.model_cache.store(models);
+                Ok(true)
+            }
+            Err(e) => {
+                warn!("ollama health check failed: {e}");
+                Ok(false)
+            }
+        }
+    }
+
+    fn estimate_cost(&self, model: &str, input_tokens: usize, output_tokens: usize) -> Option<f64> {
+        Some(Self::model_info(model).estimate_cost(input_tokens, output_tokens))
+    }
+
+    fn check_token_limit(&self, model: &str, max_tokens: usize) -> bool {
+        ModelCatalog::global().check_token_limit(AiProviderType::Ollama, model, max_tokens)
+    }
+
+    fn get_model_info(&self, model: &str) -> Option<ModelInfo> {
+        ModelCatalog::global()
+            .get(AiProviderType::Ollama, model)
+            .cloned()
+    }
+
+    fn get_config_keys(&self) -> Vec<&'static str> {
+        vec!["OLLAMA_BASE_URL"]
+    }
+
+    fn supports_function_calling(&self) -> bool {
+        true
+    }
+
+    async fn send_request_with_functions(
+        &self,
+        request: &AiRequest,
+        functions: &[FunctionDefinition],
+    ) -> AiResult<AiResponse> {
+        let body = self.build_request(request, Some(functions));
+        self.post_chat(&body).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::AiErrReason;
+    use crate::providers::stub::{StubResponse, StubServer};
+
+    const TAGS_BODY: &str = r#"{
+        "models": [
+            {"name": "qwen2.5:7b", "model": "qwen2.5:7b", "size": 4683087332},
+            {"name": "llama3.1:latest", "model": "llama3.1:latest", "size": 4920753328}
+        ]
+    }"#;
+
+    #[tokio::test]
+    async fn test_list_models_from_tags() {
+        let server = StubServer::start(vec![StubResponse::json(200, TAGS_BODY)]).await;
+        let provider = OllamaProvider::new(5).with_base_url(server.base_url().to_string());
+
+        let models = provider.list_models().await.unwrap();
+        let names: Vec<&str> = models.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, vec!["qwen2.5:7b", "llama3.1:latest"]);
+        assert!(models.iter().all(|m| m.provider == AiProviderType::Ollama));
+
+        assert!(provider.is_model_available("llama3.1").await);
+        assert!(!provider.is_model_available("gpt-4o").await);
+        assert_eq!(server.requests()[0].path, "/api/tags");
+    }
+
+    #[tokio::test]
+    async fn test_tags_status_and_cache() {
+        let server = StubServer::start(vec![
+            StubResponse::json(200, TAGS_BODY),
+            StubResponse::json(500, "upstream proxy error"),
+        ])
+        .await;
+        let provider = OllamaProvider::new(5).with_base_url(server.base_url().to_string());
+
+        // 列表缓存期内只请求一次 /api/tags
+        provider.list_models().await.unwrap();
+        assert!(provider.is_model_available("qwen2.5:7b").await);
+        assert_eq!(server.requests().len(), 1);
+
+        // 非成功状态按状态码映射，而不是 JSON 解析错误
+        let err = provider.fetch_tags().await.unwrap_err();
+        assert!(matches!(
+            err.reason(),
+            OrionAiReason::Ai(AiErrReason::ProviderUnavailable(_))
+        ));
+    }
+
+    #[test]
+    fn test_unknown_local_model_not_limited() {
+        let provider = OllamaProvider::new(5);
+        assert!(provider.check_token_limit("my-finetune:32k", 100_000));
+        assert!(provider.get_model_info("my-finetune:32k").is_none());
+        assert_eq!(
+            provider.estimate_cost("my-finetune:32k", 1000, 1000),
+            Some(0.0)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_health_check_reflects_daemon() {
+        let server = StubServer::start(vec![StubResponse::json(200, TAGS_BODY)]).await;
+        let provider = OllamaProvider::new(5).with_base_url(server.base_url().to_string());
+        assert!(provider.health_check().await.unwrap());
+
+        // 未监听的端口，模拟守护进程未启动
+        let down = OllamaProvider::new(1).with_base_url("http://127.0.0.1:1".to_string());
+        assert!(!down.health_check().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_chat_with_tool_calls() {
+        let server = StubServer::start(vec![StubResponse::json(
+            200,
+            r#"{
+                "model": "qwen2.5:7b",
+                "message": {
+                    "role": "assistant",
+                    "content": "",
+                    "tool_calls": [{"function": {"name": "git-status", "arguments": {"path": "."}}}]
+                },
+                "done": true,
+                "done_reason": "stop",
+                "prompt_eval_count": 42,
+                "eval_count": 8
+            }"#,
+        )])
+        .await;
+        let provider = OllamaProvider::new(5).with_base_url(server.base_url().to_string());
+
+        let request = AiRequest::builder()
+            .model("qwen2.5:7b")
+            .system_prompt("sys")
+            .user_prompt("status?")
+            .max_tokens(256)
+            .build();
+        let response = provider
+            .send_request_with_functions(&request, &crate::func::git::create_git_functions())
+            .await
+            .unwrap();
+
+        assert_eq!(response.provider, AiProviderType::Ollama);
+        assert_eq!(response.usage.total_tokens, 50);
+        let tool_calls = response.tool_calls.unwrap();
+        assert_eq!(tool_calls[0].function.name, "git-status");
+        assert_eq!(tool_calls[0].function.arguments, r#"{"path":"."}"#);
+
+        let captured = &server.requests()[0];
+        assert_eq!(captured.path, "/api/chat");
+        let body = captured.json();
+        assert_eq!(body["stream"], false);
+        assert_eq!(body["options"]["num_predict"], 256);
+        assert_eq!(body["messages"].as_array().unwrap().len(), 2);
+        assert_eq!(body["tools"][0]["type"], "function");
+    }
+
+    #[test]
+    fn test_model_not_found_error() {
+        let err = OllamaProvider::convert_response_from_text(
+            r#"{"error": "model \"mistral\" not found, try pulling it first"}"#,
+            "mistral",
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err.reason(),
+            OrionAiReason::Ai(AiErrReason::InvalidModel(_))
+        ));
+    }
+}
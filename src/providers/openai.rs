@@ -1,15 +1,19 @@
 use async_trait::async_trait;
-use log::debug;
-use orion_error::{ErrorOwe, ErrorWith};
+use log::{debug, warn};
+use orion_error::{ErrorOwe, ErrorWith, ToStructError, UvsConfFrom};
 use reqwest::{Client, header};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::sync::Arc;
-use std::time::Duration;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
+use crate::config::{CompatConfig, ModelCatalog, RetryPolicy};
 use crate::error::AiResult;
+use crate::error::OrionAiReason;
 use crate::provider::*;
 use crate::providers::resp::convert_response_from_text;
+use crate::providers::retry::{ensure_success, send_with_retry};
+use crate::providers::stream::openai_sse_stream;
 use getset::{Getters, MutGetters, Setters};
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -24,15 +28,46 @@ struct OpenAiRequest {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Message {
     pub role: String,
+    #[serde(default, deserialize_with = "null_as_empty")]
     pub content: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub tool_calls: Option<Vec<OpenAiToolCall>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+}
+
+impl From<&ChatMessage> for Message {
+    fn from(message: &ChatMessage) -> Self {
+        let tool_calls = message.tool_calls.as_ref().map(|calls| {
+            calls
+                .iter()
+                .map(|call| OpenAiToolCall {
+                    index: None,
+                    id: call.id.clone(),
+                    r#type: call.r#type.clone(),
+                    function: OpenAiFunctionCall {
+                        name: call.function.name.clone(),
+                        arguments: call.function.arguments.clone(),
+                    },
+                })
+                .collect()
+        });
+
+        Self {
+            role: message.role.to_string(),
+            content: message.content.clone(),
+            tool_calls,
+            tool_call_id: message.tool_call_id.clone(),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
 pub struct OpenAiResponse {
+    #[serde(default)]
     pub choices: Vec<Choice>,
     pub usage: Option<Usage>,
+    #[serde(default)]
     pub model: String,
 }
 
@@ -51,6 +86,7 @@ pub struct Choice {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct OpenAiToolCall {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub index: Option<u32>,
     pub id: String,
     pub r#type: String,
@@ -60,9 +96,30 @@ pub struct OpenAiToolCall {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct OpenAiFunctionCall {
     pub name: String,
+    #[serde(deserialize_with = "arguments_as_string")]
     pub arguments: String,
 }
 
+/// 带工具调用的回复中 `content` 可能为 null
+fn null_as_empty<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(Option::<String>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+/// GLM 的 `arguments` 可能直接返回 JSON 对象，统一转成字符串
+fn arguments_as_string<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::String(arguments) => Ok(arguments),
+        serde_json::Value::Null => Ok("{}".to_string()),
+        other => Ok(other.to_string()),
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct OpenAiTool {
     pub r#type: String,
@@ -87,60 +144,105 @@ struct OpenAiRequestWithTools {
     tool_choice: Option<serde_json::Value>,
 }
 
+#[derive(Debug, Serialize)]
+struct OpenAiStreamRequest {
+    model: String,
+    messages: Vec<Message>,
+    max_tokens: Option<usize>,
+    temperature: Option<f32>,
+    stream: bool,
+    stream_options: serde_json::Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<OpenAiTool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<serde_json::Value>,
+}
+
 impl OpenAiProvider {
     pub fn convert_to_openai_tools(
         functions: &[crate::provider::FunctionDefinition],
     ) -> Vec<OpenAiTool> {
         functions
             .iter()
-            .map(|f| {
-                let properties: serde_json::Map<String, serde_json::Value> = f
-                    .parameters
-                    .iter()
-                    .map(|p| {
-                        (
-                            p.name.clone(),
-                            serde_json::json!({
-                                "type": Self::map_parameter_type(&p.r#type),
-                                "description": p.description
-                            }),
-                        )
-                    })
-                    .collect();
-
-                let required: Vec<String> = f
-                    .parameters
-                    .iter()
-                    .filter(|p| p.required)
-                    .map(|p| p.name.clone())
-                    .collect();
-
-                OpenAiTool {
-                    r#type: "function".to_string(),
-                    function: OpenAiFunction {
-                        name: f.name.clone(),
-                        description: f.description.clone(),
-                        parameters: serde_json::json!({
-                            "type": "object",
-                            "properties": properties,
-                            "required": required
-                        }),
-                    },
-                }
+            .map(|f| OpenAiTool {
+                r#type: "function".to_string(),
+                function: OpenAiFunction {
+                    name: f.name.clone(),
+                    description: f.description.clone(),
+                    parameters: f.parameters_schema(),
+                },
             })
             .collect()
     }
 
-    fn map_parameter_type(param_type: &str) -> String {
-        match param_type {
-            "string" => "string".to_string(),
-            "array" => "array".to_string(),
-            "number" | "integer" => "number".to_string(),
-            "boolean" => "boolean".to_string(),
-            "object" => "object".to_string(),
-            _ => "string".to_string(), // 默认为 string
+    /// 将请求中的完整对话转换为 OpenAI messages
+    pub fn convert_messages(request: &AiRequest) -> Vec<Message> {
+        request.conversation().iter().map(Message::from).collect()
+    }
+}
+
+/// `GET /models` 的默认缓存时长
+pub const MODEL_LIST_TTL: Duration = Duration::from_secs(600);
+
+/// 请求失败时目录兜底列表的缓存时长，避免每次查询都等待失败的请求
+pub const MODEL_LIST_NEGATIVE_TTL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Deserialize)]
+struct OpenAiModelList {
+    #[serde(default)]
+    data: Vec<OpenAiModelEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAiModelEntry {
+    id: String,
+}
+
+/// 在线模型列表缓存，克隆出的 provider 共享同一份缓存
+#[derive(Clone, Debug)]
+pub struct ModelListCache {
+    ttl: Duration,
+    negative_ttl: Duration,
+    /// 过期时间与模型列表
+    entry: Arc<Mutex<Option<(Instant, Vec<ModelInfo>)>>>,
+}
+
+impl Default for ModelListCache {
+    fn default() -> Self {
+        Self::new(MODEL_LIST_TTL)
+    }
+}
+
+impl ModelListCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            negative_ttl: MODEL_LIST_NEGATIVE_TTL.min(ttl),
+            entry: Arc::new(Mutex::new(None)),
         }
     }
+
+    pub(crate) fn fresh(&self) -> Option<Vec<ModelInfo>> {
+        let entry = self.entry.lock().unwrap();
+        entry
+            .as_ref()
+            .filter(|(expires_at, _)| Instant::now() < *expires_at)
+            .map(|(_, models)| models.clone())
+    }
+
+    pub(crate) fn store(&self, models: Vec<ModelInfo>) {
+        *self.entry.lock().unwrap() = Some((Instant::now() + self.ttl, models));
+    }
+
+    /// 缓存目录兜底列表，按较短的失败缓存时长过期
+    fn store_fallback(&self, models: Vec<ModelInfo>) {
+        *self.entry.lock().unwrap() = Some((Instant::now() + self.negative_ttl, models));
+    }
+
+    /// 清空缓存，下次查询重新请求
+    pub fn invalidate(&self) {
+        *self.entry.lock().unwrap() = None;
+    }
 }
 
 #[derive(Clone, Debug, Getters, Setters, MutGetters)]
@@ -151,6 +253,11 @@ pub struct OpenAiProvider {
     base_url: String,
     organization: Option<String>,
     provider_type: AiProviderType,
+    retry: RetryPolicy,
+    model_cache: ModelListCache,
+    /// 只能通过 [`OpenAiProvider::with_compat`] 设置，以便校验请求头
+    #[getset(skip)]
+    compat: CompatConfig,
 }
 
 impl OpenAiProvider {
@@ -166,6 +273,9 @@ impl OpenAiProvider {
             api_key,
             base_url: "https://api.openai.com/v1".to_string(),
             organization: None,
+            retry: RetryPolicy::default(),
+            model_cache: ModelListCache::default(),
+            compat: CompatConfig::default(),
             provider_type: AiProviderType::OpenAi,
         }
     }
@@ -182,10 +292,14 @@ impl OpenAiProvider {
             api_key,
             base_url: "https://api.deepseek.com/v1".to_string(),
             organization: None,
+            retry: RetryPolicy::default(),
+            model_cache: ModelListCache::default(),
+            compat: CompatConfig::default(),
             provider_type: AiProviderType::DeepSeek,
         }
     }
-    pub fn kimi_k2(api_key: String, timeout_sec: u64) -> Self {
+    /// 创建Kimi (Moonshot) 兼容Provider (OpenAI格式)
+    pub fn kimi(api_key: String, timeout_sec: u64) -> Self {
         let client = Client::builder()
             .timeout(Duration::from_secs(timeout_sec))
             .build()
@@ -196,10 +310,37 @@ impl OpenAiProvider {
             api_key,
             base_url: "https://api.moonshot.cn/v1".to_string(),
             organization: None,
+            retry: RetryPolicy::default(),
+            model_cache: ModelListCache::default(),
+            compat: CompatConfig::default(),
             provider_type: AiProviderType::Kimi,
         }
     }
 
+    #[deprecated(note = "use `OpenAiProvider::kimi`")]
+    pub fn kimi_k2(api_key: String, timeout_sec: u64) -> Self {
+        Self::kimi(api_key, timeout_sec)
+    }
+
+    /// 创建智谱GLM兼容Provider (OpenAI格式)
+    pub fn glm(api_key: String, timeout_sec: u64) -> Self {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(timeout_sec))
+            .build()
+            .unwrap_or_else(|_| Client::new());
+
+        Self {
+            client: Arc::new(client),
+            api_key,
+            base_url: "https://open.bigmodel.cn/api/paas/v4".to_string(),
+            organization: None,
+            retry: RetryPolicy::default(),
+            model_cache: ModelListCache::default(),
+            compat: CompatConfig::default(),
+            provider_type: AiProviderType::Glm,
+        }
+    }
+
     /// 创建Groq兼容Provider (OpenAI格式)
     pub fn groq(api_key: String, timeout_sec: u64) -> Self {
         let client = Client::builder()
@@ -212,27 +353,158 @@ impl OpenAiProvider {
             api_key,
             base_url: "https://api.groq.com/openai/v1".to_string(),
             organization: None,
+            retry: RetryPolicy::default(),
+            model_cache: ModelListCache::default(),
+            compat: CompatConfig::default(),
             provider_type: AiProviderType::Groq,
         }
     }
 
+    /// 创建自定义的 OpenAI 兼容 Provider，如 vLLM、LM Studio、OpenRouter
+    pub fn custom(
+        provider_type: AiProviderType,
+        api_key: String,
+        base_url: String,
+        timeout_sec: u64,
+    ) -> Self {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(timeout_sec))
+            .build()
+            .unwrap_or_else(|_| Client::new());
+
+        Self {
+            client: Arc::new(client),
+            api_key,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            organization: None,
+            retry: RetryPolicy::default(),
+            provider_type,
+            model_cache: ModelListCache::default(),
+            compat: CompatConfig::default(),
+        }
+    }
+
     pub fn with_base_url(mut self, url: String) -> Self {
         self.base_url = url;
         self
     }
 
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
     pub fn with_organization(mut self, org: String) -> Self {
         self.organization = Some(org);
         self
     }
 
-    fn create_headers(&self) -> header::HeaderMap {
+    pub fn compat(&self) -> &CompatConfig {
+        &self.compat
+    }
+
+    /// 设置鉴权方式和附加请求头，非法的头名称或取值返回配置错误
+    pub fn with_compat(mut self, compat: CompatConfig) -> AiResult<Self> {
+        let names = std::iter::once(&compat.auth_header).chain(compat.headers.keys());
+        for name in names {
+            header::HeaderName::from_bytes(name.as_bytes()).map_err(|e| {
+                OrionAiReason::from_conf(format!("{}: header {name}: {e}", self.provider_type))
+                    .to_err()
+            })?;
+        }
+        for (name, value) in &compat.headers {
+            header::HeaderValue::from_str(value).map_err(|e| {
+                OrionAiReason::from_conf(format!("{}: header {name}: {e}", self.provider_type))
+                    .to_err()
+            })?;
+        }
+        self.compat = compat;
+        Ok(self)
+    }
+
+    /// 设置在线模型列表的缓存时长
+    pub fn with_model_list_ttl(mut self, ttl: Duration) -> Self {
+        self.model_cache = ModelListCache::new(ttl);
+        self
+    }
+
+    /// 请求 `GET {base_url}/models`，并合并目录中的窗口和价格信息
+    pub async fn fetch_models(&self) -> AiResult<Vec<ModelInfo>> {
+        let url = format!("{}/models", self.base_url);
+        let response = self
+            .client
+            .get(&url)
+            .headers(self.create_headers()?)
+            .send()
+            .await
+            .owe_res()
+            .with(url.clone())?;
+        let response = ensure_success(response, self.provider_type, "")
+            .await
+            .with(url)?;
+        let response_text = response.text().await.owe_data()?;
+        debug!("Raw models body: {response_text}");
+        let list: OpenAiModelList = serde_json::from_str(&response_text).owe_data()?;
+
+        let catalog = ModelCatalog::global();
+        Ok(list
+            .data
+            .into_iter()
+            .map(|entry| {
+                catalog
+                    .get(self.provider_type, &entry.id)
+                    .cloned()
+                    .unwrap_or_else(|| ModelInfo {
+                        name: entry.id,
+                        provider: self.provider_type,
+                        max_tokens: 0,
+                        supports_images: false,
+                        supports_reasoning: false,
+                        cost_per_1k_input: 0.0,
+                        cost_per_1k_output: 0.0,
+                    })
+            })
+            .collect())
+    }
+
+    /// GLM 与 Kimi 只接受 `[0, 1]` 区间的 temperature
+    fn temperature(&self, request: &AiRequest) -> Option<f32> {
+        match self.provider_type {
+            AiProviderType::Glm | AiProviderType::Kimi => {
+                request.temperature.map(|t| t.clamp(0.0, 1.0))
+            }
+            _ => request.temperature,
+        }
+    }
+
+    /// 构建请求头，密钥或组织名中含有不能放入请求头的字符时返回配置错误
+    fn create_headers(&self) -> AiResult<header::HeaderMap> {
+        let header_value = |name: &str, value: &str| {
+            header::HeaderValue::from_str(value).map_err(|e| {
+                OrionAiReason::from_conf(format!("{}: header {name}: {e}", self.provider_type))
+                    .to_err()
+            })
+        };
         let mut headers = header::HeaderMap::new();
 
-        headers.insert(
-            header::AUTHORIZATION,
-            header::HeaderValue::from_str(&format!("Bearer {}", self.api_key)).unwrap(),
-        );
+        // 本地服务可不配置密钥
+        if !self.api_key.is_empty() {
+            let name = header::HeaderName::from_bytes(self.compat.auth_header.as_bytes()).map_err(
+                |e| {
+                    OrionAiReason::from_conf(format!(
+                        "{}: header {}: {e}",
+                        self.provider_type, self.compat.auth_header
+                    ))
+                    .to_err()
+                },
+            )?;
+            let value = if self.compat.auth_scheme.is_empty() {
+                self.api_key.clone()
+            } else {
+                format!("{} {}", self.compat.auth_scheme, self.api_key)
+            };
+            headers.insert(name, header_value(&self.compat.auth_header, &value)?);
+        }
 
         headers.insert(
             header::CONTENT_TYPE,
@@ -241,94 +513,19 @@ impl OpenAiProvider {
 
         if let Some(org) = &self.organization {
             headers.insert(
-                reqwest::header::HeaderName::from_static("OpenAI-Organization"),
-                header::HeaderValue::from_str(org).unwrap(),
+                reqwest::header::HeaderName::from_static("openai-organization"),
+                header_value("openai-organization", org)?,
             );
         }
 
-        headers
-    }
-
-    fn map_model_to_info(&self, model: &str) -> ModelInfo {
-        let model_map: HashMap<&str, (usize, bool, bool, f64, f64, AiProviderType)> =
-            HashMap::from([
-                (
-                    "glm-4.5",
-                    (128000, true, false, 0.00007, 0.00028, AiProviderType::Glm),
-                ),
-                // OpenAI models
-                (
-                    "gpt-4o",
-                    (128000, true, true, 0.005, 0.015, AiProviderType::OpenAi),
-                ),
-                // DeepSeek models (99.5% cost reduction)
-                (
-                    "deepseek-chat",
-                    (
-                        32768,
-                        true,
-                        false,
-                        0.00007,
-                        0.00028,
-                        AiProviderType::DeepSeek,
-                    ),
-                ),
-                (
-                    "deepseek-coder",
-                    (
-                        32768,
-                        true,
-                        false,
-                        0.00007,
-                        0.00028,
-                        AiProviderType::DeepSeek,
-                    ),
-                ),
-                (
-                    "deepseek-reasoner",
-                    (
-                        32768,
-                        true,
-                        true,
-                        0.00014,
-                        0.00056,
-                        AiProviderType::DeepSeek,
-                    ),
-                ),
-                // Groq models
-                (
-                    "mixtral-8x7b-32768",
-                    (32768, false, false, 0.00027, 0.00027, AiProviderType::Groq),
-                ),
-                (
-                    "llama3-70b-8192",
-                    (8192, false, false, 0.00059, 0.00079, AiProviderType::Groq),
-                ),
-                (
-                    "gemma2-9b-it",
-                    (8192, false, false, 0.00010, 0.00010, AiProviderType::Groq),
-                ),
-            ]);
-
-        let default = (4096, false, false, 0.001, 0.002, AiProviderType::OpenAi);
-        let (
-            max_tokens,
-            supports_images,
-            supports_reasoning,
-            input_cost,
-            output_cost,
-            provider_type,
-        ) = model_map.get(model).unwrap_or(&default);
-
-        ModelInfo {
-            name: model.to_string(),
-            provider: *provider_type,
-            max_tokens: *max_tokens,
-            supports_images: *supports_images,
-            supports_reasoning: *supports_reasoning,
-            cost_per_1k_input: *input_cost,
-            cost_per_1k_output: *output_cost,
+        for (name, value) in &self.compat.headers {
+            if let Ok(name) = header::HeaderName::from_bytes(name.as_bytes()) {
+                let value = header_value(name.as_str(), value)?;
+                headers.insert(name, value);
+            }
         }
+
+        Ok(headers)
     }
 }
 
@@ -345,56 +542,66 @@ impl AiProvider for OpenAiProvider {
         }
     }
 
+    /// 在线列表按 TTL 缓存；目录中未登记的模型 `max_tokens` 为 0，表示未知。
+    /// 请求失败时退回目录中的静态列表，按 [`MODEL_LIST_NEGATIVE_TTL`] 缓存
     async fn list_models(&self) -> AiResult<Vec<ModelInfo>> {
-        let models = match self.provider_type {
-            AiProviderType::Glm => vec!["glm-4.5"],
-            AiProviderType::OpenAi => vec!["gpt-4o", "gpt-4o-mini", "gpt-4-turbo", "gpt-3.5-turbo"],
-            AiProviderType::DeepSeek => {
-                vec!["deepseek-chat", "deepseek-coder", "deepseek-reasoner"]
+        if let Some(models) = self.model_cache.fresh() {
+            return Ok(models);
+        }
+        match self.fetch_models().await {
+            Ok(models) => {
+                self.model_cache.store(models.clone());
+                Ok(models)
             }
-            AiProviderType::Groq => vec!["mixtral-8x7b-32768", "llama3-70b-8192", "gemma2-9b-it"],
-            _ => vec!["gpt-4o-mini"],
-        };
+            Err(e) => {
+                warn!(
+                    "{} list models failed, using catalog: {e}",
+                    self.provider_type
+                );
+                let models = ModelCatalog::global().models_of(self.provider_type);
+                self.model_cache.store_fallback(models.clone());
+                Ok(models)
+            }
+        }
+    }
 
-        Ok(models.iter().map(|m| self.map_model_to_info(m)).collect())
+    /// 直接请求模型列表，不使用缓存和目录兜底；成功的结果写入缓存
+    async fn health_check(&self) -> AiResult<bool> {
+        match self.fetch_models().await {
+            Ok(models) => {
+                self.model_cache.store(models);
+                Ok(true)
+            }
+            Err(e) => {
+                warn!("{} health check failed: {e}", self.provider_type);
+                Ok(false)
+            }
+        }
     }
 
     async fn send_request(&self, request: &AiRequest) -> AiResult<AiResponse> {
-        let system_msg = Message {
-            role: "system".to_string(),
-            content: request.system_prompt.clone(),
-            tool_calls: None,
-        };
-
-        let user_msg = Message {
-            role: "user".to_string(),
-            content: request.user_prompt.clone(),
-            tool_calls: None,
-        };
-
         let openai_request = OpenAiRequest {
             model: request.model.clone(),
-            messages: vec![system_msg, user_msg],
+            messages: Self::convert_messages(request),
             max_tokens: request.max_tokens,
-            temperature: request.temperature,
+            temperature: self.temperature(request),
             stream: false,
         };
         debug!("send client request: {openai_request:#?}");
 
         let url = format!("{}/chat/completions", self.base_url);
         debug!("send client url: {url}");
-        let response = self
-            .client
-            .post(&url)
-            .headers(self.create_headers())
-            .json(&openai_request)
-            .send()
-            .await
-            .owe_res()
-            .with(url)?;
+        let headers = self.create_headers()?;
+        let response = send_with_retry(&self.retry, self.provider_type, &request.model, || {
+            self.client
+                .post(&url)
+                .headers(headers.clone())
+                .json(&openai_request)
+        })
+        .await
+        .with(url)?;
 
         debug!("Client response: {response:#?}");
-        println!("{} think....", request.model);
 
         // Get raw response text first
         let response_text = response.text().await.owe_data()?;
@@ -412,15 +619,22 @@ impl AiProvider for OpenAiProvider {
     }
 
     fn estimate_cost(&self, model: &str, input_tokens: usize, output_tokens: usize) -> Option<f64> {
-        let model_info = self.map_model_to_info(model);
-        let cost = (input_tokens as f64 * model_info.cost_per_1k_input / 1000.0)
-            + (output_tokens as f64 * model_info.cost_per_1k_output / 1000.0);
-        Some(cost)
+        ModelCatalog::global().estimate_cost_or_default(
+            self.provider_type,
+            model,
+            input_tokens,
+            output_tokens,
+        )
     }
 
     fn check_token_limit(&self, model: &str, max_tokens: usize) -> bool {
-        let model_info = self.map_model_to_info(model);
-        max_tokens <= model_info.max_tokens
+        ModelCatalog::global().check_token_limit(self.provider_type, model, max_tokens)
+    }
+
+    fn get_model_info(&self, model: &str) -> Option<ModelInfo> {
+        ModelCatalog::global()
+            .get(self.provider_type, model)
+            .cloned()
     }
 
     fn get_config_keys(&self) -> Vec<&'static str> {
@@ -428,6 +642,8 @@ impl AiProvider for OpenAiProvider {
             AiProviderType::OpenAi => vec!["OPENAI_API_KEY", "OPENAI_ORG_ID", "OPENAI_BASE_URL"],
             AiProviderType::DeepSeek => vec!["DEEPSEEK_API_KEY", "DEEPSEEK_BASE_URL"],
             AiProviderType::Groq => vec!["GROQ_API_KEY", "GROQ_BASE_URL"],
+            AiProviderType::Glm => vec!["GLM_API_KEY", "GLM_BASE_URL"],
+            AiProviderType::Kimi => vec!["KIMI_API_KEY", "KIMI_BASE_URL"],
             _ => vec!["API_KEY", "BASE_URL"],
         }
     }
@@ -436,6 +652,50 @@ impl AiProvider for OpenAiProvider {
         true // OpenAI 支持函数调用
     }
 
+    async fn send_request_stream(&self, request: &AiRequest) -> AiResult<AiResponseStream> {
+        let tools = match &request.functions {
+            Some(functions) if request.enable_function_calling && !functions.is_empty() => {
+                Some(Self::convert_to_openai_tools(functions))
+            }
+            _ => None,
+        };
+        let tool_choice = tools.as_ref().map(|_| serde_json::json!("auto"));
+
+        let openai_request = OpenAiStreamRequest {
+            model: request.model.clone(),
+            messages: Self::convert_messages(request),
+            max_tokens: request.max_tokens,
+            temperature: self.temperature(request),
+            stream: true,
+            stream_options: serde_json::json!({ "include_usage": true }),
+            tools,
+            tool_choice,
+        };
+        debug!("send client stream request: {openai_request:#?}");
+
+        let url = format!("{}/chat/completions", self.base_url);
+        let headers = self.create_headers()?;
+        let response = send_with_retry(&self.retry, self.provider_type, &request.model, || {
+            self.client
+                .post(&url)
+                .headers(headers.clone())
+                .json(&openai_request)
+        })
+        .await
+        .with(url.clone())?;
+
+        let provider = self.clone();
+        let model = request.model.clone();
+        Ok(openai_sse_stream(
+            response.bytes_stream(),
+            self.provider_type,
+            request.model.clone(),
+            move |input_tokens, output_tokens| {
+                provider.estimate_cost(&model, input_tokens, output_tokens)
+            },
+        ))
+    }
+
     async fn send_request_with_functions(
         &self,
         request: &crate::provider::AiRequest,
@@ -445,20 +705,9 @@ impl AiProvider for OpenAiProvider {
 
         let openai_request = OpenAiRequestWithTools {
             model: request.model.clone(),
-            messages: vec![
-                Message {
-                    role: "system".to_string(),
-                    content: request.system_prompt.clone(),
-                    tool_calls: None,
-                },
-                Message {
-                    role: "user".to_string(),
-                    content: request.user_prompt.clone(),
-                    tool_calls: None,
-                },
-            ],
+            messages: Self::convert_messages(request),
             max_tokens: request.max_tokens,
-            temperature: request.temperature,
+            temperature: self.temperature(request),
             stream: false,
             tools: Some(openai_tools),
             tool_choice: Some(serde_json::json!("auto")),
@@ -471,15 +720,15 @@ impl AiProvider for OpenAiProvider {
 
         let url = format!("{}/chat/completions", self.base_url);
 
-        let response = self
-            .client
-            .post(&url)
-            .headers(self.create_headers())
-            .json(&openai_request)
-            .send()
-            .await
-            .owe_res()
-            .with(url.clone())?;
+        let headers = self.create_headers()?;
+        let response = send_with_retry(&self.retry, self.provider_type, &request.model, || {
+            self.client
+                .post(&url)
+                .headers(headers.clone())
+                .json(&openai_request)
+        })
+        .await
+        .with(url.clone())?;
 
         let response_text = response.text().await.owe_data()?;
         debug!("Raw response body: {response_text}");
@@ -495,3 +744,330 @@ impl AiProvider for OpenAiProvider {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FunctionDefinition, FunctionParameter, func::git::create_git_functions};
+
+    #[test]
+    fn test_convert_tools_keeps_parameter_schema() {
+        let functions = create_git_functions();
+        let tools =
+            serde_json::to_value(OpenAiProvider::convert_to_openai_tools(&functions)).unwrap();
+        let git_add = tools
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|t| t["function"]["name"] == "git-add")
+            .unwrap();
+        let files = &git_add["function"]["parameters"]["properties"]["files"];
+        assert_eq!(files["type"], "array");
+        assert_eq!(files["items"]["type"], "string");
+        assert_eq!(git_add["function"]["parameters"]["required"][0], "files");
+
+        let count = FunctionParameter::new("count", "integer", "次数", false)
+            .with_schema(serde_json::json!({"minimum": 1, "default": 4}));
+        let schema = count.to_schema();
+        assert_eq!(schema["type"], "integer");
+        assert_eq!(schema["minimum"], 1);
+        assert_eq!(schema["default"], 4);
+        assert_eq!(
+            FunctionParameter::new("x", "unknown", "", false).to_schema()["type"],
+            "string"
+        );
+    }
+
+    #[test]
+    fn test_convert_tools_with_full_schema() {
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": {
+                "changes": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "path": {"type": "string"},
+                            "mode": {"type": "string", "enum": ["add", "remove"]}
+                        },
+                        "required": ["path"]
+                    }
+                },
+                "note": {"type": ["string", "null"], "description": "备注"}
+            },
+            "required": ["changes"],
+            "additionalProperties": false
+        });
+        let function = FunctionDefinition::from_schema("apply-changes", "应用变更", schema.clone());
+        assert_eq!(function.parameters[0].r#type, "array");
+        assert_eq!(function.parameters[1].r#type, "string");
+        assert_eq!(
+            function.schema,
+            Some(serde_json::json!({"additionalProperties": false}))
+        );
+        let tools = OpenAiProvider::convert_to_openai_tools(&[function]);
+        assert_eq!(tools[0].function.parameters, schema);
+    }
+
+    #[test]
+    fn test_convert_messages_with_tool_round() {
+        let call = FunctionCall {
+            index: Some(0),
+            id: "call_1".to_string(),
+            r#type: "function".to_string(),
+            function: FunctionCallInfo {
+                name: "git-status".to_string(),
+                arguments: "{}".to_string(),
+            },
+        };
+        let result = FunctionResult {
+            name: "git-status".to_string(),
+            result: serde_json::json!({"status": ""}),
+            error: None,
+        };
+        let response = AiResponse {
+            content: String::new(),
+            model: "deepseek-chat".to_string(),
+            usage: UsageInfo {
+                prompt_tokens: 0,
+                completion_tokens: 0,
+                total_tokens: 0,
+                estimated_cost: None,
+            },
+            finish_reason: Some("tool_calls".to_string()),
+            provider: AiProviderType::DeepSeek,
+            metadata: HashMap::new(),
+            tool_calls: Some(vec![call.clone()]),
+        };
+
+        let mut request = AiRequest::builder()
+            .model("deepseek-chat")
+            .system_prompt("sys")
+            .user_prompt("status?")
+            .build();
+        request.push_tool_round(&response, &[(call, result)]);
+
+        let messages = serde_json::to_value(OpenAiProvider::convert_messages(&request)).unwrap();
+        let roles: Vec<&str> = messages
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|m| m["role"].as_str().unwrap())
+            .collect();
+        assert_eq!(roles, vec!["system", "user", "assistant", "tool"]);
+        assert_eq!(messages[2]["tool_calls"][0]["id"], "call_1");
+        assert!(messages[2]["tool_calls"][0].get("index").is_none());
+        assert_eq!(messages[3]["tool_call_id"], "call_1");
+        assert_eq!(messages[3]["content"], r#"{"status":""}"#);
+        assert!(messages[1].get("tool_call_id").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_list_models_live_and_cached() {
+        use crate::providers::stub::{StubResponse, StubServer};
+
+        let server = StubServer::start(vec![StubResponse::json(
+            200,
+            r#"{"object":"list","data":[{"id":"gpt-4o","object":"model"},{"id":"gpt-5-preview","object":"model"}]}"#,
+        )])
+        .await;
+        let provider = OpenAiProvider::new("sk-test".to_string(), 5)
+            .with_base_url(server.base_url().to_string());
+
+        let models = provider.list_models().await.unwrap();
+        assert_eq!(models.len(), 2);
+        assert_eq!(models[0].max_tokens, 128000);
+        assert_eq!(models[1].name, "gpt-5-preview");
+        assert_eq!(models[1].max_tokens, 0);
+        assert!(provider.is_model_available("gpt-5-preview").await);
+        assert!(!provider.is_model_available("gpt-3.5-turbo").await);
+
+        let captured = server.requests();
+        assert_eq!(captured.len(), 1);
+        assert_eq!(captured[0].method, "GET");
+        assert_eq!(captured[0].path, "/models");
+        assert_eq!(captured[0].header("authorization"), Some("Bearer sk-test"));
+
+        provider.model_cache().invalidate();
+        provider.list_models().await.unwrap();
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_list_models_falls_back_to_catalog() {
+        use crate::providers::stub::{StubResponse, StubServer};
+
+        let server = StubServer::start(vec![StubResponse::json(500, r#"{"error":"down"}"#)]).await;
+        let provider = OpenAiProvider::deep_seek("sk-test".to_string(), 5)
+            .with_base_url(server.base_url().to_string())
+            .with_model_list_ttl(Duration::from_secs(60));
+
+        let models = provider.list_models().await.unwrap();
+        assert_eq!(
+            models,
+            ModelCatalog::global().models_of(AiProviderType::DeepSeek)
+        );
+        assert!(provider.is_model_available("deepseek-chat").await);
+        // 兜底列表短时缓存，不重复请求
+        assert_eq!(server.requests().len(), 1);
+        assert!(!provider.health_check().await.unwrap());
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_health_check_refreshes_cache() {
+        use crate::providers::stub::{StubResponse, StubServer};
+
+        let server = StubServer::start(vec![
+            StubResponse::json(503, r#"{"error":"down"}"#),
+            StubResponse::json(200, r#"{"object":"list","data":[{"id":"gpt-4o"}]}"#),
+        ])
+        .await;
+        let provider = OpenAiProvider::new("sk-test".to_string(), 5)
+            .with_base_url(server.base_url().to_string());
+
+        // 缓存中的兜底列表不掩盖服务状态
+        provider.list_models().await.unwrap();
+        assert!(provider.health_check().await.unwrap());
+        let models = provider.list_models().await.unwrap();
+        assert_eq!(models.len(), 1);
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[test]
+    fn test_glm_and_kimi_profiles() {
+        let glm = OpenAiProvider::glm("key".to_string(), 5);
+        assert_eq!(glm.provider_type(), AiProviderType::Glm);
+        assert_eq!(glm.base_url(), "https://open.bigmodel.cn/api/paas/v4");
+        assert_eq!(glm.get_config_keys(), vec!["GLM_API_KEY", "GLM_BASE_URL"]);
+        assert!(glm.get_model_info("glm-4.5-air").is_some());
+
+        let kimi = OpenAiProvider::kimi("key".to_string(), 5);
+        assert_eq!(kimi.provider_type(), AiProviderType::Kimi);
+        assert_eq!(kimi.base_url(), "https://api.moonshot.cn/v1");
+        assert_eq!(
+            kimi.get_config_keys(),
+            vec!["KIMI_API_KEY", "KIMI_BASE_URL"]
+        );
+        assert!(kimi.get_model_info("moonshot-v1-32k").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_glm_tool_call_fixture() {
+        use crate::providers::stub::{StubResponse, StubServer};
+
+        let server = StubServer::start(vec![StubResponse::json(
+            200,
+            include_str!("fixtures/glm_tool_call.json"),
+        )])
+        .await;
+        let provider = OpenAiProvider::glm("glm-key".to_string(), 5)
+            .with_base_url(server.base_url().to_string());
+        let request = AiRequest::builder()
+            .model("glm-4.5")
+            .user_prompt("git-status")
+            .build();
+
+        let response = provider
+            .send_request_with_functions(&request, &create_git_functions())
+            .await
+            .unwrap();
+        assert_eq!(response.provider, AiProviderType::Glm);
+        assert_eq!(response.content, "");
+        let calls = response.tool_calls.unwrap();
+        assert_eq!(calls[0].function.name, "git-status");
+        assert_eq!(calls[0].function.arguments, r#"{"path":"."}"#);
+        let cost = response.usage.estimated_cost.unwrap();
+        assert!((cost - (0.32 * 0.00007 + 0.018 * 0.00028)).abs() < 1e-12);
+
+        let captured = server.requests();
+        assert_eq!(captured[0].path, "/chat/completions");
+        assert_eq!(captured[0].header("authorization"), Some("Bearer glm-key"));
+    }
+
+    #[tokio::test]
+    async fn test_kimi_chat_fixture() {
+        use crate::providers::stub::{StubResponse, StubServer};
+
+        let server = StubServer::start(vec![StubResponse::json(
+            200,
+            include_str!("fixtures/kimi_chat.json"),
+        )])
+        .await;
+        let provider = OpenAiProvider::kimi("kimi-key".to_string(), 5)
+            .with_base_url(server.base_url().to_string());
+        let request = AiRequest::builder()
+            .model("kimi-k2-0711-preview")
+            .user_prompt("status?")
+            .temperature(1.5)
+            .build();
+
+        let response = provider.send_request(&request).await.unwrap();
+        assert_eq!(response.provider, AiProviderType::Kimi);
+        assert_eq!(response.model, "kimi-k2-0711-preview");
+        let cost = response.usage.estimated_cost.unwrap();
+        assert!((cost - 0.0028).abs() < 1e-9);
+        assert_eq!(server.requests()[0].json()["temperature"], 1.0);
+    }
+
+    #[tokio::test]
+    async fn test_custom_provider_headers() {
+        use crate::providers::stub::{StubResponse, StubServer};
+
+        let server = StubServer::start(vec![StubResponse::json(
+            200,
+            include_str!("fixtures/kimi_chat.json"),
+        )])
+        .await;
+        let gateway = AiProviderType::custom("gateway");
+        let compat = CompatConfig {
+            auth_header: "api-key".to_string(),
+            auth_scheme: String::new(),
+            headers: HashMap::from([("X-Team".to_string(), "ai".to_string())]),
+            model_prefixes: Vec::new(),
+        };
+        let provider = OpenAiProvider::custom(
+            gateway,
+            "secret".to_string(),
+            format!("{}/", server.base_url()),
+            5,
+        )
+        .with_compat(compat)
+        .unwrap();
+        let request = AiRequest::builder()
+            .model("kimi-k2-0711-preview")
+            .user_prompt("hi")
+            .build();
+
+        let response = provider.send_request(&request).await.unwrap();
+        assert_eq!(response.provider, gateway);
+        assert_eq!(response.usage.estimated_cost, None);
+
+        let captured = server.requests();
+        assert_eq!(captured[0].path, "/chat/completions");
+        assert_eq!(captured[0].header("api-key"), Some("secret"));
+        assert_eq!(captured[0].header("authorization"), None);
+        assert_eq!(captured[0].header("x-team"), Some("ai"));
+
+        let bad = CompatConfig {
+            headers: HashMap::from([("bad header".to_string(), "x".to_string())]),
+            ..Default::default()
+        };
+        assert!(
+            OpenAiProvider::custom(gateway, String::new(), server.base_url().to_string(), 5)
+                .with_compat(bad)
+                .is_err()
+        );
+
+        // 密钥中的换行不能放入请求头，返回配置错误而不是 panic
+        let provider = OpenAiProvider::custom(
+            gateway,
+            "secret\n".to_string(),
+            server.base_url().to_string(),
+            5,
+        );
+        let err = provider.send_request(&request).await.unwrap_err();
+        assert!(matches!(err.reason(), OrionAiReason::Uvs(_)), "{err}");
+    }
+}
@@ -0,0 +1,431 @@
+//! 录制与回放
+//!
+//! [`RecordingProvider`] 包装真实 provider，把成功的交互（含完整结束的流式响应）以 [`ThreadRecord`]
+//! JSONL 写入夹具文件；[`ReplayStore`] 按请求指纹索引夹具，供 `MockProvider` 离线回放。
+//!
+//! 夹具记录的是 provider 实际收到的请求（路由后的模型名、最终提示词）。Thread 的 JSONL 日志
+//! 记录的是路由前的请求，指纹对不上，不能作为夹具使用。
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures_util::StreamExt;
+use log::warn;
+use orion_error::ToStructError;
+
+use crate::error::{AiErrReason, AiResult, OrionAiReason};
+use crate::provider::*;
+use crate::providers::stream::StreamCollector;
+use crate::thread::ThreadRecord;
+
+/// 请求指纹：对模型、系统提示、用户输入和历史消息做 FNV-1a 哈希
+///
+/// 温度、max_tokens 和函数定义不参与计算，调整它们不会使夹具失效
+pub fn request_fingerprint(request: &AiRequest) -> String {
+    let key = serde_json::json!({
+        "model": request.model,
+        "system_prompt": request.system_prompt,
+        "user_prompt": request.user_prompt,
+        "messages": request.messages,
+    });
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in key.to_string().bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{hash:016x}")
+}
+
+/// 按请求指纹索引的回放数据
+///
+/// 同一指纹录制了多次时按录制顺序依次返回，用完后重复最后一次
+#[derive(Debug, Default)]
+pub struct ReplayStore {
+    responses: HashMap<String, Vec<AiResponse>>,
+    cursors: Mutex<HashMap<String, usize>>,
+}
+
+impl ReplayStore {
+    pub fn from_records(records: impl IntoIterator<Item = ThreadRecord>) -> Self {
+        let mut responses: HashMap<String, Vec<AiResponse>> = HashMap::new();
+        for record in records {
+            responses
+                .entry(request_fingerprint(&record.request))
+                .or_default()
+                .push(record.response);
+        }
+        Self {
+            responses,
+            cursors: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 从单个 JSONL 文件或目录下的全部 `.jsonl` 文件加载
+    pub fn load(path: &Path) -> AiResult<Self> {
+        if !path.is_dir() {
+            return Ok(Self::from_records(ThreadRecord::read_jsonl(path)?));
+        }
+        let entries = std::fs::read_dir(path).map_err(|e| {
+            OrionAiReason::from(AiErrReason::ContextError(format!(
+                "Failed to read directory {}: {}",
+                path.display(),
+                e
+            )))
+            .to_err()
+        })?;
+        let mut files: Vec<PathBuf> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|file| file.extension().is_some_and(|ext| ext == "jsonl"))
+            .collect();
+        files.sort();
+        let mut records = Vec::new();
+        for file in files {
+            records.extend(ThreadRecord::read_jsonl(&file)?);
+        }
+        Ok(Self::from_records(records))
+    }
+
+    pub fn len(&self) -> usize {
+        self.responses.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.responses.is_empty()
+    }
+
+    /// 取出请求对应的录制响应
+    pub fn replay(&self, request: &AiRequest) -> AiResult<AiResponse> {
+        let fingerprint = request_fingerprint(request);
+        let recorded = self.responses.get(&fingerprint).ok_or_else(|| {
+            OrionAiReason::from(AiErrReason::InvalidInput(format!(
+                "no recorded response for model {} (fingerprint {fingerprint})",
+                request.model
+            )))
+            .to_err()
+        })?;
+        let mut cursors = self.cursors.lock().expect("replay cursor lock");
+        let cursor = cursors.entry(fingerprint).or_insert(0);
+        let response = recorded[(*cursor).min(recorded.len() - 1)].clone();
+        *cursor += 1;
+        Ok(response)
+    }
+}
+
+/// 夹具录制器，多个 [`RecordingProvider`] 可共享同一个录制器写入同一文件
+///
+/// 写入与 Thread 记录共用文件锁，`seq` 在锁内分配，多个进程录制到同一夹具也不会交错或重号
+pub struct FixtureRecorder {
+    path: PathBuf,
+    session_id: String,
+}
+
+impl FixtureRecorder {
+    pub fn open(path: PathBuf) -> AiResult<Arc<Self>> {
+        let session_id = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "recording".to_string());
+        Ok(Arc::new(Self { path, session_id }))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    async fn record(
+        &self,
+        start_time: DateTime<Utc>,
+        request: &AiRequest,
+        response: &AiResponse,
+    ) -> AiResult<()> {
+        let record = ThreadRecord {
+            session_id: self.session_id.clone(),
+            seq: 0,
+            timestamp: start_time.to_rfc3339(),
+            latency_ms: (Utc::now() - start_time).num_milliseconds().max(0) as u64,
+            request: request.clone(),
+            response: response.clone(),
+        };
+        record.append_jsonl(&self.path).await.map(drop)
+    }
+
+    /// 夹具写入失败不影响本次调用，只记录警告
+    async fn record_or_warn(
+        &self,
+        start_time: DateTime<Utc>,
+        request: &AiRequest,
+        response: &AiResponse,
+    ) {
+        if let Err(e) = self.record(start_time, request, response).await {
+            warn!("failed to record fixture to {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// 录制包装：透明转发到内部 provider，并把成功的交互追加到夹具文件
+///
+/// 夹具写入失败只记录警告，不影响调用结果
+pub struct RecordingProvider {
+    inner: Arc<dyn AiProvider>,
+    recorder: Arc<FixtureRecorder>,
+}
+
+impl RecordingProvider {
+    pub fn new(inner: Arc<dyn AiProvider>, recorder: Arc<FixtureRecorder>) -> Self {
+        Self { inner, recorder }
+    }
+
+    async fn record<F, Fut>(&self, request: &AiRequest, send: F) -> AiResult<AiResponse>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = AiResult<AiResponse>>,
+    {
+        let start_time = Utc::now();
+        let response = send().await?;
+        self.recorder
+            .record_or_warn(start_time, request, &response)
+            .await;
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl AiProvider for RecordingProvider {
+    fn provider_type(&self) -> AiProviderType {
+        self.inner.provider_type()
+    }
+
+    async fn is_model_available(&self, model: &str) -> bool {
+        self.inner.is_model_available(model).await
+    }
+
+    async fn list_models(&self) -> AiResult<Vec<ModelInfo>> {
+        self.inner.list_models().await
+    }
+
+    async fn send_request(&self, request: &AiRequest) -> AiResult<AiResponse> {
+        self.record(request, || self.inner.send_request(request))
+            .await
+    }
+
+    /// 边转发边聚合事件，流正常结束后把聚合出的响应写入夹具；流内出错则不录制
+    async fn send_request_stream(&self, request: &AiRequest) -> AiResult<AiResponseStream> {
+        let start_time = Utc::now();
+        let stream = self.inner.send_request_stream(request).await?;
+        let state = (
+            stream,
+            Some(StreamCollector::new()),
+            self.recorder.clone(),
+            request.clone(),
+            self.inner.provider_type(),
+        );
+        Ok(Box::pin(futures_util::stream::unfold(
+            state,
+            move |(mut stream, mut collector, recorder, request, provider)| async move {
+                match stream.next().await {
+                    Some(event) => {
+                        match &event {
+                            Ok(event) => {
+                                if let Some(collector) = collector.as_mut() {
+                                    collector.push(event);
+                                }
+                            }
+                            Err(_) => collector = None,
+                        }
+                        Some((event, (stream, collector, recorder, request, provider)))
+                    }
+                    None => {
+                        if let Some(collector) = collector {
+                            let response = collector.into_response(provider, &request.model);
+                            recorder
+                                .record_or_warn(start_time, &request, &response)
+                                .await;
+                        }
+                        None
+                    }
+                }
+            },
+        )))
+    }
+
+    fn get_config_keys(&self) -> Vec<&'static str> {
+        self.inner.get_config_keys()
+    }
+
+    async fn health_check(&self) -> AiResult<bool> {
+        self.inner.health_check().await
+    }
+
+    fn estimate_cost(&self, model: &str, input_tokens: usize, output_tokens: usize) -> Option<f64> {
+        self.inner.estimate_cost(model, input_tokens, output_tokens)
+    }
+
+    fn check_token_limit(&self, model: &str, max_tokens: usize) -> bool {
+        self.inner.check_token_limit(model, max_tokens)
+    }
+
+    fn get_model_info(&self, model: &str) -> Option<ModelInfo> {
+        self.inner.get_model_info(model)
+    }
+
+    fn supports_function_calling(&self) -> bool {
+        self.inner.supports_function_calling()
+    }
+
+    async fn send_request_with_functions(
+        &self,
+        request: &AiRequest,
+        functions: &[FunctionDefinition],
+    ) -> AiResult<AiResponse> {
+        self.record(request, || {
+            self.inner.send_request_with_functions(request, functions)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::func::git::create_git_functions;
+    use crate::providers::mock::MockProvider;
+    use crate::providers::stream::collect_stream;
+    use orion_error::TestAssertWithMsg;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_record_then_replay() {
+        let dir = TempDir::new().unwrap();
+        let fixture = dir.path().join("fixtures").join("git.jsonl");
+        let recorder = RecordingProvider::new(
+            Arc::new(MockProvider::new()),
+            FixtureRecorder::open(fixture.clone()).assert("recorder"),
+        );
+
+        let chat = AiRequest::builder()
+            .model("gpt-4o-mini")
+            .user_prompt("你好")
+            .build();
+        let tool = AiRequest::builder()
+            .model("gpt-4o-mini")
+            .user_prompt("git-status")
+            .build();
+        let recorded_chat = recorder.send_request(&chat).await.assert("chat");
+        let recorded_tool = recorder
+            .send_request_with_functions(&tool, &create_git_functions())
+            .await
+            .assert("tool");
+        let streamed = AiRequest::builder()
+            .model("gpt-4o-mini")
+            .user_prompt("流式")
+            .build();
+        let stream = recorder
+            .send_request_stream(&streamed)
+            .await
+            .assert("stream");
+        let recorded_stream = collect_stream(stream, AiProviderType::Mock, "gpt-4o-mini")
+            .await
+            .assert("collect");
+        assert_eq!(ThreadRecord::read_jsonl(&fixture).unwrap()[2].seq, 3);
+
+        let store = ReplayStore::load(fixture.parent().unwrap()).assert("load");
+        assert_eq!(store.len(), 3);
+        let replay = MockProvider::with_replay(Arc::new(store));
+        let replayed = replay.send_request(&chat).await.assert("replay chat");
+        assert_eq!(replayed.content, recorded_chat.content);
+        assert_eq!(replayed.usage, recorded_chat.usage);
+        // 回放与是否携带函数定义无关，工具调用原样返回
+        let replayed = replay.send_request(&tool).await.assert("replay tool");
+        assert_eq!(replayed.tool_calls, recorded_tool.tool_calls);
+        // 流式调用同样录制，回放时可再以流式取回
+        let stream = replay
+            .send_request_stream(&streamed)
+            .await
+            .assert("replay stream");
+        let replayed = collect_stream(stream, AiProviderType::Mock, "gpt-4o-mini")
+            .await
+            .assert("collect replay");
+        assert_eq!(replayed.content, recorded_stream.content);
+
+        let unknown = AiRequest::builder()
+            .model("gpt-4o-mini")
+            .user_prompt("未录制")
+            .build();
+        let err = replay.send_request(&unknown).await.unwrap_err();
+        assert!(matches!(
+            err.reason(),
+            OrionAiReason::Ai(AiErrReason::InvalidInput(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_record_failure_keeps_response() {
+        let dir = TempDir::new().unwrap();
+        let fixture = dir.path().join("blocked.jsonl");
+        let recorder = RecordingProvider::new(
+            Arc::new(MockProvider::new()),
+            FixtureRecorder::open(fixture.clone()).assert("recorder"),
+        );
+        // 夹具路径被目录占用，写入必然失败
+        std::fs::create_dir_all(&fixture).unwrap();
+
+        let request = AiRequest::builder()
+            .model("gpt-4o-mini")
+            .user_prompt("你好")
+            .build();
+        let response = recorder.send_request(&request).await.assert("send");
+        assert_eq!(response.provider, AiProviderType::Mock);
+
+        let stream = recorder
+            .send_request_stream(&request)
+            .await
+            .assert("stream");
+        let streamed = collect_stream(stream, AiProviderType::Mock, "gpt-4o-mini")
+            .await
+            .assert("collect");
+        assert_eq!(streamed.content, response.content);
+    }
+
+    #[test]
+    fn test_repeated_requests_replay_in_order() {
+        let request = AiRequest::builder()
+            .model("mock-gpt")
+            .user_prompt("重试")
+            .build();
+        let record = |content: &str| ThreadRecord {
+            session_id: "s".to_string(),
+            seq: 1,
+            timestamp: String::new(),
+            latency_ms: 0,
+            request: request.clone(),
+            response: AiResponse {
+                content: content.to_string(),
+                model: "mock-gpt".to_string(),
+                usage: UsageInfo {
+                    prompt_tokens: 1,
+                    completion_tokens: 1,
+                    total_tokens: 2,
+                    estimated_cost: None,
+                },
+                finish_reason: Some("stop".to_string()),
+                provider: AiProviderType::Mock,
+                metadata: HashMap::new(),
+                tool_calls: None,
+            },
+        };
+        let store = ReplayStore::from_records([record("first"), record("second")]);
+        assert_eq!(store.replay(&request).unwrap().content, "first");
+        assert_eq!(store.replay(&request).unwrap().content, "second");
+        assert_eq!(store.replay(&request).unwrap().content, "second");
+
+        let mut other = request.clone();
+        other.temperature = Some(0.2);
+        assert_eq!(request_fingerprint(&other), request_fingerprint(&request));
+        other.messages.push(ChatMessage::user("更早的一轮"));
+        assert_ne!(request_fingerprint(&other), request_fingerprint(&request));
+    }
+}
@@ -1,13 +1,123 @@
 //! 响应转换器模块
 //!
 //! 这个模块负责将各种 AI 提供商的响应转换为统一的 AiResponse 格式
-//! 主要包含从 OpenAI 格式响应到 AiResponse 的转换逻辑
+//! 主要包含从 OpenAI 格式响应到 AiResponse 的转换逻辑，
+//! 以及错误体、内容过滤、空回复和输出截断的识别
 
 use crate::AiResult;
-use crate::error::OrionAiReason;
+use crate::error::{AiErrReason, OrionAiReason};
 use crate::provider::{AiProviderType, AiResponse, FunctionCall, FunctionCallInfo, UsageInfo};
 use crate::providers::openai::OpenAiResponse;
-use orion_error::{ErrorOwe, ToStructError, UvsLogicFrom};
+use orion_error::ToStructError;
+
+/// 错误体摘要在错误信息中保留的最大字符数
+const BODY_SNIPPET_CHARS: usize = 200;
+
+/// 提供商返回的错误信息
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProviderErrorBody {
+    /// 错误码，没有时取错误类型
+    pub code: String,
+    pub message: String,
+}
+
+/// 解析 `{"error": {...}}` 或 `{"error": "..."}` 形式的错误体
+pub fn parse_error_body(body: &str) -> Option<ProviderErrorBody> {
+    let value: serde_json::Value = serde_json::from_str(body).ok()?;
+    match value.get("error")? {
+        serde_json::Value::String(message) => Some(ProviderErrorBody {
+            code: String::new(),
+            message: message.clone(),
+        }),
+        serde_json::Value::Object(error) => {
+            let text = |key: &str| match error.get(key) {
+                Some(serde_json::Value::String(s)) => Some(s.clone()),
+                Some(serde_json::Value::Number(n)) => Some(n.to_string()),
+                _ => None,
+            };
+            Some(ProviderErrorBody {
+                code: text("code").or_else(|| text("type")).unwrap_or_default(),
+                message: text("message").unwrap_or_default(),
+            })
+        }
+        _ => None,
+    }
+}
+
+/// 按错误码映射为具体的错误类型
+///
+/// 错误信息的措辞因提供商而异，除没有错误码的 Ollama 外只按错误码识别，避免误判
+pub fn provider_error(
+    provider: AiProviderType,
+    model: &str,
+    error: &ProviderErrorBody,
+) -> AiErrReason {
+    // Ollama 的错误体只有信息，没有错误码
+    if provider == AiProviderType::Ollama
+        && error.code.is_empty()
+        && error.message.contains("not found")
+    {
+        return AiErrReason::InvalidModel(model.to_string());
+    }
+    let code = error.code.to_lowercase();
+    match code.as_str() {
+        // 1301 为 GLM 的敏感内容错误码
+        "content_filter" | "content_policy_violation" | "1301" => {
+            AiErrReason::SensitiveContentFiltered
+        }
+        "rate_limit_exceeded"
+        | "rate_limit_error"
+        | "engine_overloaded"
+        | "overloaded_error"
+        | "1302"
+        | "1305" => {
+            AiErrReason::RateLimitError(format!("{provider} ({}): {}", error.code, error.message))
+        }
+        "model_not_found" | "not_found_error" | "1211" => {
+            AiErrReason::InvalidModel(model.to_string())
+        }
+        "invalid_api_key" | "authentication_error" | "permission_error" => {
+            AiErrReason::PermissionDenied(format!(
+                "{provider} authentication failed ({}): {}",
+                error.code, error.message
+            ))
+        }
+        _ => AiErrReason::ProviderError(
+            provider.to_string(),
+            error.code.clone(),
+            error.message.clone(),
+        ),
+    }
+}
+
+/// 截取响应体开头，用于错误信息
+pub fn body_snippet(body: &str) -> String {
+    let trimmed = body.trim();
+    match trimmed.char_indices().nth(BODY_SNIPPET_CHARS) {
+        Some((end, _)) => format!("{}...", &trimmed[..end]),
+        None => trimmed.to_string(),
+    }
+}
+
+/// 解析 OpenAI 格式的响应文本，错误体和无法识别的内容返回对应错误
+fn parse_response(
+    response_text: &str,
+    provider_type: AiProviderType,
+    request_model: &str,
+) -> AiResult<OpenAiResponse> {
+    if let Some(error) = parse_error_body(response_text) {
+        return Err(
+            OrionAiReason::from(provider_error(provider_type, request_model, &error)).to_err(),
+        );
+    }
+    serde_json::from_str(response_text).map_err(|e| {
+        OrionAiReason::from(AiErrReason::ExecutionError(format!(
+            "{provider_type} returned an unrecognized response ({e}): {}",
+            body_snippet(response_text)
+        )))
+        .to_err()
+    })
+}
 
 /// OpenAI 响应转换器
 pub struct OpenAiResponseConverter {
@@ -28,21 +138,11 @@ impl OpenAiResponseConverter {
         openai_response: OpenAiResponse,
         request_model: &str,
         cost_calculator: impl Fn(&str, usize, usize) -> Option<f64>,
-    ) -> AiResponse {
-        let choice = openai_response
-            .choices
-            .first()
-            .expect("No choices in response");
-
-        AiResponse {
-            content: choice.message.content.clone(),
-            model: openai_response.model.clone(),
-            usage: self.convert_usage(&openai_response, request_model, cost_calculator),
-            finish_reason: choice.finish_reason.clone(),
-            provider: self.provider_type,
-            metadata: std::collections::HashMap::new(),
-            tool_calls: None,
-        }
+    ) -> AiResult<AiResponse> {
+        let mut response =
+            self.convert_response_with_functions(openai_response, request_model, cost_calculator)?;
+        response.tool_calls = None;
+        Ok(response)
     }
 
     /// 转换 OpenAI 响应到 AiResponse（带函数调用）
@@ -53,71 +153,13 @@ impl OpenAiResponseConverter {
         openai_response: OpenAiResponse,
         request_model: &str,
         cost_calculator: impl Fn(&str, usize, usize) -> Option<f64>,
-    ) -> AiResponse {
-        let choice = openai_response
-            .choices
-            .first()
-            .expect("No choices in response");
-
-        let tool_calls = choice.message.tool_calls.as_ref().map(|tool_calls| {
-            tool_calls
-                .iter()
-                .map(|tool_call| FunctionCall {
-                    index: tool_call.index,
-                    id: tool_call.id.clone(),
-                    r#type: tool_call.r#type.clone(),
-                    function: FunctionCallInfo {
-                        name: tool_call.function.name.clone(),
-                        arguments: tool_call.function.arguments.clone(),
-                    },
-                })
-                .collect()
-        });
-
-        AiResponse {
-            content: choice.message.content.clone(),
-            model: openai_response.model.clone(),
-            usage: self.convert_usage(&openai_response, request_model, cost_calculator),
-            finish_reason: choice.finish_reason.clone(),
-            provider: self.provider_type,
-            metadata: std::collections::HashMap::new(),
-            tool_calls,
-        }
-    }
-
-    /// 转换使用信息和成本计算
-    fn convert_usage(
-        &self,
-        openai_response: &OpenAiResponse,
-        request_model: &str,
-        cost_calculator: impl Fn(&str, usize, usize) -> Option<f64>,
-    ) -> UsageInfo {
-        let prompt_tokens = openai_response
-            .usage
-            .as_ref()
-            .map(|u| u.prompt_tokens)
-            .unwrap_or(0);
-
-        let completion_tokens = openai_response
-            .usage
-            .as_ref()
-            .map(|u| u.completion_tokens)
-            .unwrap_or(0);
-
-        let total_tokens = openai_response
-            .usage
-            .as_ref()
-            .map(|u| u.total_tokens)
-            .unwrap_or(0);
-
-        let estimated_cost = cost_calculator(request_model, prompt_tokens, completion_tokens);
-
-        UsageInfo {
-            prompt_tokens,
-            completion_tokens,
-            total_tokens,
-            estimated_cost,
-        }
+    ) -> AiResult<AiResponse> {
+        convert_response_auto(
+            openai_response,
+            self.provider_type,
+            request_model,
+            cost_calculator,
+        )
     }
 }
 
@@ -131,8 +173,8 @@ pub fn convert_response_from_text(
     request_model: &str,
     cost_calculator: impl Fn(&str, usize, usize) -> Option<f64>,
 ) -> AiResult<AiResponse> {
-    // 首先解析 JSON 文本
-    let openai_response: OpenAiResponse = serde_json::from_str(response_text).owe_data()?;
+    // 首先解析 JSON 文本，识别错误体
+    let openai_response = parse_response(response_text, provider_type, request_model)?;
 
     // 然后使用自动转换逻辑
     convert_response_auto(
@@ -145,7 +187,8 @@ pub fn convert_response_from_text(
 
 /// 统一的响应转换实现
 ///
-/// 这个函数自动根据响应数据判断是否需要解析函数调用
+/// 这个函数自动根据响应数据判断是否需要解析函数调用；
+/// 空回复、内容过滤和输出截断返回对应错误
 fn convert_response_auto(
     openai_response: OpenAiResponse,
     provider_type: AiProviderType,
@@ -153,26 +196,12 @@ fn convert_response_auto(
     cost_calculator: impl Fn(&str, usize, usize) -> Option<f64>,
 ) -> AiResult<AiResponse> {
     let choice = openai_response.choices.first().ok_or_else(|| {
-        OrionAiReason::from_logic("TODO: no choices in response".to_string()).to_err()
+        OrionAiReason::from(AiErrReason::EmptyResponse(format!(
+            "{provider_type} returned no choices for model {request_model}"
+        )))
+        .to_err()
     })?;
 
-    // 自动判断是否需要解析函数调用
-    // tool_calls应该在message级别，这是正确的API格式
-    let tool_calls = choice.message.tool_calls.as_ref().map(|tool_calls| {
-        tool_calls
-            .iter()
-            .map(|tool_call| FunctionCall {
-                index: tool_call.index,
-                id: tool_call.id.clone(),
-                r#type: tool_call.r#type.clone(),
-                function: FunctionCallInfo {
-                    name: tool_call.function.name.clone(),
-                    arguments: tool_call.function.arguments.clone(),
-                },
-            })
-            .collect()
-    });
-
     // 转换使用信息
     let prompt_tokens = openai_response
         .usage
@@ -192,11 +221,32 @@ fn convert_response_auto(
         .map(|u| u.total_tokens)
         .unwrap_or(0);
 
+    // 自动判断是否需要解析函数调用
+    // tool_calls应该在message级别，这是正确的API格式
+    let tool_calls = choice.message.tool_calls.as_ref().map(|tool_calls| {
+        tool_calls
+            .iter()
+            .map(|tool_call| FunctionCall {
+                index: tool_call.index,
+                id: tool_call.id.clone(),
+                r#type: tool_call.r#type.clone(),
+                function: FunctionCallInfo {
+                    name: tool_call.function.name.clone(),
+                    arguments: tool_call.function.arguments.clone(),
+                },
+            })
+            .collect()
+    });
+
     let estimated_cost = cost_calculator(request_model, prompt_tokens, completion_tokens);
 
-    Ok(AiResponse {
+    check_finish_reason(AiResponse {
         content: choice.message.content.clone(),
-        model: openai_response.model.clone(),
+        model: if openai_response.model.is_empty() {
+            request_model.to_string()
+        } else {
+            openai_response.model.clone()
+        },
         usage: UsageInfo {
             prompt_tokens,
             completion_tokens,
@@ -210,6 +260,22 @@ fn convert_response_auto(
     })
 }
 
+/// 按结束原因识别内容过滤和输出截断
+///
+/// 截断时错误携带部分响应，调用方仍可取得已生成的内容和用量
+pub fn check_finish_reason(response: AiResponse) -> AiResult<AiResponse> {
+    match response.finish_reason.as_deref() {
+        // GLM 使用 sensitive 表示内容被过滤
+        Some("content_filter") | Some("sensitive") => {
+            Err(OrionAiReason::from(AiErrReason::SensitiveContentFiltered).to_err())
+        }
+        Some("length") => {
+            Err(OrionAiReason::from(AiErrReason::OutputTruncated(Box::new(response))).to_err())
+        }
+        _ => Ok(response),
+    }
+}
+
 #[cfg(test)]
 mod helper_tests {
     use super::*;
@@ -302,12 +368,10 @@ mod helper_tests {
         );
 
         // 验证返回错误
-        assert!(result.is_err());
-        let error = result.unwrap_err();
-        assert_eq!(
-            error.to_string(),
-            "[800] BUG :logic error << TODO: no choices in response"
-        );
+        assert!(matches!(
+            result.unwrap_err().reason(),
+            OrionAiReason::Ai(AiErrReason::EmptyResponse(_))
+        ));
     }
 
     #[test]
@@ -377,12 +441,10 @@ mod helper_tests {
         );
 
         // 验证返回错误
-        assert!(result.is_err());
-        let error = result.unwrap_err();
-        assert_eq!(
-            error.to_string(),
-            "[800] BUG :logic error << TODO: no choices in response"
-        );
+        assert!(matches!(
+            result.unwrap_err().reason(),
+            OrionAiReason::Ai(AiErrReason::EmptyResponse(_))
+        ));
     }
 
     #[test]
@@ -750,6 +812,142 @@ mod helper_tests {
     }
 }
 
+#[cfg(test)]
+mod error_body_tests {
+    use super::*;
+
+    fn reason_of(body: &str, provider: AiProviderType, model: &str) -> AiErrReason {
+        match convert_response_from_text(body, provider, model, |_, _, _| None)
+            .unwrap_err()
+            .reason()
+        {
+            OrionAiReason::Ai(reason) => match reason {
+                AiErrReason::ProviderError(p, c, m) => {
+                    AiErrReason::ProviderError(p.clone(), c.clone(), m.clone())
+                }
+                AiErrReason::RateLimitError(m) => AiErrReason::RateLimitError(m.clone()),
+                AiErrReason::InvalidModel(m) => AiErrReason::InvalidModel(m.clone()),
+                AiErrReason::ExecutionError(m) => AiErrReason::ExecutionError(m.clone()),
+                AiErrReason::OutputTruncated(r) => AiErrReason::OutputTruncated(r.clone()),
+                AiErrReason::SensitiveContentFiltered => AiErrReason::SensitiveContentFiltered,
+                AiErrReason::PermissionDenied(m) => AiErrReason::PermissionDenied(m.clone()),
+                other => panic!("unexpected reason: {other:?}"),
+            },
+            other => panic!("unexpected reason: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_error_envelopes() {
+        let body = r#"{"error":{"message":"Invalid parameter: tools","type":"invalid_request_error","param":null,"code":"invalid_parameter"}}"#;
+        assert_eq!(
+            reason_of(body, AiProviderType::OpenAi, "gpt-4o"),
+            AiErrReason::ProviderError(
+                "openai".to_string(),
+                "invalid_parameter".to_string(),
+                "Invalid parameter: tools".to_string()
+            )
+        );
+
+        // 没有 code 时取 type
+        let body = r#"{"error":{"message":"quota exceeded","type":"insufficient_quota"}}"#;
+        assert_eq!(
+            reason_of(body, AiProviderType::DeepSeek, "deepseek-chat"),
+            AiErrReason::ProviderError(
+                "deepseek".to_string(),
+                "insufficient_quota".to_string(),
+                "quota exceeded".to_string()
+            )
+        );
+
+        let body = r#"{"error":{"code":"1301","message":"系统检测到输入或生成内容可能包含不安全或敏感内容"}}"#;
+        assert_eq!(
+            reason_of(body, AiProviderType::Glm, "glm-4.5"),
+            AiErrReason::SensitiveContentFiltered
+        );
+
+        // 只按错误码识别，信息中出现 sensitive 不代表内容被过滤
+        let body = r#"{"error":{"code":"invalid_api_key","message":"key must not be logged, it is sensitive"}}"#;
+        assert!(matches!(
+            reason_of(body, AiProviderType::OpenAi, "gpt-4o"),
+            AiErrReason::PermissionDenied(_)
+        ));
+
+        let body = r#"{"error":{"code":1302,"message":"并发数过高"}}"#;
+        assert!(matches!(
+            reason_of(body, AiProviderType::Glm, "glm-4.5"),
+            AiErrReason::RateLimitError(m) if m.contains("1302")
+        ));
+
+        let body = r#"{"error":{"code":"model_not_found","message":"no such model"}}"#;
+        assert_eq!(
+            reason_of(body, AiProviderType::Kimi, "kimi-x"),
+            AiErrReason::InvalidModel("kimi-x".to_string())
+        );
+
+        let body = r#"{"error":"upstream timeout"}"#;
+        assert_eq!(
+            reason_of(body, AiProviderType::custom("gateway"), "qwen"),
+            AiErrReason::ProviderError(
+                "custom:gateway".to_string(),
+                String::new(),
+                "upstream timeout".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_unrecognized_body() {
+        let body = format!("<html>{}</html>", "x".repeat(500));
+        match reason_of(&body, AiProviderType::OpenAi, "gpt-4o") {
+            AiErrReason::ExecutionError(message) => {
+                assert!(message.starts_with("openai returned an unrecognized response"));
+                assert!(message.ends_with("..."));
+                assert!(message.len() < 400);
+            }
+            other => panic!("unexpected reason: {other:?}"),
+        }
+        assert_eq!(parse_error_body(r#"{"error":null,"choices":[]}"#), None);
+    }
+
+    #[test]
+    fn test_finish_reasons() {
+        let body = |finish: &str| {
+            format!(
+                r#"{{"model":"kimi-k2-0711-preview","choices":[{{"message":{{"role":"assistant","content":"部分"}},"finish_reason":"{finish}"}}],"usage":{{"prompt_tokens":10,"completion_tokens":64,"total_tokens":74}}}}"#
+            )
+        };
+        assert_eq!(
+            reason_of(&body("content_filter"), AiProviderType::Kimi, "kimi"),
+            AiErrReason::SensitiveContentFiltered
+        );
+        assert_eq!(
+            reason_of(&body("sensitive"), AiProviderType::Glm, "glm-4.5"),
+            AiErrReason::SensitiveContentFiltered
+        );
+        match reason_of(
+            &body("length"),
+            AiProviderType::Kimi,
+            "kimi-k2-0711-preview",
+        ) {
+            AiErrReason::OutputTruncated(partial) => {
+                assert_eq!(partial.content, "部分");
+                assert_eq!(partial.usage.total_tokens, 74);
+                assert_eq!(partial.finish_reason.as_deref(), Some("length"));
+            }
+            other => panic!("unexpected reason: {other:?}"),
+        }
+        let response = convert_response_from_text(
+            &body("stop"),
+            AiProviderType::Kimi,
+            "kimi-k2-0711-preview",
+            |_, _, _| None,
+        )
+        .unwrap();
+        assert_eq!(response.content, "部分");
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -766,6 +964,7 @@ mod tests {
                     role: "assistant".to_string(),
                     content: "这是一个测试响应".to_string(),
                     tool_calls: None,
+                    tool_call_id: None,
                 },
                 finish_reason: Some("stop".to_string()),
             }],
@@ -778,7 +977,9 @@ mod tests {
         };
 
         // 转换响应
-        let response = converter.convert_response(openai_response, "gpt-4", |_, _, _| Some(0.003));
+        let response = converter
+            .convert_response(openai_response, "gpt-4", |_, _, _| Some(0.003))
+            .unwrap();
 
         // 验证结果
         assert_eq!(response.content, "这是一个测试响应");
@@ -811,6 +1012,7 @@ mod tests {
                             arguments: "{}".to_string(),
                         },
                     }]),
+                    tool_call_id: None,
                 },
                 finish_reason: Some("tool_calls".to_string()),
             }],
@@ -823,11 +1025,11 @@ mod tests {
         };
 
         // 转换响应
-        let response = converter.convert_response_with_functions(
-            openai_response,
-            "deepseek-chat",
-            |_, _, _| Some(0.001),
-        );
+        let response = converter
+            .convert_response_with_functions(openai_response, "deepseek-chat", |_, _, _| {
+                Some(0.001)
+            })
+            .unwrap();
 
         // 验证基本响应信息
         assert_eq!(response.content, "我来帮您执行Git操作");
@@ -863,6 +1065,7 @@ mod tests {
                     role: "assistant".to_string(),
                     content: "这是一个测试响应".to_string(),
                     tool_calls: Some(vec![]),
+                    tool_call_id: None,
                 },
                 finish_reason: Some("tool_calls".to_string()),
             }],
@@ -871,7 +1074,9 @@ mod tests {
         };
 
         // 转换响应（使用不计算成本的函数）
-        let response = converter.convert_response(openai_response, "gpt-3.5-turbo", |_, _, _| None);
+        let response = converter
+            .convert_response(openai_response, "gpt-3.5-turbo", |_, _, _| None)
+            .unwrap();
 
         // 验证默认值
         assert_eq!(response.content, "这是一个测试响应");
@@ -931,6 +1136,7 @@ mod tests {
                             },
                         },
                     ]),
+                    tool_call_id: None,
                 },
                 finish_reason: Some("tool_calls".to_string()),
             }],
@@ -942,10 +1148,9 @@ mod tests {
             model: "gpt-4".to_string(),
         };
 
-        let response =
-            converter.convert_response_with_functions(openai_response, "gpt-4-turbo", |_, _, _| {
-                Some(0.006)
-            });
+        let response = converter
+            .convert_response_with_functions(openai_response, "gpt-4-turbo", |_, _, _| Some(0.006))
+            .unwrap();
 
         // 验证多个函数调用
         assert!(response.tool_calls.is_some());
@@ -979,6 +1184,7 @@ mod tests {
                     role: "assistant".to_string(),
                     content: "这是一个测试响应".to_string(),
                     tool_calls: Some(vec![]),
+                    tool_call_id: None,
                 },
                 finish_reason: Some("tool_calls".to_string()),
             }],
@@ -990,9 +1196,9 @@ mod tests {
             model: "gpt-4".to_string(),
         };
 
-        let response =
-            converter
-                .convert_response_with_functions(openai_response, "gpt-4", |_, _, _| Some(0.003));
+        let response = converter
+            .convert_response_with_functions(openai_response, "gpt-4", |_, _, _| Some(0.003))
+            .unwrap();
 
         // 空的工具调用数组应该保持为空数组
         assert!(response.tool_calls.is_some());
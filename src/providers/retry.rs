@@ -0,0 +1,329 @@
+//! provider HTTP 调用的重试与状态码映射
+
+use std::time::Duration;
+
+use log::warn;
+use orion_error::{ErrorOwe, ToStructError};
+use reqwest::{RequestBuilder, Response, StatusCode, header};
+
+use crate::config::RetryPolicy;
+use crate::error::{AiErrReason, AiError, AiResult, OrionAiReason};
+use crate::provider::AiProviderType;
+use crate::providers::resp::{body_snippet, parse_error_body, provider_error};
+
+/// 是否为可重试的状态码
+pub fn is_retryable_status(status: StatusCode) -> bool {
+    matches!(status.as_u16(), 408 | 429 | 500 | 502 | 503 | 504 | 529)
+}
+
+/// 解析 `Retry-After` 头（秒数）
+pub fn retry_after(response: &Response) -> Option<Duration> {
+    response
+        .headers()
+        .get(header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<f64>().ok())
+        .filter(|secs| *secs >= 0.0)
+        .map(Duration::from_secs_f64)
+}
+
+/// 按重试策略发送请求
+///
+/// `build` 每次调用需构造一个新的请求。可重试的状态码和连接/超时错误会按策略退避重试，
+/// 最终的非成功响应由 [`ensure_success`] 映射为错误。
+pub async fn send_with_retry<F>(
+    policy: &RetryPolicy,
+    provider: AiProviderType,
+    model: &str,
+    build: F,
+) -> AiResult<Response>
+where
+    F: Fn() -> RequestBuilder,
+{
+    let mut attempt = 0;
+    loop {
+        let can_retry = attempt < policy.max_retries;
+        match build().send().await {
+            Ok(response) if can_retry && is_retryable_status(response.status()) => {
+                let delay = match retry_after(&response) {
+                    Some(delay) if delay > policy.max_backoff() => {
+                        return ensure_success(response, provider, model).await;
+                    }
+                    Some(delay) => delay,
+                    None => policy.backoff(attempt),
+                };
+                warn!(
+                    "{provider} returned HTTP {}, retrying in {:?} ({}/{})",
+                    response.status(),
+                    delay,
+                    attempt + 1,
+                    policy.max_retries
+                );
+                tokio::time::sleep(delay).await;
+            }
+            Ok(response) => return ensure_success(response, provider, model).await,
+            Err(e) if can_retry && (e.is_timeout() || e.is_connect()) => {
+                let delay = policy.backoff(attempt);
+                warn!(
+                    "{provider} request failed: {e}, retrying in {:?} ({}/{})",
+                    delay,
+                    attempt + 1,
+                    policy.max_retries
+                );
+                tokio::time::sleep(delay).await;
+            }
+            Err(e) if e.is_timeout() || e.is_connect() => {
+                return Err(
+                    OrionAiReason::from(AiErrReason::ProviderUnavailable(format!(
+                        "{provider}: {e}"
+                    )))
+                    .to_err(),
+                );
+            }
+            Err(e) => return Err::<Response, _>(e).owe_res(),
+        }
+        attempt += 1;
+    }
+}
+
+/// 非成功响应映射为对应的错误
+pub async fn ensure_success(
+    response: Response,
+    provider: AiProviderType,
+    model: &str,
+) -> AiResult<Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body = response.text().await.unwrap_or_default();
+    Err(status_error(status, provider, model, &body))
+}
+
+/// 按错误体和 HTTP 状态码构造错误
+///
+/// 错误体（OpenAI/Anthropic 的 `{"error": {...}}`、Ollama 的 `{"error": "..."}`）能识别出
+/// 具体错误时优先采用，否则按状态码映射，保证 5xx/429 仍被视为暂时性错误
+pub fn status_error(
+    status: StatusCode,
+    provider: AiProviderType,
+    model: &str,
+    body: &str,
+) -> AiError {
+    let lower = body.to_lowercase();
+    let mentions_missing_model = lower.contains("model")
+        && (lower.contains("not found")
+            || lower.contains("not exist")
+            || lower.contains("model_not_found")
+            || lower.contains("invalid model"));
+    let envelope = parse_error_body(body).map(|error| provider_error(provider, model, &error));
+
+    let reason = match (status.as_u16(), envelope) {
+        (_, Some(reason)) if !matches!(reason, AiErrReason::ProviderError(..)) => reason,
+        (429 | 529, _) => {
+            AiErrReason::RateLimitError(format!("{provider} (HTTP {status}): {body}"))
+        }
+        (401 | 403, _) => AiErrReason::PermissionDenied(format!(
+            "{provider} authentication failed (HTTP {status}): {body}"
+        )),
+        (500 | 502 | 503 | 504, _) => {
+            AiErrReason::ProviderUnavailable(format!("{provider} (HTTP {status}): {body}"))
+        }
+        (404, _) if lower.contains("model") => AiErrReason::InvalidModel(model.to_string()),
+        (400, _) if mentions_missing_model => AiErrReason::InvalidModel(model.to_string()),
+        (_, Some(reason)) => reason,
+        (_, None) => AiErrReason::ExecutionError(format!(
+            "{provider} request failed with HTTP {status}: {}",
+            body_snippet(body)
+        )),
+    };
+    OrionAiReason::from(reason).to_err()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::stub::{StubResponse, StubServer};
+
+    fn fast_policy(max_retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_retries,
+            initial_backoff_ms: 1,
+            max_backoff_ms: 50,
+            backoff_multiplier: 2.0,
+        }
+    }
+
+    async fn call(server: &StubServer, policy: &RetryPolicy) -> AiResult<Response> {
+        let client = reqwest::Client::new();
+        let url = format!("{}/chat/completions", server.base_url());
+        send_with_retry(policy, AiProviderType::OpenAi, "gpt-4o", || {
+            client.post(&url).body("{}")
+        })
+        .await
+    }
+
+    fn reason(result: AiResult<Response>) -> AiErrReason {
+        match result.unwrap_err().reason() {
+            OrionAiReason::Ai(reason) => match reason {
+                AiErrReason::InvalidModel(m) => AiErrReason::InvalidModel(m.clone()),
+                AiErrReason::RateLimitError(m) => AiErrReason::RateLimitError(m.clone()),
+                AiErrReason::PermissionDenied(m) => AiErrReason::PermissionDenied(m.clone()),
+                AiErrReason::ExecutionError(m) => AiErrReason::ExecutionError(m.clone()),
+                AiErrReason::ProviderError(p, c, m) => {
+                    AiErrReason::ProviderError(p.clone(), c.clone(), m.clone())
+                }
+                AiErrReason::ProviderUnavailable(m) => AiErrReason::ProviderUnavailable(m.clone()),
+                other => panic!("unexpected reason: {other:?}"),
+            },
+            other => panic!("unexpected reason: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_backoff_grows_and_caps() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.backoff(0), Duration::from_millis(500));
+        assert_eq!(policy.backoff(1), Duration::from_millis(1000));
+        assert_eq!(policy.backoff(10), Duration::from_millis(8000));
+    }
+
+    #[tokio::test]
+    async fn test_retries_until_success() {
+        let server = StubServer::start(vec![
+            StubResponse::json(503, "{}"),
+            StubResponse::json(429, "{}").with_header("Retry-After", "0"),
+            StubResponse::json(200, r#"{"ok":true}"#),
+        ])
+        .await;
+
+        let response = call(&server, &fast_policy(2)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_after_retries_exhausted() {
+        let server = StubServer::start(vec![StubResponse::json(429, "{}")]).await;
+
+        let result = call(&server, &fast_policy(1)).await;
+        assert!(matches!(reason(result), AiErrReason::RateLimitError(_)));
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_server_error_maps_to_unavailable() {
+        let server = StubServer::start(vec![StubResponse::json(503, "{}")]).await;
+
+        let result = call(&server, &fast_policy(1)).await;
+        assert!(matches!(
+            reason(result),
+            AiErrReason::ProviderUnavailable(_)
+        ));
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_long_retry_after_is_not_waited() {
+        let server = StubServer::start(vec![
+            StubResponse::json(429, "{}").with_header("Retry-After", "120"),
+        ])
+        .await;
+
+        let result = call(&server, &fast_policy(3)).await;
+        assert!(matches!(reason(result), AiErrReason::RateLimitError(_)));
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_status_mapping_without_retry() {
+        let server = StubServer::start(vec![
+            StubResponse::json(401, r#"{"error":{"message":"bad key"}}"#),
+            StubResponse::json(404, r#"{"error":{"code":"model_not_found"}}"#),
+            StubResponse::json(400, r#"{"error":{"message":"bad request"}}"#),
+        ])
+        .await;
+        let policy = fast_policy(3);
+
+        assert!(matches!(
+            reason(call(&server, &policy).await),
+            AiErrReason::PermissionDenied(_)
+        ));
+        assert_eq!(
+            reason(call(&server, &policy).await),
+            AiErrReason::InvalidModel("gpt-4o".to_string())
+        );
+        assert_eq!(
+            reason(call(&server, &policy).await),
+            AiErrReason::ProviderError(
+                "openai".to_string(),
+                String::new(),
+                "bad request".to_string()
+            )
+        );
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[test]
+    fn test_error_envelopes_before_status() {
+        let reason_of = |status: u16, provider: AiProviderType, model: &str, body: &str| {
+            let status = StatusCode::from_u16(status).unwrap();
+            reason(Err(status_error(status, provider, model, body)))
+        };
+
+        let overloaded =
+            r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#;
+        for status in [500, 529] {
+            assert!(matches!(
+                reason_of(status, AiProviderType::Anthropic, "claude-sonnet-4-0", overloaded),
+                AiErrReason::RateLimitError(m) if m.contains("overloaded_error")
+            ));
+        }
+        assert_eq!(
+            reason_of(
+                400,
+                AiProviderType::Anthropic,
+                "claude-sonnet-4-0",
+                r#"{"type":"error","error":{"type":"invalid_request_error","message":"max_tokens: field required"}}"#,
+            ),
+            AiErrReason::ProviderError(
+                "anthropic".to_string(),
+                "invalid_request_error".to_string(),
+                "max_tokens: field required".to_string()
+            )
+        );
+
+        assert_eq!(
+            reason_of(
+                404,
+                AiProviderType::Ollama,
+                "mistral",
+                r#"{"error":"model \"mistral\" not found, try pulling it first"}"#,
+            ),
+            AiErrReason::InvalidModel("mistral".to_string())
+        );
+        // 无法识别的错误体仍按状态码判断是否为暂时性错误
+        assert!(matches!(
+            reason_of(
+                500,
+                AiProviderType::Ollama,
+                "qwen2.5:7b",
+                r#"{"error":"llama runner process has terminated"}"#,
+            ),
+            AiErrReason::ProviderUnavailable(m) if m.contains("llama runner")
+        ));
+        assert_eq!(
+            reason_of(
+                400,
+                AiProviderType::Ollama,
+                "qwen2.5:7b",
+                r#"{"error":"invalid options"}"#,
+            ),
+            AiErrReason::ProviderError(
+                "ollama".to_string(),
+                String::new(),
+                "invalid options".to_string()
+            )
+        );
+    }
+}
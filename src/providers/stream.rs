@@ -0,0 +1,549 @@
+//! 流式响应支持
+//!
+//! 包含 SSE 解码、OpenAI 兼容格式的增量解析、合成事件流以及把事件流聚合回 AiResponse 的工具。
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt::Display;
+
+use futures_util::stream::{self, Stream, StreamExt};
+use orion_error::ToStructError;
+use serde::Deserialize;
+
+use crate::error::{AiErrReason, AiResult, OrionAiReason};
+use crate::provider::{
+    AiProviderType, AiResponse, AiResponseStream, AiStreamEvent, FunctionCall, FunctionCallInfo,
+    UsageInfo,
+};
+use crate::providers::resp::{check_finish_reason, parse_error_body, provider_error};
+
+/// SSE 解码器，按行缓存字节，遇到空行输出一个完整的 data 载荷
+#[derive(Debug, Default)]
+pub struct SseDecoder {
+    buffer: Vec<u8>,
+    data_lines: Vec<String>,
+}
+
+impl SseDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 输入一段字节，返回其中已完整的事件载荷
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+
+        while let Some(pos) = self.buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line);
+            self.process_line(line.trim_end_matches(['\r', '\n']), &mut events);
+        }
+        events
+    }
+
+    /// 输入结束，返回残留的事件载荷
+    pub fn flush(&mut self) -> Vec<String> {
+        let mut events = Vec::new();
+        if !self.buffer.is_empty() {
+            let rest = std::mem::take(&mut self.buffer);
+            let line = String::from_utf8_lossy(&rest).to_string();
+            self.process_line(line.trim_end_matches('\r'), &mut events);
+        }
+        self.process_line("", &mut events);
+        events
+    }
+
+    fn process_line(&mut self, line: &str, events: &mut Vec<String>) {
+        if line.is_empty() {
+            if !self.data_lines.is_empty() {
+                events.push(self.data_lines.join("\n"));
+                self.data_lines.clear();
+            }
+        } else if let Some(data) = line.strip_prefix("data:") {
+            self.data_lines
+                .push(data.strip_prefix(' ').unwrap_or(data).to_string());
+        }
+        // 注释行和 event/id/retry 字段对 OpenAI 兼容格式没有意义，直接忽略
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAiStreamChunk {
+    #[serde(default)]
+    choices: Vec<OpenAiStreamChoice>,
+    #[serde(default)]
+    usage: Option<OpenAiStreamUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAiStreamChoice {
+    #[serde(default)]
+    delta: OpenAiStreamDelta,
+    #[serde(default)]
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct OpenAiStreamDelta {
+    #[serde(default)]
+    content: Option<String>,
+    #[serde(default)]
+    tool_calls: Option<Vec<OpenAiStreamToolCall>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAiStreamToolCall {
+    #[serde(default)]
+    index: Option<u32>,
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(default)]
+    function: Option<OpenAiStreamFunction>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAiStreamFunction {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    arguments: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAiStreamUsage {
+    prompt_tokens: usize,
+    completion_tokens: usize,
+    total_tokens: usize,
+}
+
+/// 解析一个 OpenAI 兼容的 chat.completion.chunk 载荷
+///
+/// 流中的 `{"error": ...}` 载荷按错误码映射为对应错误
+pub fn parse_openai_chunk(
+    data: &str,
+    provider: AiProviderType,
+    model: &str,
+    cost_calculator: &impl Fn(usize, usize) -> Option<f64>,
+) -> Vec<AiResult<AiStreamEvent>> {
+    if let Some(error) = parse_error_body(data) {
+        return vec![Err(OrionAiReason::from(provider_error(
+            provider, model, &error,
+        ))
+        .to_err())];
+    }
+    let chunk: OpenAiStreamChunk = match serde_json::from_str(data) {
+        Ok(chunk) => chunk,
+        Err(e) => {
+            return vec![Err(OrionAiReason::from(AiErrReason::ExecutionError(
+                format!("invalid stream chunk: {e}"),
+            ))
+            .to_err())];
+        }
+    };
+
+    let mut events = Vec::new();
+    for choice in chunk.choices {
+        if let Some(content) = choice.delta.content
+            && !content.is_empty()
+        {
+            events.push(Ok(AiStreamEvent::TextDelta(content)));
+        }
+        for call in choice.delta.tool_calls.unwrap_or_default() {
+            let (name, arguments) = match call.function {
+                Some(f) => (f.name, f.arguments.unwrap_or_default()),
+                None => (None, String::new()),
+            };
+            events.push(Ok(AiStreamEvent::ToolCallDelta {
+                index: call.index.unwrap_or(0),
+                id: call.id,
+                name,
+                arguments,
+            }));
+        }
+        if choice.finish_reason.is_some() {
+            events.push(Ok(AiStreamEvent::Finish(choice.finish_reason)));
+        }
+    }
+
+    if let Some(usage) = chunk.usage {
+        events.push(Ok(AiStreamEvent::Usage(UsageInfo {
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+            total_tokens: usage.total_tokens,
+            estimated_cost: cost_calculator(usage.prompt_tokens, usage.completion_tokens),
+        })));
+    }
+    events
+}
+
+/// 事件聚合器，按顺序累积文本、工具调用、用量和结束原因
+pub(crate) struct StreamCollector {
+    content: String,
+    calls: BTreeMap<u32, FunctionCall>,
+    usage: UsageInfo,
+    finish_reason: Option<String>,
+}
+
+impl StreamCollector {
+    pub(crate) fn new() -> Self {
+        Self {
+            content: String::new(),
+            calls: BTreeMap::new(),
+            usage: UsageInfo {
+                prompt_tokens: 0,
+                completion_tokens: 0,
+                total_tokens: 0,
+                estimated_cost: None,
+            },
+            finish_reason: None,
+        }
+    }
+
+    pub(crate) fn push(&mut self, event: &AiStreamEvent) {
+        match event {
+            AiStreamEvent::TextDelta(text) => self.content.push_str(text),
+            AiStreamEvent::ToolCallDelta {
+                index,
+                id,
+                name,
+                arguments,
+            } => {
+                let call = self.calls.entry(*index).or_insert_with(|| FunctionCall {
+                    index: Some(*index),
+                    id: String::new(),
+                    r#type: "function".to_string(),
+                    function: FunctionCallInfo {
+                        name: String::new(),
+                        arguments: String::new(),
+                    },
+                });
+                if let Some(id) = id {
+                    call.id = id.clone();
+                }
+                if let Some(name) = name {
+                    call.function.name.push_str(name);
+                }
+                call.function.arguments.push_str(arguments);
+            }
+            AiStreamEvent::Usage(u) => self.usage = u.clone(),
+            AiStreamEvent::Finish(reason) => self.finish_reason = reason.clone(),
+        }
+    }
+
+    pub(crate) fn into_response(self, provider: AiProviderType, model: &str) -> AiResponse {
+        AiResponse {
+            content: self.content,
+            model: model.to_string(),
+            usage: self.usage,
+            finish_reason: self.finish_reason,
+            provider,
+            metadata: HashMap::new(),
+            tool_calls: if self.calls.is_empty() {
+                None
+            } else {
+                Some(self.calls.into_values().collect())
+            },
+        }
+    }
+}
+
+struct SseState<S, F> {
+    inner: std::pin::Pin<Box<S>>,
+    decoder: SseDecoder,
+    pending: VecDeque<AiResult<AiStreamEvent>>,
+    cost_calculator: F,
+    provider: AiProviderType,
+    model: String,
+    collector: Option<StreamCollector>,
+    finished: bool,
+}
+
+impl<S, F> SseState<S, F>
+where
+    F: Fn(usize, usize) -> Option<f64>,
+{
+    fn push_payloads(&mut self, payloads: Vec<String>) {
+        for data in payloads {
+            if self.finished {
+                break;
+            }
+            if data.trim() == "[DONE]" {
+                self.finish();
+                break;
+            }
+            let events =
+                parse_openai_chunk(&data, self.provider, &self.model, &self.cost_calculator);
+            for event in events {
+                match &event {
+                    Ok(event) => {
+                        if let Some(collector) = self.collector.as_mut() {
+                            collector.push(event);
+                        }
+                    }
+                    // 流内错误后提供商不再输出有效内容
+                    Err(_) => self.finished = true,
+                }
+                self.pending.push_back(event);
+            }
+        }
+    }
+
+    /// 流正常结束，内容过滤和输出截断以错误事件收尾，截断时携带已聚合的部分响应
+    fn finish(&mut self) {
+        self.finished = true;
+        if let Some(collector) = self.collector.take()
+            && let Err(e) = check_finish_reason(collector.into_response(self.provider, &self.model))
+        {
+            self.pending.push_back(Err(e));
+        }
+    }
+}
+
+/// 把 OpenAI 兼容的 SSE 字节流转换为事件流
+pub fn openai_sse_stream<S, B, E, F>(
+    byte_stream: S,
+    provider: AiProviderType,
+    model: String,
+    cost_calculator: F,
+) -> AiResponseStream
+where
+    S: Stream<Item = Result<B, E>> + Send + 'static,
+    B: AsRef<[u8]> + Send + 'static,
+    E: Display + Send + 'static,
+    F: Fn(usize, usize) -> Option<f64> + Send + 'static,
+{
+    let state = SseState {
+        inner: Box::pin(byte_stream),
+        decoder: SseDecoder::new(),
+        pending: VecDeque::new(),
+        cost_calculator,
+        provider,
+        model,
+        collector: Some(StreamCollector::new()),
+        finished: false,
+    };
+
+    stream::unfold(state, |mut state| async move {
+        loop {
+            if let Some(event) = state.pending.pop_front() {
+                return Some((event, state));
+            }
+            if state.finished {
+                return None;
+            }
+            match state.inner.next().await {
+                Some(Ok(chunk)) => {
+                    let payloads = state.decoder.feed(chunk.as_ref());
+                    state.push_payloads(payloads);
+                }
+                Some(Err(e)) => {
+                    state.finished = true;
+                    state
+                        .pending
+                        .push_back(Err(OrionAiReason::from(AiErrReason::ExecutionError(
+                            format!("stream read failed: {e}"),
+                        ))
+                        .to_err()));
+                }
+                None => {
+                    let payloads = state.decoder.flush();
+                    state.push_payloads(payloads);
+                    if !state.finished {
+                        state.finish();
+                    }
+                }
+            }
+        }
+    })
+    .boxed()
+}
+
+/// 把完整响应拆成合成事件流，用于不支持流式的提供商
+pub fn stream_from_response(response: AiResponse) -> AiResponseStream {
+    let mut events = Vec::new();
+    if !response.content.is_empty() {
+        events.push(AiStreamEvent::TextDelta(response.content));
+    }
+    for (i, call) in response
+        .tool_calls
+        .unwrap_or_default()
+        .into_iter()
+        .enumerate()
+    {
+        events.push(AiStreamEvent::ToolCallDelta {
+            index: call.index.unwrap_or(i as u32),
+            id: Some(call.id),
+            name: Some(call.function.name),
+            arguments: call.function.arguments,
+        });
+    }
+    events.push(AiStreamEvent::Usage(response.usage));
+    events.push(AiStreamEvent::Finish(response.finish_reason));
+
+    stream::iter(events.into_iter().map(Ok)).boxed()
+}
+
+/// 消费事件流并聚合为完整的 AiResponse
+///
+/// 结束原因的处理与非流式响应一致，输出截断时错误携带部分响应
+pub async fn collect_stream(
+    mut stream: AiResponseStream,
+    provider: AiProviderType,
+    model: &str,
+) -> AiResult<AiResponse> {
+    let mut collector = StreamCollector::new();
+    while let Some(event) = stream.next().await {
+        collector.push(&event?);
+    }
+    check_finish_reason(collector.into_response(provider, model))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::{AiProvider, AiRequest};
+    use crate::providers::mock::MockProvider;
+    use crate::providers::openai::OpenAiProvider;
+    use crate::providers::stub::{StubResponse, StubServer};
+
+    #[test]
+    fn test_sse_decoder_split_chunks() {
+        let mut decoder = SseDecoder::new();
+        assert!(decoder.feed(b"data: {\"a\":").is_empty());
+        let events = decoder.feed(b"1}\n\n: keep-alive\n\ndata: [DONE]\n\n");
+        assert_eq!(events, vec!["{\"a\":1}".to_string(), "[DONE]".to_string()]);
+
+        // 多字节字符被拆在两个分片中
+        let bytes = "data: 你好\n\n".as_bytes();
+        assert!(decoder.feed(&bytes[..8]).is_empty());
+        assert_eq!(decoder.feed(&bytes[8..]), vec!["你好".to_string()]);
+
+        assert!(decoder.feed(b"data: tail").is_empty());
+        assert_eq!(decoder.flush(), vec!["tail".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_openai_sse_stream_text_tools_usage() {
+        let body = [
+            r#"data: {"choices":[{"delta":{"role":"assistant","content":"你"}}]}"#,
+            r#"data: {"choices":[{"delta":{"content":"好"}}]}"#,
+            r#"data: {"choices":[{"delta":{"tool_calls":[{"index":0,"id":"call_1","type":"function","function":{"name":"git-status","arguments":""}}]}}]}"#,
+            r#"data: {"choices":[{"delta":{"tool_calls":[{"index":0,"function":{"arguments":"{\"path\":"}}]}}]}"#,
+            r#"data: {"choices":[{"delta":{"tool_calls":[{"index":0,"function":{"arguments":"\".\"}"}}]}}]}"#,
+            r#"data: {"choices":[{"delta":{},"finish_reason":"tool_calls"}]}"#,
+            r#"data: {"choices":[],"usage":{"prompt_tokens":20,"completion_tokens":7,"total_tokens":27}}"#,
+            "data: [DONE]",
+        ]
+        .join("\n\n")
+            + "\n\n";
+
+        let server = StubServer::start(vec![
+            StubResponse::json(200, body).with_header("Content-Type", "text/event-stream"),
+        ])
+        .await;
+        let provider = OpenAiProvider::deep_seek("test-key".to_string(), 5)
+            .with_base_url(server.base_url().to_string());
+
+        let request = AiRequest::builder()
+            .model("deepseek-chat")
+            .user_prompt("hi")
+            .functions(crate::func::git::create_git_functions())
+            .enable_function_calling(true)
+            .build();
+        let stream = provider.send_request_stream(&request).await.unwrap();
+        let response = collect_stream(stream, AiProviderType::DeepSeek, "deepseek-chat")
+            .await
+            .unwrap();
+
+        assert_eq!(response.content, "你好");
+        assert_eq!(response.finish_reason, Some("tool_calls".to_string()));
+        assert_eq!(response.usage.total_tokens, 27);
+        assert!(response.usage.estimated_cost.is_some());
+        let tool_calls = response.tool_calls.unwrap();
+        assert_eq!(tool_calls.len(), 1);
+        assert_eq!(tool_calls[0].id, "call_1");
+        assert_eq!(tool_calls[0].function.name, "git-status");
+        assert_eq!(tool_calls[0].function.arguments, r#"{"path":"."}"#);
+
+        let sent = server.requests()[0].json();
+        assert_eq!(sent["stream"], true);
+        assert_eq!(sent["stream_options"]["include_usage"], true);
+        assert!(sent["tools"].is_array());
+    }
+
+    #[tokio::test]
+    async fn test_openai_sse_stream_errors_and_truncation() {
+        let sse = |lines: &[&str]| lines.join("\n\n") + "\n\n";
+        let server = StubServer::start(vec![
+            StubResponse::json(
+                200,
+                sse(&[
+                    r#"data: {"choices":[{"delta":{"content":"部分"}}]}"#,
+                    r#"data: {"error":{"code":"1301","message":"内容不安全"}}"#,
+                    r#"data: {"choices":[{"delta":{"content":"不会输出"}}]}"#,
+                ]),
+            ),
+            StubResponse::json(
+                200,
+                sse(&[
+                    r#"data: {"choices":[{"delta":{"content":"未完"}}]}"#,
+                    r#"data: {"choices":[{"delta":{},"finish_reason":"length"}]}"#,
+                    r#"data: {"choices":[],"usage":{"prompt_tokens":5,"completion_tokens":16,"total_tokens":21}}"#,
+                    "data: [DONE]",
+                ]),
+            ),
+        ])
+        .await;
+        let provider = OpenAiProvider::glm("test-key".to_string(), 5)
+            .with_base_url(server.base_url().to_string());
+        let request = AiRequest::builder()
+            .model("glm-4.5")
+            .user_prompt("hi")
+            .build();
+
+        let stream = provider.send_request_stream(&request).await.unwrap();
+        let err = collect_stream(stream, AiProviderType::Glm, "glm-4.5")
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.reason(),
+            OrionAiReason::Ai(AiErrReason::SensitiveContentFiltered)
+        ));
+
+        let stream = provider.send_request_stream(&request).await.unwrap();
+        let err = collect_stream(stream, AiProviderType::Glm, "glm-4.5")
+            .await
+            .unwrap_err();
+        match err.reason() {
+            OrionAiReason::Ai(AiErrReason::OutputTruncated(partial)) => {
+                assert_eq!(partial.content, "未完");
+                assert_eq!(partial.usage.total_tokens, 21);
+            }
+            other => panic!("unexpected reason: {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_synthetic_stream_fallback() {
+        let provider = MockProvider::new();
+        let request = AiRequest::builder()
+            .model("mock")
+            .user_prompt("please run git-status")
+            .functions(crate::func::git::create_git_functions())
+            .enable_function_calling(true)
+            .build();
+
+        let mut stream = provider.send_request_stream(&request).await.unwrap();
+        let mut events = Vec::new();
+        while let Some(event) = stream.next().await {
+            events.push(event.unwrap());
+        }
+
+        assert!(matches!(events[0], AiStreamEvent::TextDelta(_)));
+        assert!(events.iter().any(|e| matches!(
+            e,
+            AiStreamEvent::ToolCallDelta { name: Some(name), .. } if name == "git-status"
+        )));
+        assert!(matches!(events.last(), Some(AiStreamEvent::Finish(_))));
+    }
+}
@@ -1,50 +1,249 @@
-use crate::{AiConfig, provider::AiProviderType};
+use crate::{
+    AiConfig, AiResult, AiRoleID,
+    config::{RouteRule, RoutingRules},
+    error::OrionAiReason,
+    provider::{AiProviderType, AiRequest},
+};
+use orion_error::{ToStructError, UvsConfFrom};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use wildmatch::WildMatch;
 
+/// 提示词超过该字符数时自动归为复杂任务
+pub const COMPLEX_PROMPT_CHARS: usize = 4000;
+/// 工具数量达到该值时自动归为复杂任务
+pub const COMPLEX_TOOL_COUNT: usize = 5;
+
+/// 任务分级，对应 `RoutingRules` 中的 `simple`/`complex`/`free` 模型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskTier {
+    Simple,
+    Complex,
+    Free,
+    /// 按提示词长度和工具数量自动判断 `Simple` 或 `Complex`
+    Auto,
+}
+
+impl TaskTier {
+    /// 按提示词长度和工具数量判断任务复杂度
+    pub fn classify(request: &AiRequest, tool_count: usize) -> TaskTier {
+        let prompt_chars: usize = request
+            .conversation()
+            .iter()
+            .map(|m| m.content.chars().count())
+            .sum();
+        if prompt_chars > COMPLEX_PROMPT_CHARS || tool_count >= COMPLEX_TOOL_COUNT {
+            TaskTier::Complex
+        } else {
+            TaskTier::Simple
+        }
+    }
+
+    /// 解析为具体分级，`Auto` 时按请求内容判断
+    pub fn resolve(self, request: &AiRequest, tool_count: usize) -> TaskTier {
+        match self {
+            TaskTier::Auto => Self::classify(request, tool_count),
+            tier => tier,
+        }
+    }
+
+    /// 该分级在路由配置中对应的模型
+    pub fn model(self, routing: &RoutingRules) -> &str {
+        match self {
+            TaskTier::Simple | TaskTier::Auto => &routing.simple,
+            TaskTier::Complex => &routing.complex,
+            TaskTier::Free => &routing.free,
+        }
+    }
+}
+
+impl std::fmt::Display for TaskTier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TaskTier::Simple => write!(f, "simple"),
+            TaskTier::Complex => write!(f, "complex"),
+            TaskTier::Free => write!(f, "free"),
+            TaskTier::Auto => write!(f, "auto"),
+        }
+    }
+}
+
+/// 编译后的模型匹配规则
+#[derive(Debug, Clone)]
+enum ModelMatcher {
+    Exact(String),
+    Glob(WildMatch),
+    Regex(Regex),
+}
+
+impl ModelMatcher {
+    fn compile(pattern: &str) -> AiResult<Self> {
+        if let Some(re) = pattern.strip_prefix("re:") {
+            Regex::new(re).map(Self::Regex).map_err(|e| {
+                OrionAiReason::from_conf(format!("invalid routing regex `{re}`: {e}")).to_err()
+            })
+        } else if pattern.contains(['*', '?']) {
+            Ok(Self::Glob(WildMatch::new(pattern)))
+        } else {
+            Ok(Self::Exact(pattern.to_string()))
+        }
+    }
+
+    fn matches(&self, model: &str) -> bool {
+        match self {
+            Self::Exact(name) => name == model,
+            Self::Glob(glob) => glob.matches(model),
+            Self::Regex(re) => re.is_match(model),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct CompiledRule {
+    matcher: ModelMatcher,
+    provider: AiProviderType,
+}
+
+impl CompiledRule {
+    fn compile(rule: &RouteRule) -> AiResult<Self> {
+        Ok(Self {
+            matcher: ModelMatcher::compile(&rule.pattern)?,
+            provider: rule.provider,
+        })
+    }
+}
+
+/// 模型路由器
+///
+/// 匹配顺序：显式指定 provider（如 `deepseek/deepseek-chat`）、角色规则、
+/// 配置规则、兼容 provider 的模型前缀、内置规则；都不匹配时返回错误。
 #[derive(Debug, Clone)]
 pub struct AiRouter {
-    rules: HashMap<String, AiProviderType>,
+    rules: Vec<CompiledRule>,
+    role_rules: HashMap<String, Vec<CompiledRule>>,
+    builtin: Vec<CompiledRule>,
 }
 
 impl AiRouter {
     pub fn new() -> Self {
-        let mut rules = HashMap::new();
+        let builtin = Self::builtin_rules()
+            .iter()
+            .map(|rule| CompiledRule::compile(rule).expect("builtin routing rule"))
+            .collect();
 
-        // 默认路由规则
-        rules.insert("simple".to_string(), AiProviderType::OpenAi);
-        rules.insert("complex".to_string(), AiProviderType::OpenAi);
-        rules.insert("free".to_string(), AiProviderType::DeepSeek);
+        Self {
+            rules: Vec::new(),
+            role_rules: HashMap::new(),
+            builtin,
+        }
+    }
 
-        Self { rules }
+    /// 从配置加载路由规则
+    pub fn from_config(config: &AiConfig) -> AiResult<Self> {
+        let mut router = Self::new();
+        router.rules = config
+            .routing
+            .rules
+            .iter()
+            .map(CompiledRule::compile)
+            .collect::<AiResult<_>>()?;
+        router.rules.extend(Self::compat_rules(config)?);
+        for (role, rules) in &config.routing.roles {
+            let compiled = rules
+                .iter()
+                .map(CompiledRule::compile)
+                .collect::<AiResult<_>>()?;
+            router.role_rules.insert(role.clone(), compiled);
+        }
+        Ok(router)
     }
 
-    pub fn select_provider(&self, model_name: &str, _config: &AiConfig) -> AiProviderType {
-        // 简单的路由逻辑：根据模型名前缀选择provider
-        if model_name.starts_with("glm") {
-            AiProviderType::Glm
-        } else if model_name.starts_with("gpt-") {
-            AiProviderType::OpenAi
-        } else if model_name.starts_with("claude") || model_name.starts_with("anthropic") {
-            AiProviderType::Anthropic
-        } else if model_name.starts_with("deepseek") {
-            AiProviderType::DeepSeek
-        } else if model_name.starts_with("mixtral")
-            || model_name.starts_with("llama3")
-            || model_name.starts_with("gemma")
+    /// 已启用 provider 在 `compat.model_prefixes` 中声明的前缀规则
+    fn compat_rules(config: &AiConfig) -> AiResult<Vec<CompiledRule>> {
+        let mut providers: Vec<_> = config
+            .providers
+            .iter()
+            .filter(|(_, provider)| provider.enabled)
+            .filter_map(|(provider_type, provider)| {
+                Some((*provider_type, provider.compat.as_ref()?))
+            })
+            .collect();
+        providers.sort_by_key(|(provider_type, _)| provider_type.to_string());
+        providers
+            .into_iter()
+            .flat_map(|(provider_type, compat)| {
+                compat
+                    .model_prefixes
+                    .iter()
+                    .map(move |prefix| RouteRule::new(format!("{prefix}*"), provider_type))
+            })
+            .map(|rule| CompiledRule::compile(&rule))
+            .collect()
+    }
+
+    /// 内置的模型前缀规则
+    fn builtin_rules() -> Vec<RouteRule> {
+        vec![
+            RouteRule::new("glm*", AiProviderType::Glm),
+            RouteRule::new("gpt-*", AiProviderType::OpenAi),
+            RouteRule::new("claude*", AiProviderType::Anthropic),
+            RouteRule::new("anthropic*", AiProviderType::Anthropic),
+            RouteRule::new("deepseek*", AiProviderType::DeepSeek),
+            RouteRule::new("kimi*", AiProviderType::Kimi),
+            RouteRule::new("moonshot*", AiProviderType::Kimi),
+            RouteRule::new("mixtral*", AiProviderType::Groq),
+            RouteRule::new("llama3*", AiProviderType::Groq),
+            RouteRule::new("gemma*", AiProviderType::Groq),
+            RouteRule::new("codellama*", AiProviderType::Ollama),
+            RouteRule::new("llama*", AiProviderType::Ollama),
+            RouteRule::new("mock*", AiProviderType::Mock),
+        ]
+    }
+
+    /// 解析模型对应的 provider 和实际发送的模型名
+    pub fn route(
+        &self,
+        model_name: &str,
+        role: Option<&AiRoleID>,
+    ) -> AiResult<(AiProviderType, String)> {
+        if let Some((prefix, model)) = model_name.split_once('/')
+            && let Ok(provider) = prefix.parse::<AiProviderType>()
         {
-            AiProviderType::Groq
-        } else if model_name.starts_with("codellama") || model_name.starts_with("llama") {
-            AiProviderType::Ollama
-        } else if model_name.starts_with("mock") {
-            AiProviderType::Mock
-        } else {
-            // 默认使用OpenAI
-            AiProviderType::OpenAi
+            return Ok((provider, model.to_string()));
         }
+
+        let role_rules = role
+            .and_then(|role| self.role_rules.get(role.as_str()))
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+
+        role_rules
+            .iter()
+            .chain(&self.rules)
+            .chain(&self.builtin)
+            .find(|rule| rule.matcher.matches(model_name))
+            .map(|rule| (rule.provider, model_name.to_string()))
+            .ok_or_else(|| {
+                OrionAiReason::from_conf(format!(
+                    "no routing rule matches model `{model_name}`; add one under `routing.rules` \
+                     or pin a provider, e.g. `openai/{model_name}`"
+                ))
+                .to_err()
+            })
     }
 
-    pub fn register_rule(&mut self, category: String, provider: AiProviderType) {
-        self.rules.insert(category, provider);
+    /// 选择模型对应的 provider
+    pub fn select_provider(&self, model_name: &str) -> AiResult<AiProviderType> {
+        self.route(model_name, None).map(|(provider, _)| provider)
+    }
+
+    /// 注册一条规则，优先于已有的配置规则
+    pub fn register_rule(&mut self, pattern: String, provider: AiProviderType) -> AiResult<()> {
+        let rule = CompiledRule::compile(&RouteRule::new(pattern, provider))?;
+        self.rules.insert(0, rule);
+        Ok(())
     }
 }
 
@@ -53,3 +252,182 @@ impl Default for AiRouter {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn router_with(rules: Vec<RouteRule>, roles: HashMap<String, Vec<RouteRule>>) -> AiRouter {
+        let mut config = AiConfig::example();
+        config.routing.rules = rules;
+        config.routing.roles = roles;
+        AiRouter::from_config(&config).unwrap()
+    }
+
+    #[test]
+    fn test_builtin_rules() {
+        let router = AiRouter::new();
+        assert_eq!(
+            router.select_provider("glm-4.5").unwrap(),
+            AiProviderType::Glm
+        );
+        assert_eq!(
+            router.select_provider("deepseek-chat").unwrap(),
+            AiProviderType::DeepSeek
+        );
+        assert_eq!(
+            router.select_provider("kimi-k2-0711-preview").unwrap(),
+            AiProviderType::Kimi
+        );
+        assert_eq!(
+            router.select_provider("llama3-70b-8192").unwrap(),
+            AiProviderType::Groq
+        );
+        assert_eq!(
+            router.select_provider("llama2").unwrap(),
+            AiProviderType::Ollama
+        );
+    }
+
+    #[test]
+    fn test_unknown_model_is_an_error() {
+        let err = AiRouter::new().select_provider("qwen-max").unwrap_err();
+        assert!(err.to_string().contains("qwen-max"));
+    }
+
+    #[test]
+    fn test_explicit_provider_pin() {
+        let router = AiRouter::new();
+        assert_eq!(
+            router.route("deepseek/deepseek-chat", None).unwrap(),
+            (AiProviderType::DeepSeek, "deepseek-chat".to_string())
+        );
+        assert_eq!(
+            router.route("ollama/qwen2.5:7b", None).unwrap(),
+            (AiProviderType::Ollama, "qwen2.5:7b".to_string())
+        );
+        // 前缀不是 provider 名称时按普通模型名匹配
+        assert!(router.route("library/qwen", None).is_err());
+    }
+
+    #[test]
+    fn test_config_and_role_rules() {
+        let router = router_with(
+            vec![
+                RouteRule::new("qwen-max", AiProviderType::OpenAi),
+                RouteRule::new("re:^qwen[0-9.]+:", AiProviderType::Ollama),
+                RouteRule::new("gpt-4o*", AiProviderType::Groq),
+            ],
+            HashMap::from([(
+                "developer".to_string(),
+                vec![RouteRule::new("*", AiProviderType::DeepSeek)],
+            )]),
+        );
+
+        assert_eq!(
+            router.select_provider("qwen-max").unwrap(),
+            AiProviderType::OpenAi
+        );
+        assert_eq!(
+            router.select_provider("qwen2.5:7b").unwrap(),
+            AiProviderType::Ollama
+        );
+        // 配置规则优先于内置规则
+        assert_eq!(
+            router.select_provider("gpt-4o-mini").unwrap(),
+            AiProviderType::Groq
+        );
+        assert_eq!(
+            router.select_provider("gpt-3.5-turbo").unwrap(),
+            AiProviderType::OpenAi
+        );
+
+        let developer = AiRoleID::new("developer");
+        assert_eq!(
+            router.route("gpt-4o", Some(&developer)).unwrap().0,
+            AiProviderType::DeepSeek
+        );
+    }
+
+    #[test]
+    fn test_invalid_regex_rejected() {
+        let mut config = AiConfig::example();
+        config.routing.rules = vec![RouteRule::new("re:(", AiProviderType::OpenAi)];
+        assert!(AiRouter::from_config(&config).is_err());
+
+        let mut router = AiRouter::new();
+        router
+            .register_rule("qwen*".to_string(), AiProviderType::Ollama)
+            .unwrap();
+        assert_eq!(
+            router.select_provider("qwen-max").unwrap(),
+            AiProviderType::Ollama
+        );
+    }
+
+    #[test]
+    fn test_task_tier_classify() {
+        let mut routing = AiConfig::example().routing;
+        routing.simple = "glm-4.5-air".to_string();
+        routing.complex = "glm-4.5".to_string();
+
+        let short = AiRequest::builder().user_prompt("列出当前目录").build();
+        assert_eq!(TaskTier::Auto.resolve(&short, 0), TaskTier::Simple);
+        assert_eq!(
+            TaskTier::Auto.resolve(&short, COMPLEX_TOOL_COUNT),
+            TaskTier::Complex
+        );
+        assert_eq!(TaskTier::Free.resolve(&short, 10), TaskTier::Free);
+
+        let long = AiRequest::builder()
+            .user_prompt("a".repeat(COMPLEX_PROMPT_CHARS + 1))
+            .build();
+        assert_eq!(TaskTier::Auto.resolve(&long, 0), TaskTier::Complex);
+
+        assert_eq!(TaskTier::Simple.model(&routing), "glm-4.5-air");
+        assert_eq!(TaskTier::Complex.model(&routing), "glm-4.5");
+        assert_eq!(TaskTier::Free.model(&routing), routing.free);
+    }
+
+    #[test]
+    fn test_custom_provider_prefixes() {
+        let mut config = AiConfig::example();
+        let vllm = AiProviderType::custom("vllm");
+        config.providers.insert(
+            vllm,
+            crate::config::ProviderConfig {
+                base_url: Some("http://localhost:8000/v1".to_string()),
+                compat: Some(crate::config::CompatConfig {
+                    model_prefixes: vec!["qwen".to_string(), "llama-3".to_string()],
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        );
+        let router = AiRouter::from_config(&config).unwrap();
+
+        assert_eq!(router.select_provider("qwen2.5-7b").unwrap(), vllm);
+        // 兼容 provider 的前缀优先于内置规则
+        assert_eq!(router.select_provider("llama-3-8b").unwrap(), vllm);
+        assert_eq!(
+            router.select_provider("llama2").unwrap(),
+            AiProviderType::Ollama
+        );
+        assert_eq!(
+            router.route("custom:vllm/mistral-7b", None).unwrap(),
+            (vllm, "mistral-7b".to_string())
+        );
+        let lmstudio = AiProviderType::custom("lmstudio");
+        assert_eq!(
+            router.route("custom:lmstudio/phi-3", None).unwrap(),
+            (lmstudio, "phi-3".to_string())
+        );
+        assert!("custom:".parse::<AiProviderType>().is_err());
+
+        // 未声明的自定义 provider 不会被解析，也不会驻留
+        assert!(router.route("custom:undeclared-gw/phi-3", None).is_err());
+        assert!(crate::provider::CustomName::lookup("undeclared-gw").is_none());
+        let name: &'static str = vllm.into();
+        assert_eq!(name, vllm.to_string());
+    }
+}
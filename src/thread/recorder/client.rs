@@ -1,14 +1,18 @@
+use async_trait::async_trait;
 use chrono::Utc;
 use std::sync::Arc;
 
 use super::ThreadFileManager;
+use crate::client::utils::tag_role_response;
 use crate::client::{AiClientTrait, AiCoreClient};
 use crate::config::ThreadConfig;
 use crate::error::AiResult;
-use crate::provider::{AiRequest, AiResponse};
+use crate::provider::{AiRequest, AiResponse, AiResponseStream, FunctionDefinition};
 use crate::roleid::AiRoleID;
 
 /// Thread记录客户端 - 嵌套式静态分发
+///
+/// 对内部客户端透明包装，成功的请求（含工具调用及其结果）记录到Thread文件
 pub struct ThreadClient {
     inner: AiCoreClient,       // 内部是AiClientEnum
     config: Arc<ThreadConfig>, // Thread配置
@@ -26,6 +30,25 @@ impl ThreadClient {
         }
     }
 
+    /// 创建绑定具名会话的Thread记录客户端，交互写入会话存储以便之后恢复
+    pub fn with_session(
+        inner: AiCoreClient,
+        config: ThreadConfig,
+        session_id: &str,
+    ) -> AiResult<Self> {
+        let file_manager = ThreadFileManager::with_session(config.clone(), session_id)?;
+        Ok(Self {
+            inner,
+            config: Arc::new(config),
+            file_manager: Arc::new(file_manager),
+        })
+    }
+
+    /// Thread文件管理器，用于列出、恢复和删除会话
+    pub fn file_manager(&self) -> &ThreadFileManager {
+        &self.file_manager
+    }
+
     /// 检查是否启用Thread记录
     fn is_thread_enabled(&self) -> bool {
         self.config.enabled
@@ -43,17 +66,17 @@ impl ThreadClient {
         request
     }
 
-    /// 发送AI请求
-    pub async fn send_request(&self, request: AiRequest) -> AiResult<AiResponse> {
+    /// 发送请求并记录交互，记录失败只告警
+    async fn send_recorded<F, Fut>(&self, request: AiRequest, send: F) -> AiResult<AiResponse>
+    where
+        F: FnOnce(AiRequest) -> Fut,
+        Fut: Future<Output = AiResult<AiResponse>>,
+    {
         let start_time = Utc::now();
 
         // 如果需要通知AI，构建增强的请求
-        let enhanced_request = if self.config.inform_ai {
-            self.build_request_with_thread_info(request.clone())
-        } else {
-            request.clone()
-        };
-        let response = self.inner.send_request(enhanced_request).await;
+        let enhanced_request = self.build_request_with_thread_info(request.clone());
+        let response = send(enhanced_request).await;
 
         // 如果启用Thread记录且响应成功，则记录交互
         if self.is_thread_enabled()
@@ -68,13 +91,272 @@ impl ThreadClient {
 
         response
     }
+}
+
+#[async_trait]
+impl AiClientTrait for ThreadClient {
+    fn build_ai_request(&self, role: &AiRoleID, user_input: &str) -> AiResult<AiRequest> {
+        self.inner.build_ai_request(role, user_input)
+    }
+
+    /// 发送AI请求
+    async fn send_request(&self, request: AiRequest) -> AiResult<AiResponse> {
+        self.send_recorded(request, |request| self.inner.send_request(request))
+            .await
+    }
+
+    /// 发送带函数定义的请求，每一轮工具调用都记录到Thread文件
+    async fn send_request_with_functions(
+        &self,
+        request: AiRequest,
+        funcs: &[FunctionDefinition],
+    ) -> AiResult<AiResponse> {
+        self.send_recorded(request, |request| {
+            self.inner.send_request_with_functions(request, funcs)
+        })
+        .await
+    }
+
+    /// 发送流式AI请求
+    ///
+    /// 流式响应不会被记录到Thread文件中
+    async fn send_request_stream(&self, request: AiRequest) -> AiResult<AiResponseStream> {
+        let enhanced_request = self.build_request_with_thread_info(request);
+        self.inner.send_request_stream(enhanced_request).await
+    }
 
     /// 基于角色的智能请求处理
-    pub async fn smart_role_request(
+    async fn smart_role_request(&self, role: &AiRoleID, user_input: &str) -> AiResult<AiResponse> {
+        let request = self.inner.build_ai_request(role, user_input)?;
+        let mut response = self
+            .send_recorded(request, |request| self.inner.send_request(request))
+            .await?;
+        tag_role_response(role, &mut response);
+        Ok(response)
+    }
+
+    /// 基于角色的函数调用请求，工具调用记录到Thread文件
+    async fn role_funs_request(
         &self,
-        _role: &AiRoleID,
-        _user_input: &str,
+        role: &AiRoleID,
+        user_input: &str,
+        func: Vec<FunctionDefinition>,
     ) -> AiResult<AiResponse> {
-        todo!();
+        let request = self.inner.build_ai_request(role, user_input)?;
+        let mut response = self
+            .send_recorded(request, |request| {
+                self.inner.send_request_with_functions(request, &func)
+            })
+            .await?;
+        tag_role_response(role, &mut response);
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AiConfig;
+    use crate::FunctionExecutor;
+    use crate::client::AiClientBuilder;
+    use crate::config::ProviderConfig;
+    use crate::exec_unit::AiExecUnit;
+    use crate::func::git::create_git_functions;
+    use crate::func::registry::FunctionRegistry;
+    use crate::provider::{AiProviderType, ChatMessage, FunctionCall, FunctionResult};
+    use orion_error::TestAssertWithMsg;
+    use std::io::Write;
+    use tempfile::{NamedTempFile, TempDir};
+
+    fn mock_thread_client(storage: &TempDir, role_file: &NamedTempFile) -> ThreadClient {
+        let mut config = AiConfig::example();
+        for provider_config in config.providers.values_mut() {
+            provider_config.enabled = false;
+        }
+        config.providers.insert(
+            AiProviderType::Mock,
+            ProviderConfig {
+                api_key: String::new(),
+                ..Default::default()
+            },
+        );
+        let client = AiClientBuilder::new(config)
+            .with_role(role_file.path().to_path_buf())
+            .build()
+            .assert("ai-client new");
+        let thread_config = ThreadConfig {
+            enabled: true,
+            storage_path: storage.path().to_path_buf(),
+            filename_template: "thread-YYYY-MM-DD.md".to_string(),
+            ..Default::default()
+        };
+        ThreadClient::new(AiCoreClient::Basic(client), thread_config)
+    }
+
+    fn write_role_file() -> NamedTempFile {
+        let mut role_file = NamedTempFile::new().unwrap();
+        write!(
+            role_file,
+            "default_role:\n  id: developer\ndefault_model: mock-gpt\nroles:\n  developer:\n    name: developer\n    description: 开发者\n    system_prompt: 你是开发者\n"
+        )
+        .unwrap();
+        role_file
+    }
+
+    #[tokio::test]
+    async fn test_role_requests_are_recorded() {
+        let storage = TempDir::new().unwrap();
+        let role_file = write_role_file();
+        let client = mock_thread_client(&storage, &role_file);
+        let role = AiRoleID::new("developer");
+
+        let response = client
+            .smart_role_request(&role, "你好")
+            .await
+            .assert("smart role request");
+        assert!(response.content.starts_with("[角色: "));
+
+        let response = client
+            .role_funs_request(&role, "git-status", create_git_functions())
+            .await
+            .assert("role funs request");
+        let calls = response.tool_calls.clone().unwrap();
+        assert_eq!(calls[0].function.name, "git-status");
+
+        // 提交工具结果继续对话
+        let mut request = AiRequest::builder()
+            .model("mock-gpt")
+            .user_prompt("git-status")
+            .build();
+        let result = FunctionResult {
+            name: "git-status".to_string(),
+            result: serde_json::json!({"status": "clean"}),
+            error: None,
+        };
+        request.push_tool_round(&response, &[(calls[0].clone(), result)]);
+        client
+            .send_request_with_functions(request, &create_git_functions())
+            .await
+            .assert("tool round");
+
+        let file = std::fs::read_dir(storage.path())
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path();
+        let content = std::fs::read_to_string(file).unwrap();
+        assert!(content.contains("## 交互记录 3"));
+        assert!(content.contains("**角色**: developer"));
+        assert!(content.contains("### 工具调用\n- `git-status`: {\"path\":\".\"}"));
+        assert!(content.contains("### 工具结果\n- `git-status`: {\"status\":\"clean\"}"));
+    }
+
+    #[tokio::test]
+    async fn test_exec_unit_tool_loop_is_recorded() {
+        let storage = TempDir::new().unwrap();
+        let role_file = write_role_file();
+        let client = mock_thread_client(&storage, &role_file);
+        let mut registry = FunctionRegistry::new();
+        registry
+            .register_functions(create_git_functions())
+            .assert("register functions");
+        registry
+            .register_executor("git-status".to_string(), Arc::new(GitStatusStub))
+            .assert("register executor");
+
+        let unit = AiExecUnit::new(client, AiRoleID::new("developer"), registry);
+        let result = unit
+            .execute_with_func("git-status")
+            .await
+            .assert("tool loop");
+        assert_eq!(result.rounds.len(), 2);
+
+        let file = std::fs::read_dir(storage.path())
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path();
+        let content = std::fs::read_to_string(file).unwrap();
+        assert!(content.contains("## 交互记录 2"));
+        assert!(content.contains("### 工具结果\n- `git-status`: {\"status\":\"clean\"}"));
+    }
+
+    struct GitStatusStub;
+
+    #[async_trait]
+    impl FunctionExecutor for GitStatusStub {
+        async fn execute(&self, function_call: &FunctionCall) -> AiResult<FunctionResult> {
+            Ok(FunctionResult {
+                name: function_call.function.name.clone(),
+                result: serde_json::json!({"status": "clean"}),
+                error: None,
+            })
+        }
+
+        fn supported_functions(&self) -> Vec<String> {
+            vec!["git-status".to_string()]
+        }
+
+        fn get_function_schema(&self, _function_name: &str) -> Option<FunctionDefinition> {
+            None
+        }
+    }
+
+    #[tokio::test]
+    async fn test_session_resume() {
+        let storage = TempDir::new().unwrap();
+        let role_file = write_role_file();
+        let basic = mock_thread_client(&storage, &role_file);
+        let thread_config = (*basic.config).clone();
+
+        let client = ThreadClient::with_session(basic.inner, thread_config.clone(), "review-1")
+            .assert("session client");
+        let first = AiRequest::builder()
+            .model("mock-gpt")
+            .system_prompt("你是开发者".to_string())
+            .user_prompt("第一轮")
+            .build();
+        let response = client.send_request(first).await.assert("first turn");
+
+        // 新的客户端接续同一会话
+        let mock = mock_thread_client(&storage, &role_file);
+        let client = ThreadClient::with_session(mock.inner, thread_config, "review-1")
+            .assert("resume client");
+        let manager = client.file_manager();
+        let next = AiRequest::builder()
+            .model("mock-gpt")
+            .user_prompt("第二轮")
+            .build();
+        let resumed = manager.resume_session("review-1", next).assert("resume");
+        assert_eq!(
+            resumed.messages,
+            vec![
+                ChatMessage::user("第一轮"),
+                ChatMessage::assistant(response.content.clone()),
+            ]
+        );
+        client.send_request(resumed).await.assert("second turn");
+
+        let sessions = manager.list_sessions().assert("list sessions");
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].id, "review-1");
+        assert_eq!(sessions[0].interactions, 2);
+        let records = manager.load_session("review-1").assert("load session");
+        assert_eq!(records[1].seq, 2);
+        assert_eq!(
+            manager
+                .load_session_messages("review-1")
+                .assert("messages")
+                .len(),
+            4
+        );
+
+        assert!(manager.delete_session("review-1").assert("delete"));
+        assert!(!manager.delete_session("review-1").assert("delete again"));
+        assert!(manager.list_sessions().assert("list").is_empty());
+        assert!(manager.load_session("review-1").is_err());
+        assert!(manager.load_session("../etc").is_err());
     }
 }
@@ -1,20 +1,35 @@
+use std::collections::HashMap;
+use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicUsize, Ordering};
 
 use chrono::{DateTime, Utc};
-use tokio::fs::OpenOptions;
-use tokio::io::AsyncWriteExt;
+use log::warn;
+use orion_error::ToStructError;
+use serde::{Deserialize, Serialize};
 
-use super::SummaryExtractor;
-use crate::config::ThreadConfig;
+use super::record::{ThreadSession, conversation_from_records};
+use super::{SummaryExtractor, ThreadRecord};
+use crate::config::{JsonlRotation, ThreadConfig};
 use crate::error::{AiErrReason, AiResult, OrionAiReason};
-use crate::provider::{AiRequest, AiResponse};
+use crate::provider::{AiRequest, AiResponse, ChatMessage, ChatRole};
+
+/// Markdown 中每条交互记录的标题前缀
+const RECORD_HEADING: &str = "## 交互记录 ";
+
+/// 只读取记录所属会话，用于统计会话内已有的交互数
+#[derive(Deserialize)]
+struct RecordSession {
+    session_id: String,
+}
 
 /// Thread文件管理器，负责记录交互到文件
+///
+/// 交互序号在文件锁内由文件现有内容推导，多个进程或管理器写同一文件时不会重复
 pub struct ThreadFileManager {
     config: std::sync::Arc<ThreadConfig>,
-    interaction_counter: AtomicUsize, // 交互计数器
-    base_path: PathBuf,               // 基础路径
+    base_path: PathBuf,  // 基础路径
+    session_id: String,  // 会话标识
+    named_session: bool, // 是否为具名会话
 }
 
 impl ThreadFileManager {
@@ -23,38 +38,76 @@ impl ThreadFileManager {
 
         Self {
             config: std::sync::Arc::new(config),
-            interaction_counter: AtomicUsize::new(1),
             base_path,
+            session_id: format!(
+                "{}-{}",
+                Utc::now().format("%Y%m%d-%H%M%S"),
+                std::process::id()
+            ),
+            named_session: false,
         }
     }
 
-    /// 记录一次AI交互
+    /// 绑定到具名会话，已有记录时接续其交互序号
+    ///
+    /// 具名会话总是写入会话存储 `sessions/<id>.jsonl`，以便之后恢复
+    pub fn with_session(config: ThreadConfig, session_id: &str) -> AiResult<Self> {
+        Self::validate_session_id(session_id)?;
+        let mut manager = Self::new(config);
+        manager.session_id = session_id.to_string();
+        manager.named_session = true;
+        Ok(manager)
+    }
+
+    /// 当前会话标识，JSONL 记录按会话切分时用作文件名
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    /// 记录一次AI交互，按 `ThreadConfig.format` 写入 Markdown 和/或 JSONL
+    ///
+    /// Markdown 标题序号为该文件内的交互序号，JSONL 的 `seq` 为会话内的交互序号
     pub async fn record_interaction(
         &self,
         timestamp: DateTime<Utc>,
         request: &AiRequest,
         response: &AiResponse,
     ) -> AiResult<()> {
-        // 1. 生成今日文件路径
-        let file_path = self.generate_daily_file_path(&timestamp);
-
-        // 2. 确保目录存在
-        self.ensure_directory_exists(&file_path)?;
-
-        // 3. 提取总结性内容
-        let summary_content = self.extract_summary_content(&response.content);
-
-        // 4. 格式化记录内容
-        let interaction_number = self.interaction_counter.fetch_add(1, Ordering::SeqCst);
-        let record_content = self.format_interaction_record(
-            timestamp,
-            interaction_number,
-            request,
-            &summary_content,
-        );
+        let format = self.config.format;
+
+        if format.writes_markdown() {
+            let file_path = self.generate_daily_file_path(&timestamp);
+            let summary_content = self.extract_summary_content(&response.content);
+            let body =
+                self.format_interaction_record(timestamp, request, response, &summary_content);
+            let title = format!("# Thread记录 - {}\n\n", timestamp.format("%Y-%m-%d"));
+
+            append_locked(
+                file_path,
+                String::new(),
+                scan_markdown_records,
+                move |number, empty| {
+                    let title = if empty { title.as_str() } else { "" };
+                    Ok(format!("{title}{RECORD_HEADING}{number}\n{body}"))
+                },
+            )
+            .await?;
+        }
 
-        // 5. 追加写入文件
-        self.append_to_file(&file_path, &record_content).await
+        if format.writes_jsonl() || self.named_session {
+            let record = ThreadRecord {
+                session_id: self.session_id.clone(),
+                seq: 0,
+                timestamp: timestamp.to_rfc3339(),
+                latency_ms: (Utc::now() - timestamp).num_milliseconds().max(0) as u64,
+                request: request.clone(),
+                response: response.clone(),
+            };
+            record
+                .append_jsonl(&self.generate_jsonl_file_path(&timestamp))
+                .await?;
+        }
+        Ok(())
     }
 
     /// 解析存储路径中的环境变量
@@ -89,18 +142,119 @@ impl ThreadFileManager {
         self.base_path.join(filename)
     }
 
-    /// 确保目录存在
-    fn ensure_directory_exists(&self, file_path: &Path) -> AiResult<()> {
-        if let Some(parent) = file_path.parent() {
-            std::fs::create_dir_all(parent).map_err(|e| {
-                OrionAiReason::from(AiErrReason::ContextError(format!(
-                    "Failed to create directory {}: {}",
-                    parent.display(),
-                    e
-                )))
-            })?;
+    /// 生成 JSONL 文件路径：具名会话或按会话切分时写入会话存储，否则按天切分
+    fn generate_jsonl_file_path(&self, timestamp: &DateTime<Utc>) -> PathBuf {
+        if self.named_session || self.config.jsonl_rotation == JsonlRotation::Session {
+            return self.session_file_path(&self.session_id);
         }
-        Ok(())
+        let date_str = timestamp.format("%Y-%m-%d").to_string();
+        let filename = self
+            .config
+            .filename_template
+            .replace("YYYY-MM-DD", &date_str);
+        let stem = filename.strip_suffix(".md").unwrap_or(&filename);
+        self.base_path.join(format!("{stem}.jsonl"))
+    }
+
+    /// 会话存储目录
+    pub fn sessions_dir(&self) -> PathBuf {
+        self.base_path.join("sessions")
+    }
+
+    fn session_file_path(&self, session_id: &str) -> PathBuf {
+        self.sessions_dir().join(format!("{session_id}.jsonl"))
+    }
+
+    /// 会话标识只允许字母、数字、`-`、`_` 和 `.`，避免越出会话目录
+    fn validate_session_id(session_id: &str) -> AiResult<()> {
+        let valid = !session_id.is_empty()
+            && !session_id.starts_with('.')
+            && session_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+        if valid {
+            Ok(())
+        } else {
+            Err(OrionAiReason::from(AiErrReason::InvalidInput(format!(
+                "invalid session id: {session_id}"
+            )))
+            .to_err())
+        }
+    }
+
+    /// 列出会话存储中的全部会话，最近活跃的在前
+    pub fn list_sessions(&self) -> AiResult<Vec<ThreadSession>> {
+        let dir = self.sessions_dir();
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+        let entries = std::fs::read_dir(&dir).map_err(|e| {
+            OrionAiReason::from(AiErrReason::ContextError(format!(
+                "Failed to read directory {}: {}",
+                dir.display(),
+                e
+            )))
+        })?;
+        let mut sessions = Vec::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == "jsonl")
+                && let Some(id) = path.file_stem().and_then(|stem| stem.to_str())
+            {
+                // 单个会话文件损坏不影响列出其他会话
+                match ThreadRecord::read_jsonl(&path) {
+                    Ok(records) => sessions.push(ThreadSession::from_records(id, &records)),
+                    Err(e) => warn!("skip session {id}: {e}"),
+                }
+            }
+        }
+        sessions.sort_by(|a, b| b.updated_at.cmp(&a.updated_at).then(a.id.cmp(&b.id)));
+        Ok(sessions)
+    }
+
+    /// 读取会话的全部记录
+    pub fn load_session(&self, session_id: &str) -> AiResult<Vec<ThreadRecord>> {
+        Self::validate_session_id(session_id)?;
+        let path = self.session_file_path(session_id);
+        if !path.exists() {
+            return Err(OrionAiReason::from(AiErrReason::InvalidInput(format!(
+                "session not found: {session_id}"
+            )))
+            .to_err());
+        }
+        ThreadRecord::read_jsonl(&path)
+    }
+
+    /// 将会话记录还原为对话消息
+    pub fn load_session_messages(&self, session_id: &str) -> AiResult<Vec<ChatMessage>> {
+        Ok(conversation_from_records(&self.load_session(session_id)?))
+    }
+
+    /// 将会话历史载入新请求，置于请求已有消息之前
+    pub fn resume_session(&self, session_id: &str, mut request: AiRequest) -> AiResult<AiRequest> {
+        let records = self.load_session(session_id)?;
+        let mut messages = conversation_from_records(&records);
+        messages.append(&mut request.messages);
+        request.messages = messages;
+        Ok(request)
+    }
+
+    /// 删除会话，返回会话是否存在
+    pub fn delete_session(&self, session_id: &str) -> AiResult<bool> {
+        Self::validate_session_id(session_id)?;
+        let path = self.session_file_path(session_id);
+        if !path.exists() {
+            return Ok(false);
+        }
+        std::fs::remove_file(&path).map_err(|e| {
+            OrionAiReason::from(AiErrReason::ContextError(format!(
+                "Failed to delete session {}: {}",
+                path.display(),
+                e
+            )))
+        })?;
+        let _ = std::fs::remove_file(seq_index_path(&path));
+        Ok(true)
     }
 
     /// 提取总结性内容
@@ -113,12 +267,12 @@ impl ThreadFileManager {
         )
     }
 
-    /// 格式化交互记录
+    /// 格式化交互记录，不含标题行
     fn format_interaction_record(
         &self,
         timestamp: DateTime<Utc>,
-        interaction_number: usize,
         request: &AiRequest,
+        response: &AiResponse,
         summary_content: &str,
     ) -> String {
         let role_str = request
@@ -126,65 +280,175 @@ impl ThreadFileManager {
             .as_ref()
             .map_or("None".to_string(), |r| r.to_string());
 
-        format!(
-            "## 交互记录 {}\n**时间**: {}\n**模型**: {}\n**角色**: {}\n\n### 用户请求\n```text\n{}```\n\n### AI响应（总结）\n{}\n\n",
-            interaction_number,
+        let mut record = format!(
+            "**会话**: {}\n**时间**: {}\n**模型**: {}\n**角色**: {}\n\n",
+            self.session_id,
             timestamp.format("%Y-%m-%d %H:%M:%S"),
             request.model,
             role_str,
-            request.user_prompt,
-            summary_content
-        )
+        );
+        if !request.user_prompt.is_empty() {
+            record.push_str(&format!(
+                "### 用户请求\n```text\n{}```\n\n",
+                request.user_prompt
+            ));
+        }
+
+        // 本轮提交的工具结果：最后一条助手消息之后的工具消息
+        let tool_results: Vec<_> = request
+            .messages
+            .iter()
+            .rev()
+            .take_while(|m| m.role != ChatRole::Assistant)
+            .filter(|m| m.role == ChatRole::Tool)
+            .collect();
+        if !tool_results.is_empty() {
+            record.push_str("### 工具结果\n");
+            for message in tool_results.into_iter().rev() {
+                record.push_str(&format!(
+                    "- `{}`: {}\n",
+                    message.name.as_deref().unwrap_or("unknown"),
+                    message.content
+                ));
+            }
+            record.push('\n');
+        }
+
+        record.push_str(&format!("### AI响应（总结）\n{summary_content}\n\n"));
+
+        if let Some(tool_calls) = response.tool_calls.as_ref().filter(|c| !c.is_empty()) {
+            record.push_str("### 工具调用\n");
+            for call in tool_calls {
+                record.push_str(&format!(
+                    "- `{}`: {}\n",
+                    call.function.name, call.function.arguments
+                ));
+            }
+            record.push('\n');
+        }
+        record
     }
+}
 
-    /// 异步追加内容到文件
-    async fn append_to_file(&self, path: &Path, content: &str) -> AiResult<()> {
-        let file_exists = path.exists();
+/// 序号索引旁路文件的内容
+///
+/// `len` 为写入索引时数据文件的长度，不一致说明文件被外部修改或上次写入中断，需要重建
+#[derive(Serialize, Deserialize)]
+struct SeqIndex {
+    len: u64,
+    seq: HashMap<String, usize>,
+}
 
-        let mut file = OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(path)
-            .await
-            .map_err(|e| {
-                OrionAiReason::from(AiErrReason::ContextError(format!(
-                    "Failed to open file {}: {}",
-                    path.display(),
-                    e
-                )))
-            })?;
-
-        // 如果文件是刚创建的，添加标题
-        if !file_exists {
-            let date_str = chrono::Utc::now().format("%Y-%m-%d").to_string();
-            let title = format!("# Thread记录 - {date_str}\n\n");
-            file.write_all(title.as_bytes()).await.map_err(|e| {
-                OrionAiReason::from(AiErrReason::ContextError(format!(
-                    "Failed to write title to file {}: {}",
-                    path.display(),
-                    e
-                )))
-            })?;
-        }
-
-        file.write_all(content.as_bytes()).await.map_err(|e| {
-            OrionAiReason::from(AiErrReason::ContextError(format!(
-                "Failed to write to file {}: {}",
-                path.display(),
-                e
-            )))
-        })?;
+/// 数据文件对应的序号索引，如 `.2024-01-01.jsonl.seq`
+fn seq_index_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{name}.seq"))
+}
 
-        file.flush().await.map_err(|e| {
+/// 在排他文件锁内追加 `render` 生成的内容，返回为 `key` 分配的序号
+///
+/// 各键的最新序号保存在序号索引中，追加时不必重读整个文件；索引缺失或与文件长度不符时
+/// 才读取文件由 `scan` 重建。`render` 接收新序号以及文件原本是否为空。
+/// 目录不存在时自动创建；锁在文件关闭时释放
+pub(crate) async fn append_locked<S, F>(
+    path: PathBuf,
+    key: String,
+    scan: S,
+    render: F,
+) -> AiResult<usize>
+where
+    S: FnOnce(&str) -> HashMap<String, usize> + Send + 'static,
+    F: FnOnce(usize, bool) -> AiResult<String> + Send + 'static,
+{
+    tokio::task::spawn_blocking(move || {
+        let context_err = |action: &str, e: std::io::Error| {
             OrionAiReason::from(AiErrReason::ContextError(format!(
-                "Failed to flush file {}: {}",
-                path.display(),
-                e
+                "Failed to {action} {}: {e}",
+                path.display()
             )))
-        })?;
+            .to_err()
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| context_err("create directory for", e))?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&path)
+            .map_err(|e| context_err("open file", e))?;
+        file.lock().map_err(|e| context_err("lock file", e))?;
+
+        let len = file
+            .metadata()
+            .map_err(|e| context_err("read metadata of", e))?
+            .len();
+        let index_path = seq_index_path(&path);
+        let cached = std::fs::read(&index_path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<SeqIndex>(&bytes).ok())
+            .filter(|index| index.len == len);
+        let mut index = match cached {
+            Some(index) => index,
+            None => {
+                let mut existing = String::new();
+                file.read_to_string(&mut existing)
+                    .map_err(|e| context_err("read file", e))?;
+                SeqIndex {
+                    len,
+                    seq: scan(&existing),
+                }
+            }
+        };
 
-        Ok(())
+        let seq = index.seq.get(&key).copied().unwrap_or(0) + 1;
+        let content = render(seq, len == 0)?;
+        file.write_all(content.as_bytes())
+            .map_err(|e| context_err("write to file", e))?;
+        file.flush().map_err(|e| context_err("flush file", e))?;
+
+        // 索引写入失败时下次追加会重建，不影响本次记录
+        index.len = len + content.len() as u64;
+        index.seq.insert(key, seq);
+        let written = serde_json::to_vec(&index)
+            .map_err(std::io::Error::other)
+            .and_then(|bytes| std::fs::write(&index_path, bytes));
+        if let Err(e) = written {
+            warn!("failed to update {}: {e}", index_path.display());
+        }
+        Ok(seq)
+    })
+    .await
+    .map_err(|e| {
+        OrionAiReason::from(AiErrReason::ContextError(format!(
+            "thread record task failed: {e}"
+        )))
+        .to_err()
+    })?
+}
+
+/// 统计 Markdown 中已有的交互记录数，整个文件共用一个序号
+fn scan_markdown_records(existing: &str) -> HashMap<String, usize> {
+    let count = existing
+        .lines()
+        .filter(|line| line.starts_with(RECORD_HEADING))
+        .count();
+    HashMap::from([(String::new(), count)])
+}
+
+/// 按会话统计 JSONL 中已有的记录数，无法解析的行不计入
+pub(crate) fn scan_jsonl_records(existing: &str) -> HashMap<String, usize> {
+    let mut counts = HashMap::new();
+    for record in existing
+        .lines()
+        .filter_map(|line| serde_json::from_str::<RecordSession>(line).ok())
+    {
+        *counts.entry(record.session_id).or_insert(0) += 1;
     }
+    counts
 }
 
 #[cfg(test)]
@@ -206,6 +470,7 @@ mod tests {
             summary_keywords: vec!["总结".to_string()],
             inform_ai: false,
             inform_message: "".to_string(),
+            ..Default::default()
         };
 
         let file_manager = ThreadFileManager::new(config);
@@ -250,4 +515,220 @@ mod tests {
         // 检查是否包含总结内容，不要求完全匹配
         assert!(content.contains("总结") || content.contains("测试成功"));
     }
+
+    #[tokio::test]
+    async fn test_jsonl_records() {
+        use crate::config::ThreadFormat;
+        use crate::provider::{FunctionCall, FunctionCallInfo};
+
+        let temp_dir = TempDir::new().unwrap();
+        let config = ThreadConfig {
+            enabled: true,
+            storage_path: temp_dir.path().to_path_buf(),
+            format: ThreadFormat::Jsonl,
+            jsonl_rotation: JsonlRotation::Session,
+            ..Default::default()
+        };
+        let file_manager = ThreadFileManager::new(config);
+        let timestamp = Utc::now();
+
+        let request = AiRequest::builder()
+            .model("mock-gpt")
+            .user_prompt("git-status")
+            .build();
+        let response = AiResponse {
+            content: String::new(),
+            model: "mock-gpt".to_string(),
+            usage: crate::provider::UsageInfo {
+                prompt_tokens: 12,
+                completion_tokens: 8,
+                total_tokens: 20,
+                estimated_cost: Some(0.002),
+            },
+            finish_reason: Some("tool_calls".to_string()),
+            provider: crate::provider::AiProviderType::Mock,
+            metadata: std::collections::HashMap::new(),
+            tool_calls: Some(vec![FunctionCall {
+                index: Some(0),
+                id: "call_1".to_string(),
+                r#type: "function".to_string(),
+                function: FunctionCallInfo {
+                    name: "git-status".to_string(),
+                    arguments: "{}".to_string(),
+                },
+            }]),
+        };
+        for _ in 0..2 {
+            file_manager
+                .record_interaction(timestamp, &request, &response)
+                .await
+                .unwrap();
+        }
+
+        // 只写 JSONL，按会话切分时写入会话存储
+        let entries: Vec<_> = std::fs::read_dir(file_manager.sessions_dir())
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "jsonl"))
+            .collect();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(std::fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+        let expected = file_manager
+            .sessions_dir()
+            .join(format!("{}.jsonl", file_manager.session_id()));
+        assert_eq!(entries[0], expected);
+
+        let records = ThreadRecord::read_jsonl(&expected).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].seq, 1);
+        assert_eq!(records[1].seq, 2);
+        assert_eq!(records[0].session_id, file_manager.session_id());
+        assert_eq!(records[0].request.user_prompt, "git-status");
+        assert_eq!(records[0].response.usage.total_tokens, 20);
+        assert_eq!(records[0].response.usage.estimated_cost, Some(0.002));
+        assert_eq!(
+            records[0].response.tool_calls.as_ref().unwrap()[0]
+                .function
+                .name,
+            "git-status"
+        );
+    }
+
+    fn mock_response() -> AiResponse {
+        AiResponse {
+            content: "完成".to_string(),
+            model: "mock-gpt".to_string(),
+            usage: crate::provider::UsageInfo {
+                prompt_tokens: 1,
+                completion_tokens: 1,
+                total_tokens: 2,
+                estimated_cost: None,
+            },
+            finish_reason: Some("stop".to_string()),
+            provider: crate::provider::AiProviderType::Mock,
+            metadata: std::collections::HashMap::new(),
+            tool_calls: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_managers_keep_sequence_unique() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = ThreadConfig {
+            enabled: true,
+            storage_path: temp_dir.path().to_path_buf(),
+            filename_template: "shared-YYYY-MM-DD.md".to_string(),
+            ..Default::default()
+        };
+        // 两个管理器同时写同一具名会话和同一份每日 Markdown
+        let first = ThreadFileManager::with_session(config.clone(), "shared").unwrap();
+        let second = ThreadFileManager::with_session(config.clone(), "shared").unwrap();
+        let unnamed = ThreadFileManager::new(config);
+        let timestamp = Utc::now();
+        let request = AiRequest::builder()
+            .model("mock-gpt")
+            .user_prompt("hi")
+            .build();
+        let response = mock_response();
+
+        let writes = (0..4).map(|i| {
+            let manager = [&first, &second][i % 2];
+            manager.record_interaction(timestamp, &request, &response)
+        });
+        for result in futures_util::future::join_all(writes).await {
+            result.unwrap();
+        }
+        unnamed
+            .record_interaction(timestamp, &request, &response)
+            .await
+            .unwrap();
+
+        let mut seqs: Vec<usize> = first
+            .load_session("shared")
+            .unwrap()
+            .iter()
+            .map(|r| r.seq)
+            .collect();
+        seqs.sort();
+        assert_eq!(seqs, vec![1, 2, 3, 4]);
+
+        let date_str = timestamp.format("%Y-%m-%d").to_string();
+        let markdown =
+            std::fs::read_to_string(temp_dir.path().join(format!("shared-{date_str}.md"))).unwrap();
+        assert_eq!(markdown.matches("# Thread记录").count(), 1);
+        assert!(markdown.contains("## 交互记录 5\n"));
+        assert!(!markdown.contains("## 交互记录 6"));
+    }
+
+    #[tokio::test]
+    async fn test_list_sessions_skips_corrupt_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = ThreadConfig {
+            enabled: true,
+            storage_path: temp_dir.path().to_path_buf(),
+            ..Default::default()
+        };
+        let manager = ThreadFileManager::with_session(config, "good").unwrap();
+        let request = AiRequest::builder()
+            .model("mock-gpt")
+            .user_prompt("hi")
+            .build();
+        manager
+            .record_interaction(Utc::now(), &request, &mock_response())
+            .await
+            .unwrap();
+        std::fs::write(manager.sessions_dir().join("broken.jsonl"), "{not json\n").unwrap();
+
+        let sessions = manager.list_sessions().unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].id, "good");
+        assert_eq!(sessions[0].interactions, 1);
+    }
+
+    #[tokio::test]
+    async fn test_seq_index_rebuilt_when_stale() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = ThreadConfig {
+            enabled: true,
+            storage_path: temp_dir.path().to_path_buf(),
+            ..Default::default()
+        };
+        let manager = ThreadFileManager::with_session(config, "indexed").unwrap();
+        let request = AiRequest::builder()
+            .model("mock-gpt")
+            .user_prompt("hi")
+            .build();
+        let record = || manager.record_interaction(Utc::now(), &request, &mock_response());
+        record().await.unwrap();
+        record().await.unwrap();
+
+        let path = manager.session_file_path("indexed");
+        let index = seq_index_path(&path);
+        assert!(index.exists());
+
+        // 索引缺失时从文件重建
+        std::fs::remove_file(&index).unwrap();
+        record().await.unwrap();
+        // 文件被外部追加后索引长度不符，同样重建
+        let first_line = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .next()
+            .unwrap()
+            .to_string();
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        writeln!(file, "{first_line}").unwrap();
+        record().await.unwrap();
+
+        let seqs: Vec<usize> = manager
+            .load_session("indexed")
+            .unwrap()
+            .iter()
+            .map(|r| r.seq)
+            .collect();
+        assert_eq!(seqs, vec![1, 2, 3, 1, 5]);
+    }
 }
@@ -1,7 +1,9 @@
 pub mod client;
 pub mod file_manager;
+pub mod record;
 pub mod summary_extractor;
 
 pub use client::ThreadClient;
 pub use file_manager::ThreadFileManager;
+pub use record::{ThreadRecord, ThreadSession};
 pub use summary_extractor::SummaryExtractor;
@@ -0,0 +1,134 @@
+//! JSONL 格式的 Thread 记录
+//!
+//! 每行一条 [`ThreadRecord`]，保留完整的请求和响应，便于分析与回放。
+
+use std::path::Path;
+
+use orion_error::ToStructError;
+use serde::{Deserialize, Serialize};
+
+use super::file_manager::{append_locked, scan_jsonl_records};
+use crate::error::{AiErrReason, AiResult, OrionAiReason};
+use crate::provider::{AiRequest, AiResponse, ChatMessage};
+
+/// 一次交互的完整记录
+///
+/// 用量与费用在 `response.usage`，工具调用在 `response.tool_calls`，
+/// 本轮提交的工具结果在 `request.messages` 中
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThreadRecord {
+    pub session_id: String,
+    /// 会话内的交互序号，从1开始
+    pub seq: usize,
+    /// 请求开始时间（RFC 3339）
+    pub timestamp: String,
+    /// 请求耗时（毫秒）
+    pub latency_ms: u64,
+    pub request: AiRequest,
+    pub response: AiResponse,
+}
+
+impl ThreadRecord {
+    /// 读取 JSONL 文件中的全部记录，忽略空行
+    pub fn read_jsonl(path: &Path) -> AiResult<Vec<ThreadRecord>> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            OrionAiReason::from(AiErrReason::ContextError(format!(
+                "Failed to read thread log {}: {}",
+                path.display(),
+                e
+            )))
+            .to_err()
+        })?;
+        content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| {
+                serde_json::from_str(line).map_err(|e| {
+                    OrionAiReason::from(AiErrReason::ContextError(format!(
+                        "Invalid thread record at {}:{}: {}",
+                        path.display(),
+                        index + 1,
+                        e
+                    )))
+                    .to_err()
+                })
+            })
+            .collect()
+    }
+
+    /// 在文件锁内追加为 JSONL 文件中的一行，目录不存在时自动创建
+    ///
+    /// `seq` 按文件中该会话已有的记录数分配，多个进程写同一文件时不会重复；返回分配的序号
+    pub async fn append_jsonl(mut self, path: &Path) -> AiResult<usize> {
+        let session_id = self.session_id.clone();
+        append_locked(
+            path.to_path_buf(),
+            session_id,
+            scan_jsonl_records,
+            move |seq, _| {
+                self.seq = seq;
+                let line = serde_json::to_string(&self).map_err(|e| {
+                    OrionAiReason::from(AiErrReason::ContextError(format!(
+                        "Failed to serialize thread record: {e}"
+                    )))
+                    .to_err()
+                })?;
+                Ok(format!("{line}\n"))
+            },
+        )
+        .await
+    }
+}
+
+/// 会话概要
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ThreadSession {
+    pub id: String,
+    /// 交互次数
+    pub interactions: usize,
+    /// 首次交互时间（RFC 3339）
+    pub started_at: String,
+    /// 最近交互时间（RFC 3339）
+    pub updated_at: String,
+}
+
+impl ThreadSession {
+    pub fn from_records(id: &str, records: &[ThreadRecord]) -> Self {
+        Self {
+            id: id.to_string(),
+            interactions: records.len(),
+            started_at: records
+                .first()
+                .map(|r| r.timestamp.clone())
+                .unwrap_or_default(),
+            updated_at: records
+                .last()
+                .map(|r| r.timestamp.clone())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// 由记录还原对话历史
+///
+/// 请求已携带之前的历史时只追加新增部分，随后依次追加用户输入和助手回复
+pub fn conversation_from_records(records: &[ThreadRecord]) -> Vec<ChatMessage> {
+    let mut history: Vec<ChatMessage> = Vec::new();
+    for record in records {
+        let messages = &record.request.messages;
+        if messages.starts_with(&history) {
+            history.extend_from_slice(&messages[history.len()..]);
+        } else {
+            history.extend_from_slice(messages);
+        }
+        if !record.request.user_prompt.is_empty() {
+            history.push(ChatMessage::user(record.request.user_prompt.clone()));
+        }
+        history.push(ChatMessage::assistant_with_tool_calls(
+            record.response.content.clone(),
+            record.response.tool_calls.clone().unwrap_or_default(),
+        ));
+    }
+    history
+}
@@ -0,0 +1,98 @@
+//! token 数量估算
+//!
+//! 不依赖具体模型的分词器：ASCII 字符按 4 个字符 1 个 token 计，
+//! 其他字符（中文等）按 1 个字符 1 个 token 计，结果偏保守。
+
+use crate::provider::{AiRequest, ChatMessage, FunctionDefinition};
+
+/// 每条消息的固定开销（角色标记等）
+pub const MESSAGE_OVERHEAD: usize = 4;
+/// 每个请求的固定开销
+pub const REQUEST_OVERHEAD: usize = 3;
+
+/// 估算文本的 token 数
+pub fn estimate_text_tokens(text: &str) -> usize {
+    let (ascii, other) = text.chars().fold((0, 0), |(ascii, other), c| {
+        if c.is_ascii() {
+            (ascii + 1, other)
+        } else {
+            (ascii, other + 1)
+        }
+    });
+    ascii.div_ceil(4) + other
+}
+
+/// 估算单条消息的 token 数，包括工具调用参数
+pub fn estimate_message_tokens(message: &ChatMessage) -> usize {
+    let tool_calls: usize = message
+        .tool_calls
+        .iter()
+        .flatten()
+        .map(|call| {
+            estimate_text_tokens(&call.function.name)
+                + estimate_text_tokens(&call.function.arguments)
+        })
+        .sum();
+    MESSAGE_OVERHEAD + estimate_text_tokens(&message.content) + tool_calls
+}
+
+/// 估算工具定义（名称、描述和参数 schema）的 token 数
+pub fn estimate_functions_tokens(functions: &[FunctionDefinition]) -> usize {
+    functions
+        .iter()
+        .map(|f| {
+            estimate_text_tokens(&f.name)
+                + estimate_text_tokens(&f.description)
+                + estimate_text_tokens(&f.parameters_schema().to_string())
+        })
+        .sum()
+}
+
+/// 估算请求的提示词 token 数
+///
+/// 包括系统提示词（已含角色规则）、对话历史、用户输入和工具定义，不含 `max_tokens`。
+pub fn estimate_request_tokens(request: &AiRequest, functions: &[FunctionDefinition]) -> usize {
+    let messages: usize = request
+        .conversation()
+        .iter()
+        .map(estimate_message_tokens)
+        .sum();
+    REQUEST_OVERHEAD + messages + estimate_functions_tokens(functions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FunctionParameter;
+
+    #[test]
+    fn test_estimate_text_tokens() {
+        assert_eq!(estimate_text_tokens(""), 0);
+        assert_eq!(estimate_text_tokens("abcd"), 1);
+        assert_eq!(estimate_text_tokens("abcde"), 2);
+        assert_eq!(estimate_text_tokens("你好世界"), 4);
+        assert_eq!(estimate_text_tokens("hi 你好"), 3);
+    }
+
+    #[test]
+    fn test_estimate_request_tokens() {
+        let request = AiRequest::builder()
+            .system_prompt("abcdabcd")
+            .user_prompt("你好")
+            .build();
+        assert_eq!(
+            estimate_request_tokens(&request, &[]),
+            REQUEST_OVERHEAD + (MESSAGE_OVERHEAD + 2) * 2
+        );
+
+        let functions = vec![FunctionDefinition::new(
+            "git-status",
+            "查看状态",
+            vec![FunctionParameter::new("path", "string", "路径", false)],
+        )];
+        assert!(
+            estimate_request_tokens(&request, &functions)
+                > estimate_request_tokens(&request, &[]) + estimate_text_tokens("查看状态")
+        );
+    }
+}
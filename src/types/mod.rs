@@ -6,11 +6,11 @@
 pub mod result;
 
 // 重新导出主要类型，便于使用
-pub use result::{ExecutionResult, ExecutionResultBuilder, ExecutionStatus};
+pub use result::{ExecutionResult, ExecutionResultBuilder, ExecutionRound, ExecutionStatus};
 
 /// 预导入的常用类型和trait
 pub mod prelude {
-    pub use super::{ExecutionResult, ExecutionResultBuilder, ExecutionStatus};
+    pub use super::{ExecutionResult, ExecutionResultBuilder, ExecutionRound, ExecutionStatus};
 }
 
 #[cfg(test)]
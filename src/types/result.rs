@@ -2,7 +2,7 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-use crate::provider::FunctionResult;
+use crate::provider::{FunctionCall, FunctionResult, UsageInfo};
 
 /// 简化的执行结果类型
 ///
@@ -20,6 +20,24 @@ pub struct ExecutionResult {
     pub status: ExecutionStatus,
     /// 额外的元数据
     pub metadata: HashMap<String, String>,
+    /// 每一轮模型交互的记录
+    #[serde(default)]
+    pub rounds: Vec<ExecutionRound>,
+}
+
+/// 单轮模型交互记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionRound {
+    /// 轮次，从1开始
+    pub round: usize,
+    /// 本轮模型输出的文本
+    pub content: String,
+    /// 本轮模型发起的工具调用
+    pub tool_calls: Vec<FunctionCall>,
+    /// 本轮工具调用的执行结果
+    pub tool_results: Vec<FunctionResult>,
+    /// 本轮用量
+    pub usage: UsageInfo,
 }
 
 /// 执行状态枚举
@@ -52,6 +70,7 @@ impl ExecutionResult {
             timestamp: Utc::now(),
             status: ExecutionStatus::Success,
             metadata: HashMap::new(),
+            rounds: Vec::new(),
         }
     }
 
@@ -75,6 +94,17 @@ impl ExecutionResult {
         self
     }
 
+    /// 设置轮次记录
+    pub fn with_rounds(mut self, rounds: Vec<ExecutionRound>) -> Self {
+        self.rounds = rounds;
+        self
+    }
+
+    /// 所有轮次的累计token用量
+    pub fn total_tokens(&self) -> usize {
+        self.rounds.iter().map(|r| r.usage.total_tokens).sum()
+    }
+
     /// 判断是否成功
     pub fn is_success(&self) -> bool {
         matches!(self.status, ExecutionStatus::Success)
@@ -152,6 +182,7 @@ impl From<ExecutionResult> for serde_json::Value {
             "timestamp": result.timestamp.to_rfc3339(),
             "status": format!("{:?}", result.status),
             "metadata": result.metadata,
+            "rounds": result.rounds,
         })
     }
 }
@@ -242,7 +273,7 @@ impl Default for ExecutionResultBuilder {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::provider::FunctionResult;
+    use crate::provider::{FunctionCall, FunctionResult, UsageInfo};
 
     #[test]
     fn test_execution_result_creation() {
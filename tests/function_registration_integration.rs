@@ -12,6 +12,7 @@ async fn test_dynamic_function_registration() {
         name: "test-custom-function".to_string(),
         description: "Test custom function".to_string(),
         parameters: vec![],
+        schema: None,
     };
 
     // 测试注册函数
@@ -36,6 +37,7 @@ async fn test_mixed_builtin_and_custom_tools() {
         name: "mixed-custom-tool".to_string(),
         description: "Custom tool for mixed test".to_string(),
         parameters: vec![],
+        schema: None,
     };
 
     struct MixedToolExecutor;
@@ -60,6 +62,7 @@ async fn test_mixed_builtin_and_custom_tools() {
                     name: "mixed-custom-tool".to_string(),
                     description: "Custom tool for mixed test".to_string(),
                     parameters: vec![],
+                    schema: None,
                 })
             } else {
                 None
@@ -114,6 +117,7 @@ async fn test_concurrent_registration() {
                 name: function_name.clone(),
                 description: format!("Concurrent test tool {}", i),
                 parameters: vec![],
+                schema: None,
             };
 
             struct ConcurrentExecutor {
@@ -140,6 +144,7 @@ async fn test_concurrent_registration() {
                             name: self.function_name.clone(),
                             description: format!("Concurrent test tool for {}", function_name),
                             parameters: vec![],
+                            schema: None,
                         })
                     } else {
                         None
@@ -224,16 +229,19 @@ async fn test_tool_set_integration() {
                     name: "integration-tool-1".to_string(),
                     description: "Integration tool 1".to_string(),
                     parameters: vec![],
+                    schema: None,
                 }),
                 "integration-tool-2" => Some(FunctionDefinition {
                     name: "integration-tool-2".to_string(),
                     description: "Integration tool 2".to_string(),
                     parameters: vec![],
+                    schema: None,
                 }),
                 "integration-tool-3" => Some(FunctionDefinition {
                     name: "integration-tool-3".to_string(),
                     description: "Integration tool 3".to_string(),
                     parameters: vec![],
+                    schema: None,
                 }),
                 _ => None,
             }
@@ -245,16 +253,19 @@ async fn test_tool_set_integration() {
             name: "integration-tool-1".to_string(),
             description: "Integration tool 1".to_string(),
             parameters: vec![],
+            schema: None,
         },
         FunctionDefinition {
             name: "integration-tool-2".to_string(),
             description: "Integration tool 2".to_string(),
             parameters: vec![],
+            schema: None,
         },
         FunctionDefinition {
             name: "integration-tool-3".to_string(),
             description: "Integration tool 3".to_string(),
             parameters: vec![],
+            schema: None,
         },
     ];
 